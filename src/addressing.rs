@@ -1,86 +1,148 @@
-use crate::system::Core;
-
-/* Probably not needed just here in case
-pub fn immediate(core: Core) -> u8 {
+use crate::{instruction::Mode, system::Core};
+
+/*
+    Effective addresses for the 6502 and 65C02 addressing modes.
+
+    `resolve()` turns the operand bytes of the instruction at PC into where
+    the instruction works, for any mode, so the loads, stores and
+    read-modify-write opcodes can all share it instead of each spelling out
+    its own modes. Immediates resolve to the address of the byte after the
+    opcode, which lets them be read like any other memory operand.
+
+    Everything indexed or indirect through the zero page stays in the zero
+    page: ZPX and ZPY wrap the index around, and (zp,X), (zp),Y and (zp)
+    take the pointer's high byte from $00 when the low byte sits at $FF.
+
+    The 65816 has its own 24-bit resolver in w65816.rs.
+*/
+
+// Where an instruction's operand is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Target {
+    Implied, // No operand
+    Accumulator, // The accumulator itself, for the shifts and rotates
+    Address(u16), // An effective address in memory
 }
-pub fn accumulator(core: Core) -> u8 {
-}*/
 
-pub fn zero_page(core: &mut Core) -> u8 {
-    core.memory[core.pc as usize + 1]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Resolved {
+    pub target: Target,
+    pub page_crossed: bool, // Indexing or a branch landed in another page
 }
 
-pub fn zero_page_x(core: &mut Core) -> u8 {
-    core.memory[(core.pc as usize + 1).wrapping_add(core.ix as usize)]
-}
+// Little endian word from two addresses, which needn't be next to each other.
+fn word(core: &mut Core, low: u16, high: u16) -> u16 {
+    let low: u8 = core.read(low);
+    let high: u8 = core.read(high);
 
-pub fn zero_page_y(core: &mut Core) -> u8 {
-    core.memory[(core.pc as usize + 1).wrapping_add(core.iy as usize)]
+    ((high as u16) << 8) | (low as u16)
 }
 
-pub fn absolute(core: &mut Core) -> u16 {
-    let pcl: u8 = core.memory[core.pc as usize + 1];
-    let pch: u8 = core.memory[core.pc as usize + 2];
-
-    ((pch as u16) << 8) | (pcl as u16)
+// Pointer held in the zero page, wrapping within it.
+fn zero_page_word(core: &mut Core, zp: u8) -> u16 {
+    word(core, zp as u16, zp.wrapping_add(1) as u16)
 }
 
-pub fn absolute_x(core: &mut Core) -> u16 {
-    let pcl: u8 = core.memory[core.pc as usize + 1];
-    let pch: u8 = core.memory[core.pc as usize + 2];
+// Adds an index, noting whether the result is in a different page.
+fn indexed(base: u16, index: u8) -> (u16, bool) {
+    let address: u16 = base.wrapping_add(index as u16);
 
-    ((pch as u16) << 8) | (pcl as u16) + core.ix as u16
+    (address, (base & 0xFF00) != (address & 0xFF00))
 }
 
-pub fn absolute_y(core: &mut Core) -> u16 {
-    let pcl: u8 = core.memory[core.pc as usize + 1];
-    let pch: u8 = core.memory[core.pc as usize + 2];
-
-    ((pch as u16) << 8) | (pcl as u16) + core.iy as u16
+// Works out the operand of the instruction at PC for the given mode.
+pub fn resolve(core: &mut Core, mode: Mode) -> Resolved {
+    let operand: u16 = core.pc.wrapping_add(1);
+
+    let (address, page_crossed): (u16, bool) = match mode {
+        Mode::Implied => return Resolved { target: Target::Implied, page_crossed: false },
+        Mode::Accumulator => return Resolved { target: Target::Accumulator, page_crossed: false },
+        Mode::Immediate | Mode::ImmediateM | Mode::ImmediateX => (operand, false),
+        Mode::ZeroPage | Mode::ZeroPageRelative => (core.read(operand) as u16, false),
+        Mode::ZeroPageX => (core.read(operand).wrapping_add(core.ix) as u16, false),
+        Mode::ZeroPageY => (core.read(operand).wrapping_add(core.iy) as u16, false),
+        Mode::Absolute => (word(core, operand, operand.wrapping_add(1)), false),
+        Mode::AbsoluteX => {
+            let base: u16 = word(core, operand, operand.wrapping_add(1));
+            indexed(base, core.ix)
+        }
+        Mode::AbsoluteY => {
+            let base: u16 = word(core, operand, operand.wrapping_add(1));
+            indexed(base, core.iy)
+        }
+        Mode::IndexedIndirect => {
+            let zp: u8 = core.read(operand).wrapping_add(core.ix);
+            (zero_page_word(core, zp), false)
+        }
+        Mode::IndirectIndexed => {
+            let zp: u8 = core.read(operand);
+            let pointer: u16 = zero_page_word(core, zp);
+            indexed(pointer, core.iy)
+        }
+        Mode::ZeroPageIndirect => {
+            let zp: u8 = core.read(operand);
+            (zero_page_word(core, zp), false)
+        }
+        // JMP (ind). The NMOS parts don't carry into the high byte of the
+        // pointer, so a pointer at $xxFF takes its high byte from $xx00. The
+        // 65C02 fixed this, at the cost of an extra cycle.
+        Mode::Indirect => {
+            let pointer: u16 = word(core, operand, operand.wrapping_add(1));
+
+            let high: u16 = if core.variant.is_cmos() {
+                pointer.wrapping_add(1)
+            } else {
+                (pointer & 0xFF00) | (pointer as u8).wrapping_add(1) as u16
+            };
+
+            (word(core, pointer, high), false)
+        }
+        Mode::AbsoluteIndexedIndirect => {
+            let pointer: u16 = word(core, operand, operand.wrapping_add(1)).wrapping_add(core.ix as u16);
+            (word(core, pointer, pointer.wrapping_add(1)), false)
+        }
+        // The branch target. The offset is sign extended, so wrapping
+        // addition handles negative offsets.
+        Mode::Relative => {
+            let next: u16 = core.pc.wrapping_add(2);
+            let target: u16 = next.wrapping_add(core.read(operand) as i8 as u16);
+            (target, (next & 0xFF00) != (target & 0xFF00))
+        }
+        _ => unreachable!("{:?} is a 65816 mode", mode)
+    };
+
+    Resolved { target: Target::Address(address), page_crossed }
 }
 
-pub fn indirect(core: &mut Core) -> u16 {
-    // Get the low and high bytes of the pointer and combine them
-    let ptl: u8 = core.memory[core.pc as usize + 1];
-    let pth: u8 = core.memory[core.pc as usize + 2];
-
-    let pointer: u16 = ((pth as u16) << 8) | (ptl as u16);
+// Resolves the operand of the instruction being run, from its mode in the
+// opcode table, and flags a page crossing for the cycle count.
+pub fn operand(core: &mut Core) -> Target {
+    let mode: Mode = core.info.expect("Decoded before running").mode;
+    let resolved: Resolved = resolve(core, mode);
 
-    // Get the low and high bytes of the actual target and combine them
-    let tl: u8 = core.memory[pointer as usize];
-    let th: u8 = core.memory[(pointer + 1) as usize];
+    core.page_crossed = resolved.page_crossed;
 
-    ((th as u16) << 8) | (tl as u16)
+    resolved.target
 }
 
-pub fn x_indirect(core: &mut Core) -> u8 {
-    let zpx: u8 = core.memory[core.pc as usize + 1].wrapping_add(core.ix);
-
-    // Get the low and high bytes of the pointer and combine them
-    let ptl: u8 = core.memory[zpx as usize];
-    let pth: u8 = core.memory[(zpx + 1) as usize];
-
-    let pointer: u16 = ((pth as u16) << 8) | (ptl as u16);
-
-    // Get the low and high bytes of the actual target and combine them
-    let tl: u8 = core.memory[pointer as usize];
-    let th: u8 = core.memory[(pointer + 1) as usize];
-
-    core.memory[(((th as u16) << 8) | (tl as u16)) as usize]
+// Effective address of the instruction being run, for modes that have one.
+pub fn operand_address(core: &mut Core) -> u16 {
+    match operand(core) {
+        Target::Address(address) => address,
+        target => unreachable!("{:?} for {:?}", target, core.info)
+    }
 }
 
-pub fn indirect_y(core: &mut Core) -> u8 {
-    let zp: u8 = core.memory[core.pc as usize + 1];
-
-    // Get the low and high bytes of the pointer and combine them
-    let ptl: u8 = core.memory[zp as usize];
-    let pth: u8 = core.memory[(zp + 1) as usize];
-
-    let pointer: u16 = ((pth as u16) << 8) | (ptl as u16);
-    
-    core.memory[pointer.wrapping_add(core.iy as u16) as usize]
+// Reads the operand of the instruction being run.
+pub fn read_operand(core: &mut Core) -> u8 {
+    match operand(core) {
+        Target::Accumulator => core.acc,
+        Target::Address(address) => core.read(address),
+        Target::Implied => unreachable!("{:?}", core.info)
+    }
 }
 
-pub fn relative(core: &mut Core) -> i8 {
-    core.memory[(core.pc as usize) + 1] as i8
-}
\ No newline at end of file
+// Length of the instruction being run, for moving PC past it.
+pub fn length(core: &Core) -> u16 {
+    core.info.map_or(1, |info| info.length as u16)
+}
@@ -0,0 +1,155 @@
+use crate::{
+    error::EmulatorError,
+    savestate::{StateReader, StateWriter},
+};
+
+/*
+    The bus the core talks to for every memory access.
+
+    Opcodes, addressing modes, the stack and the interrupt vectors all go
+    through `Core::read()` and `Core::write()`, which hand the access to
+    whatever `Bus` the core was built with. That could be plain RAM, ROM,
+    mirrored regions or memory-mapped hardware, anything that wants to see
+    or react to the accesses a program makes. The bus is also ticked every
+    clock cycle and can drive the interrupt lines, see devices.rs for a bus
+    with hardware mapped onto it.
+
+    Debugging views such as memory dumps, and the bus cycle model in cycles.rs
+    while it works out an instruction's accesses, look at memory through
+    `peek()` instead, which mustn't have side effects.
+    A device register that clears itself when read shouldn't be cleared by
+    the debugger looking at it.
+
+    Any part of the address space can also be marked as ROM on the core,
+    whatever the bus behind it is. CPU writes to ROM never reach the bus.
+    They're either dropped like they would be on real hardware, or trapped
+    as a fault so a debugger can stop on the instruction that made them.
+    Loading an image with `Core::load()` isn't a CPU write, so it still
+    works on ROM.
+
+    Buses with state of their own, memory or otherwise, save and restore it
+    for save states through `save()` and `restore()`, see savestate.rs. The
+    defaults save nothing, which suits a bus with nothing to keep.
+
+    Going back through the history, see history.rs, doesn't restore a whole
+    state for every step. It takes the byte each CPU write lands in from
+    `stored()`, before and after the write, and puts it back later with
+    `put_back()`, which no device sees. Everything else the bus keeps, like
+    mapper and device registers, is taken before every step through
+    `save_registers()`, so it should stay small. A bus that doesn't override
+    these is left as it is when going back, the same as with save states.
+*/
+
+pub trait Bus {
+    // A read made by the CPU.
+    fn read(&mut self, address: u16) -> u8;
+
+    // A write made by the CPU.
+    fn write(&mut self, address: u16, value: u8);
+
+    // Look at an address without the CPU reading it.
+    fn peek(&self, address: u16) -> u8;
+
+    // Called once for every clock cycle, for hardware that runs alongside the core.
+    fn tick(&mut self) {}
+
+    // True while something on the bus is holding the IRQ line.
+    fn irq(&self) -> bool {
+        false
+    }
+
+    // True while something on the bus is holding the NMI line.
+    fn nmi(&self) -> bool {
+        false
+    }
+
+    // Write whatever the bus needs to carry on where it was into a save state.
+    fn save(&self, _state: &mut StateWriter) {}
+
+    // Read back what `save()` wrote.
+    fn restore(&mut self, _state: &mut StateReader) -> Result<(), EmulatorError> {
+        Ok(())
+    }
+
+    // The byte of memory a write to the address lands in. None where writes
+    // don't land in memory, like device and mapper registers.
+    fn stored(&self, _address: u16) -> Option<u8> {
+        None
+    }
+
+    // Put back a byte from `stored()`, without it being a write.
+    fn put_back(&mut self, _address: u16, _value: u8) {}
+
+    // The part of `save()` that isn't memory, like mapper and device registers.
+    fn save_registers(&self, _state: &mut StateWriter) {}
+
+    // Read back what `save_registers()` wrote.
+    fn restore_registers(&mut self, _state: &mut StateReader) -> Result<(), EmulatorError> {
+        Ok(())
+    }
+}
+
+// What happens when the CPU writes to a ROM region.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RomWrites {
+    #[default]
+    Ignore, // The write is dropped
+    Trap, // The write is dropped and recorded as a fault
+}
+
+// A trapped write to ROM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WriteFault {
+    pub address: u16,
+    pub value: u8,
+    pub pc: u16, // PC of the instruction making the write
+}
+
+// Plain 64kb of RAM filling the whole address space, what the core has by default.
+pub struct Ram {
+    pub memory: [u8; 65536], // 64kb of memory
+}
+
+impl Default for Ram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ram {
+    pub fn new() -> Self {
+        Self { memory: [0; 65536] }
+    }
+}
+
+impl Bus for Ram {
+    fn read(&mut self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.memory[address as usize] = value;
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        self.memory[address as usize]
+    }
+
+    fn save(&self, state: &mut StateWriter) {
+        state.bytes(&self.memory);
+    }
+
+    fn restore(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        self.memory.copy_from_slice(state.bytes(65536)?);
+
+        Ok(())
+    }
+
+    fn stored(&self, address: u16) -> Option<u8> {
+        Some(self.memory[address as usize])
+    }
+
+    fn put_back(&mut self, address: u16, value: u8) {
+        self.memory[address as usize] = value;
+    }
+}
@@ -0,0 +1,135 @@
+use crate::{
+    bus::Bus,
+    error::EmulatorError,
+    system::{power_on, read_binary, run_step, Core, Event, Variant},
+    table::{gen_table, OpcodeTable},
+    trace::Tracer,
+};
+
+/*
+    The CPU as a library.
+
+    `Cpu` bundles a core with the opcode table for its variant, so it can be
+    driven from other programs without going through the shell. Build one,
+    load a program, and then either step it an instruction at a time or run
+    it for a number of cycles or until something happens. The core is public,
+    so registers, memory and the interrupt lines are all there to be looked at
+    or changed between calls.
+
+    Errors are returned rather than panicking, whatever the program being run
+    does. A trapped ROM write is reported after the instruction that made it
+    has finished, see `EmulatorError`.
+
+    With a `Tracer` set, every instruction run through any of these writes a
+    line to the trace, see trace.rs.
+*/
+
+// What a single call to `Cpu::step()` did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub pc: u16, // PC before the step, where the instruction was fetched from
+    pub event: Event, // Whether an instruction, an interrupt or nothing ran
+    pub cycles: u64, // Clock cycles the step took
+}
+
+pub struct Cpu {
+    pub core: Core,
+    table: OpcodeTable,
+    tracer: Option<Tracer>,
+}
+
+impl Cpu {
+    // A powered on CPU with 64kb of RAM. PC comes from the reset vector, so
+    // it'll want setting after a program is loaded unless the vector was.
+    pub fn new(variant: Variant) -> Self {
+        Self::with_core(Core::new(), variant)
+    }
+
+    // A powered on CPU wired up to the given bus.
+    pub fn with_bus(variant: Variant, bus: Box<dyn Bus>) -> Self {
+        Self::with_core(Core::with_bus(bus), variant)
+    }
+
+    fn with_core(core: Core, variant: Variant) -> Self {
+        Self { core: power_on(core, variant), table: gen_table(variant), tracer: None }
+    }
+
+    // Load a block of bytes, wrapping at the top of memory.
+    pub fn load(&mut self, start: u16, data: &[u8]) {
+        self.core.load(start, data);
+    }
+
+    // Load a block of bytes and mark it as ROM.
+    pub fn load_rom(&mut self, start: u16, data: &[u8]) {
+        self.core.load_rom(start, data);
+    }
+
+    // Load a binary file, which has to fit in memory from the start address.
+    pub fn load_file(&mut self, path: &str, start: u16, rom: bool) -> Result<(), EmulatorError> {
+        let data: Vec<u8> = read_binary(path, start)?;
+
+        if rom { self.load_rom(start, &data) }
+        else { self.load(start, &data) }
+
+        Ok(())
+    }
+
+    // Save the whole machine to a file, see savestate.rs.
+    pub fn save(&self, path: &str) -> Result<(), EmulatorError> {
+        self.core.save_file(path)
+    }
+
+    // Carry on from a file written by `save()`, on a CPU built the same way.
+    pub fn restore(&mut self, path: &str) -> Result<(), EmulatorError> {
+        self.core.restore_file(path)
+    }
+
+    // Start tracing every instruction, or stop with None. The old tracer is
+    // flushed and handed back.
+    pub fn set_tracer(&mut self, tracer: Option<Tracer>) -> Result<Option<Tracer>, EmulatorError> {
+        let mut old: Option<Tracer> = std::mem::replace(&mut self.tracer, tracer);
+
+        if let Some(old) = &mut old { old.flush()? }
+
+        Ok(old)
+    }
+
+    // Run one instruction, or the interrupt sequence taking its place.
+    pub fn step(&mut self) -> Result<Step, EmulatorError> {
+        let pc: u16 = self.core.pc;
+        let before: u64 = self.core.cycles;
+
+        let event: Event = match &mut self.tracer {
+            Some(tracer) => tracer.step(&mut self.core, &self.table)?,
+            None => run_step(&mut self.core, &self.table)?,
+        };
+
+        Ok(Step { pc, event, cycles: self.core.cycles - before })
+    }
+
+    // Run whole instructions until at least the given number of cycles have
+    // gone by, returning how many did. The last instruction can run over,
+    // and a halted core stops short as nothing more will happen until RESET.
+    pub fn run_for_cycles(&mut self, cycles: u64) -> Result<u64, EmulatorError> {
+        let before: u64 = self.core.cycles;
+
+        while self.core.cycles - before < cycles {
+            if self.step()?.event == Event::Halted { break }
+        }
+
+        Ok(self.core.cycles - before)
+    }
+
+    // Step until the predicate holds for the core after a step, returning
+    // that step. A halted core stops here too, rather than running forever.
+    pub fn run_until<F>(&mut self, mut predicate: F) -> Result<Step, EmulatorError>
+    where
+        F: FnMut(&Core) -> bool,
+    {
+        loop {
+            let step: Step = self.step()?;
+
+            if step.event == Event::Halted || predicate(&self.core) { return Ok(step) }
+        }
+    }
+}
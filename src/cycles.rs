@@ -0,0 +1,552 @@
+use crate::{
+    error::EmulatorError,
+    instruction::{Instruction, Mnemonic::{self, *}, Mode},
+    status::{Flag, Status},
+    system::{Core, Variant},
+    table::OpcodeTable,
+};
+
+use std::collections::VecDeque;
+
+/*
+    Cycle by cycle bus model for the 6502 family.
+
+    Every cycle of a 6502 is a bus access, including the ones where it has
+    nothing useful to do. Those show up as dummy reads of whatever address is
+    on the bus at the time, and read-modify-write instructions on the NMOS
+    parts write the unmodified value back before writing the result. Hardware
+    mapped into memory sees all of these, so they matter.
+
+    The opcode functions still do all their work in one go. Before an
+    instruction runs, `plan()` works out the accesses it makes from the
+    addressing mode and the kind of instruction, following the tables in
+    64doc. Each access is either one the opcode function makes itself, or a
+    dummy that only exists on the bus. While the instruction runs, the plan
+    is replayed alongside it: when the opcode function makes its next access,
+    the dummies planned before it are made on the bus first. Whatever dummies
+    are left at the end are made by `finish()`. Every access goes through the
+    bus in the order the hardware makes them, with the values that were
+    actually read or driven, and the NMOS read-modify-write writes back
+    whatever it read.
+
+    An access the plan doesn't expect is still made and handed out, it just
+    can't put any dummies in front of it. The tests check every opcode lines
+    up with its plan.
+
+    The 65816 isn't modelled, so stepping it by cycle is refused.
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+}
+
+// A single bus cycle, as seen on the address and data pins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BusCycle {
+    pub address: u16,
+    pub value: u8,
+    pub access: Access,
+}
+
+// An access worked out before the instruction runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Planned {
+    Read(u16), // Made by the opcode function
+    Write(u16), // Made by the opcode function
+    Dummy(u16), // A read only the bus sees
+    WriteBack(u16), // NMOS read-modify-write writing the value it read back
+}
+
+impl Planned {
+    fn is_dummy(&self) -> bool {
+        matches!(self, Planned::Dummy(_) | Planned::WriteBack(_))
+    }
+}
+
+// How an instruction uses its operand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Class {
+    Read,
+    Write,
+    Modify,
+    Ignore, // NOPs work out the address but don't read it
+}
+
+fn class(mnemonic: Mnemonic) -> Class {
+    match mnemonic {
+        Nop => Class::Ignore,
+        Sta | Stx | Sty | Stz | Sax | Sha | Shx | Shy | Tas => Class::Write,
+        Asl | Lsr | Rol | Ror | Inc | Dec | Tsb | Trb
+        | Slo | Rla | Sre | Rra | Dcp | Isc => Class::Modify,
+        _ => Class::Read,
+    }
+}
+
+fn stack(sp: u8) -> u16 {
+    0x0100 | sp as u16
+}
+
+struct Plan<'a> {
+    core: &'a Core,
+    cmos: bool,
+    accesses: Vec<Planned>,
+}
+
+impl Plan<'_> {
+    // Reads return what's there now, for working out the later addresses.
+    fn read(&mut self, address: u16) -> u8 {
+        self.accesses.push(Planned::Read(address));
+
+        self.core.peek(address)
+    }
+
+    fn dummy(&mut self, address: u16) -> u8 {
+        self.accesses.push(Planned::Dummy(address));
+
+        self.core.peek(address)
+    }
+
+    fn write(&mut self, address: u16) {
+        self.accesses.push(Planned::Write(address));
+    }
+
+    // Address of the byte following the opcode.
+    fn operand(&self, offset: u16) -> u16 {
+        self.core.pc.wrapping_add(offset)
+    }
+
+    fn word(&mut self, low: u16, high: u16) -> u16 {
+        let low: u8 = self.read(low);
+        let high: u8 = self.read(high);
+
+        ((high as u16) << 8) | low as u16
+    }
+
+    // RESET runs the interrupt sequence with the stack writes turned into reads.
+    fn reset(&mut self) {
+        let (pc, sp) = (self.core.pc, self.core.sp);
+
+        self.dummy(pc);
+        self.dummy(pc);
+
+        for offset in 0..3 {
+            self.dummy(stack(sp.wrapping_sub(offset)));
+        }
+
+        self.word(0xFFFC, 0xFFFD);
+    }
+
+    // IRQ and NMI fetch the next opcode, throw it away, then push PC and status.
+    fn interrupt(&mut self, vector: u16) {
+        let (pc, sp) = (self.core.pc, self.core.sp);
+
+        self.dummy(pc);
+        self.dummy(pc);
+
+        for offset in 0..3 {
+            self.write(stack(sp.wrapping_sub(offset)));
+        }
+
+        self.word(vector, vector + 1);
+    }
+
+    fn instruction(&mut self, info: Instruction) {
+        let (mnemonic, mode) = (info.mnemonic, info.mode);
+        let (pc, sp) = (self.core.pc, self.core.sp);
+
+        let opcode: u8 = self.read(pc);
+
+        match mnemonic {
+            Brk => {
+                self.dummy(self.operand(1));
+
+                for offset in 0..3 {
+                    self.write(stack(sp.wrapping_sub(offset)));
+                }
+
+                self.word(0xFFFE, 0xFFFF);
+            }
+            Jsr => {
+                // The low byte of the target is held while PC is pushed.
+                self.read(self.operand(1));
+                self.dummy(stack(sp));
+                self.write(stack(sp));
+                self.write(stack(sp.wrapping_sub(1)));
+                self.read(self.operand(2));
+            }
+            Rts => {
+                self.dummy(self.operand(1));
+                self.dummy(stack(sp));
+
+                let target: u16 = self.word(stack(sp.wrapping_add(1)), stack(sp.wrapping_add(2)));
+
+                // PC is incremented past the JSR's last byte with a dummy read.
+                self.dummy(target);
+            }
+            Rti => {
+                self.dummy(self.operand(1));
+                self.dummy(stack(sp));
+
+                for offset in 1..=3 {
+                    self.read(stack(sp.wrapping_add(offset)));
+                }
+            }
+            Pha | Php | Phx | Phy => {
+                self.dummy(self.operand(1));
+                self.write(stack(sp));
+            }
+            Pla | Plp | Plx | Ply => {
+                self.dummy(self.operand(1));
+                self.dummy(stack(sp));
+                self.read(stack(sp.wrapping_add(1)));
+            }
+            Jmp => self.jump(mode),
+            Bpl | Bmi | Bvc | Bvs | Bcc | Bcs | Bne | Beq | Bra => {
+                let stat: Status = self.core.stat;
+
+                let taken: bool = match mnemonic {
+                    Bpl => !stat.get(Flag::Negative),
+                    Bmi => stat.get(Flag::Negative),
+                    Bvc => !stat.get(Flag::Overflow),
+                    Bvs => stat.get(Flag::Overflow),
+                    Bcc => !stat.get(Flag::Carry),
+                    Bcs => stat.get(Flag::Carry),
+                    Bne => !stat.get(Flag::Zero),
+                    Beq => stat.get(Flag::Zero),
+                    _ => true,
+                };
+
+                // The offset is always fetched, but only used when taken.
+                if taken {
+                    let offset: u8 = self.read(self.operand(1));
+                    self.branch(self.operand(2), offset);
+                } else {
+                    self.dummy(self.operand(1));
+                }
+            }
+            Wai | Stp => {
+                self.dummy(self.operand(1));
+                self.dummy(self.operand(1));
+            }
+            Jam => {
+                self.dummy(self.operand(1));
+            }
+            Bbr(bit) | Bbs(bit) => {
+                let zp: u8 = self.read(self.operand(1));
+                let value: u8 = self.read(zp as u16);
+                self.dummy(zp as u16);
+
+                let taken: bool = ((value >> bit) & 1 == 1) == matches!(mnemonic, Bbs(_));
+
+                if taken {
+                    let offset: u8 = self.read(self.operand(2));
+                    self.branch(self.operand(3), offset);
+                } else {
+                    self.dummy(self.operand(2));
+                }
+            }
+            Rmb(_) | Smb(_) => {
+                let zp: u8 = self.read(self.operand(1));
+                self.read(zp as u16);
+                self.dummy(zp as u16);
+                self.write(zp as u16);
+            }
+            // 65C02 single cycle NOPs don't even read the next byte.
+            Nop if info.cycles == 1 => {}
+            // The 65C02's NOP $5C reads from the top page for eight cycles.
+            Nop if self.cmos && opcode == 0x5C => {
+                let low: u8 = self.read(self.operand(1));
+                self.read(self.operand(2));
+                self.dummy(0xFF00 | low as u16);
+
+                for _ in 0..4 {
+                    self.dummy(0xFFFF);
+                }
+            }
+            _ => match mode {
+                Mode::Implied | Mode::Accumulator => {
+                    self.dummy(self.operand(1));
+                }
+                _ => self.memory_operand(opcode, mnemonic, mode),
+            },
+        }
+
+        // The 65C02 takes an extra cycle to correct the flags in decimal mode.
+        let decimal: bool = self.core.stat.get(Flag::Decimal) && self.core.variant.has_decimal_mode();
+
+        if self.cmos && decimal && matches!(mnemonic, Adc | Sbc) {
+            if let Some(Planned::Read(address)) = self.accesses.last().copied() {
+                self.dummy(address);
+            }
+        }
+    }
+
+    // A taken branch reads the next opcode, then the wrong page if the
+    // target is in another page.
+    fn branch(&mut self, next: u16, offset: u8) {
+        let target: u16 = next.wrapping_add(offset as i8 as u16);
+
+        self.dummy(next);
+
+        if (next & 0xFF00) != (target & 0xFF00) {
+            self.dummy((next & 0xFF00) | (target & 0x00FF));
+        }
+    }
+
+    // The value SHA, SHX, SHY and TAS start from, before it's ANDed.
+    fn unstable(&self, mnemonic: Mnemonic) -> Option<u8> {
+        let core: &Core = self.core;
+
+        match mnemonic {
+            Sha | Tas => Some(core.acc & core.ix),
+            Shx => Some(core.ix),
+            Shy => Some(core.iy),
+            _ => None,
+        }
+    }
+
+    fn jump(&mut self, mode: Mode) {
+        let pointer: u16 = self.word(self.operand(1), self.operand(2));
+
+        match mode {
+            Mode::Indirect if self.cmos => {
+                self.dummy(self.operand(2));
+                self.word(pointer, pointer.wrapping_add(1));
+            }
+            // The NMOS parts don't carry into the high byte of the pointer.
+            Mode::Indirect => {
+                self.word(pointer, (pointer & 0xFF00) | (pointer.wrapping_add(1) & 0x00FF));
+            }
+            Mode::AbsoluteIndexedIndirect => {
+                let pointer: u16 = pointer.wrapping_add(self.core.ix as u16);
+
+                self.dummy(self.operand(2));
+                self.word(pointer, pointer.wrapping_add(1));
+            }
+            _ => {}
+        }
+    }
+
+    // Loads, stores and read-modify-write instructions.
+    fn memory_operand(&mut self, opcode: u8, mnemonic: Mnemonic, mode: Mode) {
+        let (ix, iy) = (self.core.ix, self.core.iy);
+        let class: Class = class(mnemonic);
+
+        // Works out the effective address, and whether an indexed mode needs
+        // the dummy read that comes before fixing up the high byte.
+        let (address, uncorrected): (u16, Option<u16>) = match mode {
+            // NOPs don't use their operand, the rest read it like memory.
+            Mode::Immediate if class == Class::Ignore => {
+                self.dummy(self.operand(1));
+                return
+            }
+            Mode::Immediate => {
+                self.read(self.operand(1));
+                return
+            }
+            Mode::ZeroPage => (self.read(self.operand(1)) as u16, None),
+            Mode::ZeroPageX | Mode::ZeroPageY => {
+                let zp: u8 = self.read(self.operand(1));
+                let index: u8 = if mode == Mode::ZeroPageX { ix } else { iy };
+
+                // Indexing takes a cycle, during which the base is read.
+                if self.cmos { self.dummy(self.operand(1)); } else { self.dummy(zp as u16); }
+
+                (zp.wrapping_add(index) as u16, None)
+            }
+            Mode::Absolute => (self.word(self.operand(1), self.operand(2)), None),
+            Mode::AbsoluteX | Mode::AbsoluteY => {
+                let base: u16 = self.word(self.operand(1), self.operand(2));
+                let index: u8 = if mode == Mode::AbsoluteX { ix } else { iy };
+                let address: u16 = base.wrapping_add(index as u16);
+
+                (address, Some((base & 0xFF00) | (address & 0x00FF)))
+            }
+            Mode::IndexedIndirect => {
+                let zp: u8 = self.read(self.operand(1));
+
+                if self.cmos { self.dummy(self.operand(1)); } else { self.dummy(zp as u16); }
+
+                let pointer: u8 = zp.wrapping_add(ix);
+
+                (self.word(pointer as u16, pointer.wrapping_add(1) as u16), None)
+            }
+            Mode::IndirectIndexed => {
+                let zp: u8 = self.read(self.operand(1));
+                let base: u16 = self.word(zp as u16, zp.wrapping_add(1) as u16);
+                let address: u16 = base.wrapping_add(iy as u16);
+
+                (address, Some((base & 0xFF00) | (address & 0x00FF)))
+            }
+            Mode::ZeroPageIndirect => {
+                let zp: u8 = self.read(self.operand(1));
+
+                (self.word(zp as u16, zp.wrapping_add(1) as u16), None)
+            }
+            _ => unreachable!("{mode:?}"),
+        };
+
+        if let Some(uncorrected) = uncorrected {
+            let crossed: bool = uncorrected != address;
+
+            // Reads skip the fix up cycle when no page was crossed. So do the
+            // 65C02's shifts on ABSX, but not its INC and DEC.
+            let fix_up: bool = match class {
+                Class::Read | Class::Ignore => crossed,
+                Class::Write => true,
+                Class::Modify => crossed || !self.cmos || matches!(opcode, 0xDE | 0xFE),
+            };
+
+            if fix_up {
+                // The 65C02 reads the last operand byte instead of the wrong address.
+                let dummy: u16 = if !self.cmos {
+                    uncorrected
+                } else if mode == Mode::IndirectIndexed {
+                    self.operand(1)
+                } else {
+                    self.operand(2)
+                };
+
+                self.dummy(dummy);
+            }
+        }
+
+        match class {
+            Class::Read => {
+                self.read(address);
+            }
+            Class::Ignore => {
+                self.dummy(address);
+            }
+            Class::Write => {
+                // The unstable stores put the value stored in the high byte
+                // of the address when indexing crosses a page.
+                let address: u16 = match (uncorrected, self.unstable(mnemonic)) {
+                    (Some(uncorrected), Some(value)) if uncorrected != address => {
+                        let result: u8 = value & ((uncorrected >> 8) as u8).wrapping_add(1);
+
+                        ((result as u16) << 8) | (address & 0x00FF)
+                    }
+                    _ => address,
+                };
+
+                self.write(address);
+            }
+            Class::Modify => {
+                self.read(address);
+
+                if self.cmos {
+                    self.dummy(address);
+                } else {
+                    self.accesses.push(Planned::WriteBack(address));
+                }
+
+                self.write(address);
+            }
+        }
+    }
+}
+
+// Works out the bus accesses the next call to `run_step()` will make.
+// This mirrors the order `run_step()` checks things in.
+pub fn plan(core: &Core, table: &OpcodeTable) -> Result<Vec<Planned>, EmulatorError> {
+    if core.variant == Variant::W65816 { return Err(EmulatorError::NoCycleModel(core.variant)) }
+
+    if core.halted && !core.reset_pending { return Ok(Vec::new()) }
+
+    if core.waiting && !(core.irq || core.nmi_pending || core.reset_pending) { return Ok(Vec::new()) }
+
+    let mut plan: Plan = Plan {
+        core,
+        cmos: core.variant.is_cmos(),
+        accesses: Vec::new(),
+    };
+
+    if core.reset_pending {
+        plan.reset();
+    } else if core.nmi_pending {
+        plan.interrupt(0xFFFA);
+    } else if core.irq && !core.stat.get(Flag::Interrupt) {
+        plan.interrupt(0xFFFE);
+    } else if let Some(info) = table.get_instruction(core.peek(core.pc)) {
+        plan.instruction(info);
+    }
+
+    Ok(plan.accesses)
+}
+
+// A plan being made on the bus while its instruction runs. The core holds
+// one while stepping by cycle, and hands its reads and writes to it.
+pub(crate) struct Replay {
+    planned: VecDeque<Planned>,
+    made: Vec<BusCycle>,
+}
+
+impl Replay {
+    pub(crate) fn new(planned: Vec<Planned>) -> Self {
+        Self { planned: planned.into(), made: Vec::new() }
+    }
+
+    pub(crate) fn read(&mut self, core: &mut Core, address: u16) -> u8 {
+        self.dummies(core, Planned::Read(address));
+
+        let value: u8 = core.bus.read(address);
+        self.made.push(BusCycle { address, value, access: Access::Read });
+
+        value
+    }
+
+    pub(crate) fn write(&mut self, core: &mut Core, address: u16, value: u8) {
+        self.dummies(core, Planned::Write(address));
+
+        core.write_bus(address, value);
+        self.made.push(BusCycle { address, value, access: Access::Write });
+    }
+
+    // Makes the dummies planned before an access, if it's the one planned next.
+    fn dummies(&mut self, core: &mut Core, access: Planned) {
+        let Some(next) = self.planned.iter().position(|planned| !planned.is_dummy()) else { return };
+
+        if self.planned[next] != access { return }
+
+        for _ in 0..next {
+            if let Some(dummy) = self.planned.pop_front() { self.dummy(core, dummy) }
+        }
+
+        self.planned.pop_front();
+    }
+
+    fn dummy(&mut self, core: &mut Core, dummy: Planned) {
+        match dummy {
+            Planned::Dummy(address) => {
+                let value: u8 = core.bus.read(address);
+                self.made.push(BusCycle { address, value, access: Access::Read });
+            }
+            Planned::WriteBack(address) => {
+                // The value the instruction read, which is still in the CPU.
+                let value: u8 = self.made.iter().rev()
+                    .find(|cycle| cycle.address == address && cycle.access == Access::Read)
+                    .map_or_else(|| core.peek(address), |cycle| cycle.value);
+
+                core.write_bus(address, value);
+                self.made.push(BusCycle { address, value, access: Access::Write });
+            }
+            Planned::Read(_) | Planned::Write(_) => unreachable!("{dummy:?} isn't a dummy"),
+        }
+    }
+}
+
+// Ends the replay once the instruction has run, making the dummies left at
+// the end of it. Returns every access made, in order. An instruction that
+// failed doesn't get the rest of its cycles.
+pub(crate) fn finish(core: &mut Core, completed: bool) -> Vec<BusCycle> {
+    let Some(mut replay) = core.replay.take() else { return Vec::new() };
+
+    while completed && replay.planned.front().is_some_and(Planned::is_dummy) {
+        if let Some(dummy) = replay.planned.pop_front() { replay.dummy(core, dummy) }
+    }
+
+    replay.made
+}
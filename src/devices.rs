@@ -0,0 +1,191 @@
+use crate::{
+    bus::{Bus, Ram},
+    error::EmulatorError,
+    savestate::{StateReader, StateWriter},
+};
+
+use std::ops::RangeInclusive;
+
+/*
+    Memory-mapped hardware.
+
+    A `Device` is anything that sits on the bus over a range of addresses,
+    like a VIA, an ACIA or an LCD controller. Reads and writes inside its
+    range go to the device, given as an offset from the start of the range,
+    so a device doesn't need to know where it was mapped. Everything outside
+    the registered ranges is plain RAM.
+
+    Devices also get a tick for every clock cycle the core runs, and can hold
+    the IRQ or NMI lines. The core checks the lines between instructions, in
+    the same way as `Core::set_irq()` and `Core::set_nmi()`. IRQ is shared
+    between all the devices, so it stays asserted while any of them hold it.
+
+    Save states take the RAM and then each device in the order they were
+    registered. A device with registers or timers worth keeping saves them
+    through `save()` and `restore()`, the rest can leave the defaults.
+*/
+
+pub trait Device {
+    // A read made by the CPU, at an offset into the device's range.
+    fn read(&mut self, offset: u16) -> u8;
+
+    // A write made by the CPU, at an offset into the device's range.
+    fn write(&mut self, offset: u16, value: u8);
+
+    // Look at a register without the CPU reading it. Devices where that
+    // isn't possible read as zero in memory dumps.
+    fn peek(&self, _offset: u16) -> u8 {
+        0
+    }
+
+    // Called once for every clock cycle.
+    fn tick(&mut self) {}
+
+    // True while the device is holding the IRQ line.
+    fn irq(&self) -> bool {
+        false
+    }
+
+    // True while the device is holding the NMI line.
+    fn nmi(&self) -> bool {
+        false
+    }
+
+    // Write the device's state into a save state.
+    fn save(&self, _state: &mut StateWriter) {}
+
+    // Read back what `save()` wrote.
+    fn restore(&mut self, _state: &mut StateReader) -> Result<(), EmulatorError> {
+        Ok(())
+    }
+}
+
+struct Mapped {
+    range: RangeInclusive<u16>,
+    device: Box<dyn Device>,
+}
+
+// A bus of RAM with devices mapped over parts of it.
+#[derive(Default)]
+pub struct DeviceBus {
+    ram: Ram,
+    devices: Vec<Mapped>,
+}
+
+impl DeviceBus {
+    pub fn new() -> Self {
+        Self { ram: Ram::new(), devices: Vec::new() }
+    }
+
+    // Map a device over a range of addresses. Ranges can't overlap, as the
+    // bus wouldn't know which device should answer.
+    pub fn register(&mut self, range: RangeInclusive<u16>, device: Box<dyn Device>) -> Result<(), EmulatorError> {
+        if range.is_empty() {
+            return Err(EmulatorError::EmptyRange(range))
+        }
+
+        if let Some(mapped) = self.devices.iter().find(|mapped| {
+            mapped.range.start() <= range.end() && range.start() <= mapped.range.end()
+        }) {
+            return Err(EmulatorError::DeviceOverlap { range, mapped: mapped.range.clone() })
+        }
+
+        self.devices.push(Mapped { range, device });
+
+        Ok(())
+    }
+
+    // The device mapped over an address, and the offset into its range.
+    fn device(&self, address: u16) -> Option<(usize, u16)> {
+        self.devices.iter()
+            .position(|mapped| mapped.range.contains(&address))
+            .map(|index| (index, address - self.devices[index].range.start()))
+    }
+}
+
+impl Bus for DeviceBus {
+    fn read(&mut self, address: u16) -> u8 {
+        match self.device(address) {
+            Some((index, offset)) => self.devices[index].device.read(offset),
+            None => self.ram.read(address),
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match self.device(address) {
+            Some((index, offset)) => self.devices[index].device.write(offset, value),
+            None => self.ram.write(address, value),
+        }
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        match self.device(address) {
+            Some((index, offset)) => self.devices[index].device.peek(offset),
+            None => self.ram.peek(address),
+        }
+    }
+
+    fn tick(&mut self) {
+        for mapped in &mut self.devices {
+            mapped.device.tick();
+        }
+    }
+
+    fn irq(&self) -> bool {
+        self.devices.iter().any(|mapped| mapped.device.irq())
+    }
+
+    fn nmi(&self) -> bool {
+        self.devices.iter().any(|mapped| mapped.device.nmi())
+    }
+
+    fn save(&self, state: &mut StateWriter) {
+        self.ram.save(state);
+        self.save_registers(state);
+    }
+
+    fn restore(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        self.ram.restore(state)?;
+        self.restore_registers(state)
+    }
+
+    fn stored(&self, address: u16) -> Option<u8> {
+        match self.device(address) {
+            Some(_) => None,
+            None => self.ram.stored(address),
+        }
+    }
+
+    fn put_back(&mut self, address: u16, value: u8) {
+        if self.device(address).is_none() { self.ram.put_back(address, value) }
+    }
+
+    // Each device gets a block of its own, so one that reads back more or
+    // less than it wrote is caught rather than throwing the rest off.
+    fn save_registers(&self, state: &mut StateWriter) {
+        state.u16(self.devices.len() as u16);
+
+        for mapped in &self.devices {
+            let mut device: StateWriter = StateWriter::new();
+            mapped.device.save(&mut device);
+            state.block(&device.into_bytes());
+        }
+    }
+
+    fn restore_registers(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        if state.u16()? as usize != self.devices.len() {
+            return Err(EmulatorError::SaveState("Saved with a different number of devices".to_string()))
+        }
+
+        for mapped in &mut self.devices {
+            let mut device: StateReader = StateReader::new(state.block()?);
+            mapped.device.restore(&mut device)?;
+
+            if !device.is_empty() {
+                return Err(EmulatorError::SaveState("Device state doesn't match the device".to_string()))
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use crate::trie::Trie;
+use crate::{error::EmulatorError, instruction::{Mnemonic, Mode}, table::OpcodeTable};
 
 use std::fs::File;
 use std::io::prelude::*;
@@ -21,138 +21,267 @@ impl BasicSystem {
     }
 }
 
-// Main disassembler function. Takes the binary vector as input.
-// Jumps and such won't work as I'm just plainly going through the binary
-// instruction by instruction, only ensuring we jump past any addresses or data.
+// Main disassembler function. Takes the binary vector as input, and writes
+// the listing to the terminal and out.txt.
 pub fn disassembler(
-    data: &Vec<u8>, 
+    data: &[u8], 
     start: &u16, 
-    prefix_trie: &Trie
-) -> std::io::Result<()> {
-    let mut i: usize = *start as usize;
-    let mut to_visit = Vec::new();
+    table: &OpcodeTable
+) -> Result<(), EmulatorError> {
+    let lines: Vec<String> = disassemble(data, start, table)?;
 
     let mut file = File::create("out.txt")?;
 
+    for line in lines {
+        print!("{line}");
+        file.write_all(line.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+// The listing itself, a line per instruction.
+// Jumps and such won't work as I'm just plainly going through the binary
+// instruction by instruction, only ensuring we jump past any addresses or data.
+pub fn disassemble(data: &[u8], start: &u16, table: &OpcodeTable) -> Result<Vec<String>, EmulatorError> {
+    let mut i: usize = *start as usize;
+    let end: usize = *start as usize + data.len();
+
+    if end > 0x10000 {
+        return Err(EmulatorError::Load("Binary exceeds memory bounds".to_string()))
+    }
+    let mut to_visit = Vec::new();
+    let mut lines: Vec<String> = Vec::new();
+
     // Initialize the basic system.
     let mut basic_system = BasicSystem::new(start);
 
     // Load the data into memory.
-    basic_system.memory[
-        (basic_system.pc as usize)..(basic_system.pc as usize + data.len())
-    ].copy_from_slice(data);
+    basic_system.memory[(basic_system.pc as usize)..end].copy_from_slice(data);
 
-    basic_system.pc = 0x0200;
+    // 65816 register widths, followed through REP and SEP so immediates are
+    // decoded at the right size. Code starts out with 8-bit registers.
+    let mut wide_m: bool = false;
+    let mut wide_x: bool = false;
 
     // I feel like this is messy and cumbersome.
     // Loop through all the provided data.
-    while i < basic_system.pc as usize + data.len() {
-        if let Some(current) = prefix_trie.get_instruction(basic_system.memory[i]) {
-            let arr: Vec<&str> = current.split(",").collect();
-
-            if arr[0] == "JMP" || arr[0] == "JSR" {
-                to_visit.push(
-                    ((basic_system.memory[i + 1] as u16) << 8) 
-                    | (basic_system.memory[i + 2] as u16)
-                );
-            }
+    while i < end {
+        if let Some(info) = table.get_instruction(basic_system.memory[i]) {
+            let mnemonic: Mnemonic = info.mnemonic;
+
+            // IMMM and IMMX immediates are a byte longer while M or X, as tracked
+            // above, selects 16-bit registers.
+            let size: usize = match info.mode {
+                Mode::ImmediateM if wide_m => 3,
+                Mode::ImmediateX if wide_x => 3,
+                _ => info.length as usize,
+            };
+
+            let bytes: Vec<u8> = (0..size).map(|offset| basic_system.memory[(i + offset) & 0xFFFF]).collect();
+            let target: u16 = info.branch_target(i as u16, &bytes).unwrap_or(0);
 
             // Memory addresses are stored as little endian values.
-            // I am honestly not sure if I'm handling addresses in the right endian or not..
-            let line: String = match arr[1] {
-                "ABS" => { // Absolute
+            let absolute: u16 = ((basic_system.memory[(i + 2) & 0xFFFF] as u16) << 8)
+                | (basic_system.memory[(i + 1) & 0xFFFF] as u16);
+
+            if mnemonic == Mnemonic::Rep || mnemonic == Mnemonic::Sep {
+                let bits: u8 = basic_system.memory[(i + 1) & 0xFFFF];
+
+                if bits & 0b00100000 != 0 { wide_m = mnemonic == Mnemonic::Rep }
+                if bits & 0b00010000 != 0 { wide_x = mnemonic == Mnemonic::Rep }
+            }
+
+            // XCE leaves M and X set whichever way it switches, emulation
+            // mode forces them and native mode is entered from emulation.
+            if mnemonic == Mnemonic::Xce {
+                wide_m = false;
+                wide_x = false;
+            }
+
+            if mnemonic == Mnemonic::Jmp || mnemonic == Mnemonic::Jsr {
+                to_visit.push(absolute);
+            }
+
+            let line: String = match info.mode {
+                Mode::Absolute => { // Absolute
                     format!(
                         "{} ${:04X} \n",
-                        arr[0],
-                        ((basic_system.memory[i + 1] as u16) << 8) 
-                        | (basic_system.memory[i + 2] as u16)
+                        mnemonic,
+                        absolute
                     )
                 }
-                "ABSX" | "ABSY" => { // Absolute X and Y
+                Mode::AbsoluteX | Mode::AbsoluteY => { // Absolute X and Y
                     format!(
                         "{} ${:04X},{} \n",
-                        arr[0],
-                        ((basic_system.memory[i + 1] as u16) << 8) 
-                        | (basic_system.memory[i + 2] as u16),
-                        arr[1].chars().last().unwrap()
+                        mnemonic,
+                        absolute,
+                        if info.mode == Mode::AbsoluteX { "X" } else { "Y" }
                     )
                 }
-                "IND" => { // Indirect
+                Mode::Indirect => { // Indirect
                     format!(
                         "{} (${:04X}) \n",
-                        arr[0],
-                        ((basic_system.memory[i + 1] as u16) << 8) 
-                        | (basic_system.memory[i + 2] as u16),
+                        mnemonic,
+                        absolute,
                     )
                 }
-                "IMP" => { // Implicit
+                Mode::Implied => { // Implicit
                     format!(
                         "{} \n",
-                        arr[0],
+                        mnemonic,
                     )
                 }
-                "ACC" => { // Accumulator
+                Mode::Accumulator => { // Accumulator
                     format!(
                         "{} A \n",
-                        arr[0],
+                        mnemonic,
                     )
                 }
-                "IMM" => { // Immediate
+                Mode::Immediate => { // Immediate
                     format!(
                         "{} #{:02X} \n",
-                        arr[0],
-                        basic_system.memory[i + 1],
+                        mnemonic,
+                        basic_system.memory[(i + 1) & 0xFFFF],
                     )
                 }
-                "ZP" => { // Zero Page
+                Mode::ImmediateM | Mode::ImmediateX => { // Immediate, 65816 only, 16 bits when M or X is clear
+                    if size == 3 {
+                        format!(
+                            "{} #{:04X} \n",
+                            mnemonic,
+                            ((basic_system.memory[(i + 2) & 0xFFFF] as u16) << 8)
+                            | (basic_system.memory[(i + 1) & 0xFFFF] as u16),
+                        )
+                    } else {
+                        format!(
+                            "{} #{:02X} \n",
+                            mnemonic,
+                            basic_system.memory[(i + 1) & 0xFFFF],
+                        )
+                    }
+                }
+                Mode::ZeroPage => { // Zero Page
                     format!(
                         "{} ${:02X} \n",
-                        arr[0],
-                        basic_system.memory[i + 1],
+                        mnemonic,
+                        basic_system.memory[(i + 1) & 0xFFFF],
                     )
                 }
-                "ZPX" | "ZPY" => { // Zero Page X and Y
+                Mode::ZeroPageX | Mode::ZeroPageY => { // Zero Page X and Y
                     format!(
                         "{} ${:02X},{} \n",
-                        arr[0],
-                        basic_system.memory[i + 1],
-                        arr[1].chars().last().unwrap(),
+                        mnemonic,
+                        basic_system.memory[(i + 1) & 0xFFFF],
+                        if info.mode == Mode::ZeroPageX { "X" } else { "Y" },
                     )
                 }
-                "INDX" => { // Indexed Indirect
+                Mode::IndexedIndirect => { // Indexed Indirect
                     format!(
                         "{} (${:02X},X) \n",
-                        arr[0],
-                        basic_system.memory[i + 1],
+                        mnemonic,
+                        basic_system.memory[(i + 1) & 0xFFFF],
+                    )
+                }
+                Mode::ZeroPageIndirect => { // Zero Page Indirect, 65C02 only
+                    format!(
+                        "{} (${:02X}) \n",
+                        mnemonic,
+                        basic_system.memory[(i + 1) & 0xFFFF],
                     )
                 }
-                "INDY" => { // Indirect Indexed
+                Mode::AbsoluteIndexedIndirect => { // Absolute Indexed Indirect, 65C02 only
+                    format!(
+                        "{} (${:04X},X) \n",
+                        mnemonic,
+                        absolute,
+                    )
+                }
+                Mode::ZeroPageRelative => { // Zero Page and Relative, used by BBR and BBS
+                    format!(
+                        "{} ${:02X},${:04X} \n",
+                        mnemonic,
+                        basic_system.memory[(i + 1) & 0xFFFF],
+                        target,
+                    )
+                }
+                Mode::IndirectIndexed => { // Indirect Indexed
                     format!(
                         "{} (${:02X}),Y \n",
-                        arr[0],
-                        basic_system.memory[i + 1],
+                        mnemonic,
+                        basic_system.memory[(i + 1) & 0xFFFF],
                     )
                 }
-                // Only the branch functions use relative addressing.
-                "REL" => { // Relative
+                Mode::IndirectLong | Mode::IndirectLongY => { // Direct Page Indirect Long, 65816 only
                     format!(
-                        "{} ${:02X} \n",
-                        arr[0],
-                        basic_system.memory[i + 1] as i8,
+                        "{} [${:02X}]{} \n",
+                        mnemonic,
+                        basic_system.memory[(i + 1) & 0xFFFF],
+                        if info.mode == Mode::IndirectLongY { ",Y" } else { "" },
+                    )
+                }
+                Mode::StackRelative => { // Stack Relative, 65816 only
+                    format!(
+                        "{} ${:02X},S \n",
+                        mnemonic,
+                        basic_system.memory[(i + 1) & 0xFFFF],
+                    )
+                }
+                Mode::StackRelativeIndirectY => { // Stack Relative Indirect Indexed, 65816 only
+                    format!(
+                        "{} (${:02X},S),Y \n",
+                        mnemonic,
+                        basic_system.memory[(i + 1) & 0xFFFF],
+                    )
+                }
+                Mode::AbsoluteLong | Mode::AbsoluteLongX => { // Absolute Long, 65816 only
+                    format!(
+                        "{} ${:06X}{} \n",
+                        mnemonic,
+                        ((basic_system.memory[(i + 3) & 0xFFFF] as u32) << 16)
+                        | ((basic_system.memory[(i + 2) & 0xFFFF] as u32) << 8)
+                        | (basic_system.memory[(i + 1) & 0xFFFF] as u32),
+                        if info.mode == Mode::AbsoluteLongX { ",X" } else { "" },
+                    )
+                }
+                Mode::AbsoluteIndirectLong => { // Absolute Indirect Long, 65816 only
+                    format!(
+                        "{} [${:04X}] \n",
+                        mnemonic,
+                        ((basic_system.memory[(i + 2) & 0xFFFF] as u16) << 8)
+                        | (basic_system.memory[(i + 1) & 0xFFFF] as u16),
+                    )
+                }
+                Mode::RelativeLong => { // Relative Long, 65816 only
+                    format!(
+                        "{} ${:04X} \n",
+                        mnemonic,
+                        target,
+                    )
+                }
+                Mode::BlockMove => { // Block Move, 65816 only. Written source first, stored destination first.
+                    format!(
+                        "{} ${:02X},${:02X} \n",
+                        mnemonic,
+                        basic_system.memory[(i + 2) & 0xFFFF],
+                        basic_system.memory[(i + 1) & 0xFFFF],
+                    )
+                }
+                // Only the branch functions use relative addressing, listed
+                // with where they go rather than the offset.
+                Mode::Relative => { // Relative
+                    format!(
+                        "{} ${:04X} \n",
+                        mnemonic,
+                        target,
                     )
                 }
-                _ => format!(
-                        "{:?} : {:?} : {:02X} \n",
-                        arr[0], arr[1],
-                        basic_system.memory[i]
-                )
             };
 
-            print!("{line}");
-            file.write_all(line.as_bytes())?;
+            lines.push(line);
 
-            if arr[2].parse::<usize>().unwrap() > 1 {
-                i += arr[2].parse::<usize>().unwrap();
+            if size > 1 {
+                i += size;
             } else {
                 i += 1
             }
@@ -161,5 +290,5 @@ pub fn disassembler(
         }
     }
 
-    Ok(())
+    Ok(lines)
 }
\ No newline at end of file
@@ -0,0 +1,70 @@
+use crate::{bus::WriteFault, system::Variant};
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+// Everything that can stop the emulator. A misbehaving program ends up as
+// one of these rather than a panic, so whatever is running the core can
+// report it and carry on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EmulatorError {
+    InvalidOpcode { opcode: u8, address: u16 }, // Nothing to run for the opcode fetched at the address
+    StackOverflow { pc: u16 }, // A push wrapped the stack pointer past 0x00
+    StackUnderflow { pc: u16 }, // A pull wrapped the stack pointer past 0xFF
+    BusFault(WriteFault), // A trapped write to ROM
+    NoCycleModel(Variant), // Stepping by cycle on a CPU the bus cycle model doesn't cover
+    EmptyRange(RangeInclusive<u16>), // A device registered over no addresses at all
+    DeviceOverlap { range: RangeInclusive<u16>, mapped: RangeInclusive<u16> }, // A device registered over one already mapped
+    EmptyRom, // A mapper given a ROM image with nothing in it
+    ZeroBankSize, // A mapper asked to split its ROM into banks of no bytes
+    WindowOutOfRange(u16), // A switched window starting too late for a whole bank to fit
+    RomSize { rom: &'static str, expected: usize, found: usize }, // A ROM image that isn't the size its slot needs
+    Load(String), // A binary that couldn't be read or doesn't fit in memory
+    SaveState(String), // A save state that can't be restored, or a core that can't be saved
+    Io(String), // Anything else going wrong with a file
+}
+
+impl fmt::Display for EmulatorError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmulatorError::InvalidOpcode { opcode, address } => {
+                write!(f, "Invalid opcode 0x{:02X} at 0x{:04X}", opcode, address)
+            }
+            EmulatorError::StackOverflow { pc } => write!(f, "Stack overflow at PC 0x{:04X}", pc),
+            EmulatorError::StackUnderflow { pc } => write!(f, "Stack underflow at PC 0x{:04X}", pc),
+            EmulatorError::BusFault(fault) => write!(
+                f,
+                "Write of 0x{:02X} to ROM at 0x{:04X} from PC 0x{:04X}",
+                fault.value, fault.address, fault.pc,
+            ),
+            EmulatorError::NoCycleModel(variant) => write!(f, "No bus cycle model for the {:?}", variant),
+            EmulatorError::EmptyRange(range) => {
+                write!(f, "Empty address range: 0x{:04X}-0x{:04X}", range.start(), range.end())
+            }
+            EmulatorError::DeviceOverlap { range, mapped } => write!(
+                f,
+                "0x{:04X}-0x{:04X} overlaps a device at 0x{:04X}-0x{:04X}",
+                range.start(), range.end(), mapped.start(), mapped.end(),
+            ),
+            EmulatorError::EmptyRom => write!(f, "ROM image is empty"),
+            EmulatorError::ZeroBankSize => write!(f, "Bank size can't be zero"),
+            EmulatorError::WindowOutOfRange(window) => {
+                write!(f, "Window at 0x{:04X} runs past the end of memory", window)
+            }
+            EmulatorError::RomSize { rom, expected, found } => {
+                write!(f, "{} ROM must be {} bytes, not {}", rom, expected, found)
+            }
+            EmulatorError::Load(error) => write!(f, "Problem loading binary: {}", error),
+            EmulatorError::SaveState(error) => write!(f, "Problem with save state: {}", error),
+            EmulatorError::Io(error) => write!(f, "Problem with file: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for EmulatorError {}
+
+impl From<std::io::Error> for EmulatorError {
+    fn from(error: std::io::Error) -> Self {
+        EmulatorError::Io(error.to_string())
+    }
+}
@@ -1,4 +1,38 @@
 import csv
+import re
+
+# Addressing modes as written in the csv, and their names in instruction.rs.
+modes = {
+    'IMP': 'Implied', 'ACC': 'Accumulator', 'IMM': 'Immediate',
+    'ZP': 'ZeroPage', 'ZPX': 'ZeroPageX', 'ZPY': 'ZeroPageY',
+    'ABS': 'Absolute', 'ABSX': 'AbsoluteX', 'ABSY': 'AbsoluteY',
+    'IND': 'Indirect', 'INDX': 'IndexedIndirect', 'INDY': 'IndirectIndexed',
+    'REL': 'Relative',
+}
+
+def mnemonic(name):
+    # The Rockwell bit instructions carry their bit number.
+    bit = re.fullmatch(r'(BBR|BBS|RMB|SMB)(\d)', name)
+    if bit:
+        return f"{bit.group(1).capitalize()}({bit.group(2)})"
+    return name.capitalize()
+
+def cycles(count):
+    # `4*` takes a cycle more on a page cross, `2/3` is a branch.
+    if count.endswith('*'):
+        return count[:-1], 'PageCross'
+    if '/' in count:
+        return count.split('/')[0], 'Branch'
+    return count, 'Fixed'
+
+def flags(letters):
+    # Capital letters are the flags the instruction changes.
+    changed = [letter for letter in letters if letter.isupper()]
+    if not changed:
+        return 'NONE'
+    if len(changed) == 7:
+        return 'ALL'
+    return ' | '.join(changed)
 
 ops = {}
 
@@ -14,8 +48,11 @@ with open('6502ops.csv', newline='') as csvfile:
         ]
 
 with open('output.txt', 'w') as file:
-    # Generate the code for trie insertions.
+    # Generate the code for the opcode table.
     for op in ops:
-        line = f"trie.insert(&{op}_u8, \"{",".join(ops[op])}\".to_string(), Some({ops[op][0].lower()}));"
+        name, mode, length, count, letters = ops[op]
+        base, penalty = cycles(count)
+        info = f"Instruction::new({mnemonic(name)}, {modes[mode]}, {length}, {base}, {penalty}, {flags(letters)})"
+        line = f"table.insert({op}, {info}, {name.lower()});"
         file.write(line + '\n')
     file.write('\n')
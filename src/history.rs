@@ -0,0 +1,320 @@
+use crate::{
+    error::EmulatorError,
+    savestate::{Registers, StateReader, StateWriter},
+    system::Core,
+};
+
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+/*
+    Execution history, for stepping backwards.
+
+    Going back an instruction means getting the whole machine back to how it
+    was before it ran, memory and devices included, without running anything
+    a second time. A device that prints what's written to it, or clears a
+    register when it's read, would see every access again if steps were
+    replayed, and writing the old value back to a mapper register or a device
+    through the bus isn't the same as it never having been written.
+
+    So every step keeps what it changed as it goes. Before it runs, `record()`
+    takes the core's registers and the bus's own registers, see bus.rs, which
+    are a few bytes for mappers and devices and nothing for plain RAM. While
+    it runs, `Core::write()` keeps each byte of memory it writes to, as it was
+    before and after. Going back to a recent step puts the old bytes back and
+    then its registers, none of it through a write the bus would see.
+
+    The whole state is only taken every so often, at the start of a stretch
+    of steps. A new stretch starts once the steps since the last one take up
+    as much room as its state, so there's never more than about twice as much
+    kept as the states themselves. The oldest stretches are dropped once
+    everything takes up more than the budget, the newest is always kept.
+
+    Anything done to the core outside of stepping, like loading a file or
+    setting PC, is followed by `checkpoint()`, which starts a stretch of its
+    own and stops going back at the state just after it. Kept bytes can't be
+    put back across a change like that, so going back past a checkpoint
+    restores the whole state at the start of the stretch being gone back to,
+    and puts each step's new bytes in from there. A checkpoint and the
+    `record()` for the step right after it are kept as one.
+*/
+
+// A byte of memory a step wrote to, as it was before and after.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Written {
+    pub address: u32, // 24-bit, anything above bank zero is the 65816's extended memory
+    pub old: u8,
+    pub new: u8,
+}
+
+// A state to go back to, the one before a step or just after a checkpoint.
+struct Entry {
+    registers: Registers,
+    bus: Vec<u8>, // The bus's registers, see `Bus::save_registers()`
+    snapshot: Option<Vec<u8>>, // The whole state, for the first entry of a stretch
+    checkpoint: bool, // Come to by changing the core, rather than by a step
+    written: usize, // How many of the kept writes are from the step after it
+}
+
+impl Entry {
+    // Roughly what it takes up with its step's writes, for the budget.
+    fn size(&self) -> usize {
+        size_of::<Self>() + self.bus.len() + self.snapshot.as_ref().map_or(0, Vec::len)
+            + self.written * size_of::<Written>()
+    }
+}
+
+pub struct History {
+    entries: VecDeque<Entry>,
+    writes: VecDeque<Written>, // Every step's writes, oldest first
+    open: bool, // The core is keeping writes for the newest entry's step
+    fresh: bool, // The newest entry is a checkpoint nothing has run since
+    linked: bool, // The core got to where it is from the newest entry by its step
+    budget: usize, // Bytes of history to keep, the newest stretch is always kept
+    size: usize, // Bytes of history kept
+    since: usize, // Bytes kept since the newest whole state, not counting it
+    snapshot: usize, // Size of the newest whole state
+    stretches: usize, // Entries with a whole state
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(64 * 1024 * 1024)
+    }
+}
+
+impl History {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            entries: VecDeque::new(),
+            writes: VecDeque::new(),
+            open: false,
+            fresh: false,
+            linked: false,
+            budget,
+            size: 0,
+            since: 0,
+            snapshot: 0,
+            stretches: 0,
+        }
+    }
+
+    // States that can be gone back to.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.writes.clear();
+        self.open = false;
+        self.fresh = false;
+        self.linked = false;
+        self.size = 0;
+        self.since = 0;
+        self.snapshot = 0;
+        self.stretches = 0;
+    }
+
+    // Call before every step.
+    pub fn record(&mut self, core: &mut Core) -> Result<(), EmulatorError> {
+        let journal: Vec<Written> = self.close(core);
+
+        if !self.fresh {
+            let whole: bool = self.is_empty() || self.since >= self.snapshot;
+            let snapshot: Option<Vec<u8>> = if whole { Some(core.save_state()?) } else { None };
+
+            self.push(core, snapshot, false);
+        }
+
+        core.journal = Some(journal);
+        self.open = true;
+        self.fresh = false;
+        self.linked = true;
+
+        Ok(())
+    }
+
+    // Call after changing the core any other way than a step, so going back
+    // stops at the change rather than going straight past it.
+    pub fn checkpoint(&mut self, core: &mut Core) -> Result<(), EmulatorError> {
+        self.close(core);
+
+        let snapshot: Vec<u8> = core.save_state()?;
+        self.push(core, Some(snapshot), true);
+
+        self.fresh = true;
+        self.linked = true;
+
+        Ok(())
+    }
+
+    // Go back a single step, or to the last checkpoint if that's nearer.
+    // Returns false when there's nothing to go back to.
+    pub fn step_back(&mut self, core: &mut Core) -> Result<bool, EmulatorError> {
+        let mut newest: usize = match self.len() {
+            0 => return Ok(false),
+            len => len - 1,
+        };
+
+        // Nothing has run since the newest state, so it's the one before.
+        let cycles: u64 = self.entries[newest].registers.cycles;
+        if self.fresh || (self.open && core.cycles == cycles) {
+            match newest {
+                0 => return Ok(false),
+                _ => newest -= 1,
+            }
+        }
+
+        self.rewind_to(core, newest).map(|_| true)
+    }
+
+    // Go back at least the given number of cycles, or as far as the history
+    // goes. Returns how many cycles were actually gone back.
+    pub fn rewind_cycles(&mut self, core: &mut Core, cycles: u64) -> Result<u64, EmulatorError> {
+        let now: u64 = core.cycles;
+        let target: u64 = now.saturating_sub(cycles);
+
+        let index: usize = match self.entries.iter().rposition(|entry| entry.registers.cycles <= target) {
+            Some(index) => index,
+            None if self.is_empty() => return Ok(0),
+            None => 0,
+        };
+
+        self.rewind_to(core, index)?;
+
+        Ok(now.saturating_sub(core.cycles))
+    }
+
+    // Puts the core back in the given state, counting from the oldest one
+    // kept, and forgets it and everything after it. Stepping on records it
+    // again.
+    fn rewind_to(&mut self, core: &mut Core, index: usize) -> Result<(), EmulatorError> {
+        self.close(core);
+
+        // Kept bytes only go back as far as the newest checkpoint.
+        let undo: bool = self.linked && !self.entries.range(index + 1..).any(|entry| entry.checkpoint);
+
+        if undo {
+            let mut end: usize = self.writes.len();
+
+            for entry in self.entries.range(index..).rev() {
+                for written in self.writes.range(end - entry.written..end).rev() {
+                    put_back(core, written.address, written.old);
+                }
+
+                end -= entry.written;
+            }
+        } else {
+            // There's always a whole state at the front.
+            let start: usize = self.entries.range(..=index).rposition(|entry| entry.snapshot.is_some()).unwrap_or(0);
+
+            if let Some(snapshot) = &self.entries[start].snapshot {
+                core.restore_state(snapshot)?;
+            }
+
+            let mut first: usize = self.entries.range(..start).map(|entry| entry.written).sum();
+
+            for entry in self.entries.range(start..index) {
+                for written in self.writes.range(first..first + entry.written) {
+                    put_back(core, written.address, written.new);
+                }
+
+                first += entry.written;
+            }
+        }
+
+        let entry: &Entry = &self.entries[index];
+        entry.registers.apply(core);
+        core.bus.restore_registers(&mut StateReader::new(&entry.bus))?;
+
+        self.linked = !entry.checkpoint;
+        self.fresh = false;
+
+        for entry in self.entries.drain(index..) {
+            self.writes.truncate(self.writes.len() - entry.written);
+            self.size -= entry.size();
+            self.stretches -= entry.snapshot.is_some() as usize;
+        }
+
+        // Everything since the newest whole state still kept.
+        let start: usize = self.entries.iter().rposition(|entry| entry.snapshot.is_some()).unwrap_or(0);
+
+        self.snapshot = self.entries.get(start).and_then(|entry| entry.snapshot.as_ref()).map_or(0, Vec::len);
+        self.since = self.entries.range(start..).map(Entry::size).sum::<usize>() - self.snapshot;
+
+        Ok(())
+    }
+
+    // Takes the writes of the step since the newest entry off the core, and
+    // hands back what kept them, empty, to keep the next step's.
+    fn close(&mut self, core: &mut Core) -> Vec<Written> {
+        let Some(mut journal) = core.journal.take() else { return Vec::new() };
+
+        if self.open {
+            if let Some(entry) = self.entries.back_mut() {
+                let bytes: usize = journal.len() * size_of::<Written>();
+
+                entry.written += journal.len();
+                self.size += bytes;
+                self.since += bytes;
+            }
+
+            self.writes.extend(journal.drain(..));
+        }
+
+        journal.clear();
+        self.open = false;
+
+        journal
+    }
+
+    fn push(&mut self, core: &Core, snapshot: Option<Vec<u8>>, checkpoint: bool) {
+        let mut bus: StateWriter = StateWriter::new();
+        core.bus.save_registers(&mut bus);
+
+        let entry: Entry = Entry { registers: Registers::of(core), bus: bus.into_bytes(), snapshot, checkpoint, written: 0 };
+
+        let size: usize = entry.size();
+        self.size += size;
+
+        match &entry.snapshot {
+            Some(snapshot) => {
+                self.snapshot = snapshot.len();
+                self.since = size - snapshot.len();
+                self.stretches += 1;
+            }
+            None => self.since += size,
+        }
+
+        self.entries.push_back(entry);
+
+        // Whole stretches go at once, as the steps in one need the state at its start.
+        while self.size > self.budget && self.stretches > 1 {
+            let Some(next) = self.entries.iter().skip(1).position(|entry| entry.snapshot.is_some()) else { break };
+
+            self.stretches -= 1;
+
+            for _ in 0..=next {
+                if let Some(entry) = self.entries.pop_front() {
+                    self.writes.drain(..entry.written);
+                    self.size -= entry.size();
+                }
+            }
+        }
+    }
+}
+
+// Puts a byte of memory back without the bus seeing a write.
+fn put_back(core: &mut Core, address: u32, value: u8) {
+    match address {
+        0x0000..=0xFFFF => core.bus.put_back(address as u16, value),
+        _ => if let Some(byte) = core.extended.get_mut(address as usize - 0x10000) {
+            *byte = value
+        },
+    }
+}
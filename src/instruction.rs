@@ -0,0 +1,136 @@
+use std::fmt;
+
+/*
+    What there is to know about an opcode without running it.
+
+    Every entry in the opcode tables built by `gen_table()` comes with one of
+    these, so the emulator, the cycle model, the disassembler and anything
+    else that wants to look at code all work from the same description.
+    `OpcodeTable::get_instruction()` gets the one for an opcode on a given variant.
+
+    The sizes and cycle counts are the ones for 8-bit registers. IMMM and
+    IMMX immediates on the 65816 gain a byte when the M or X flag is clear,
+    and its opcode functions add the cycles for 16-bit registers themselves.
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mnemonic {
+    // Official NMOS instructions.
+    Adc, And, Asl, Bcc, Bcs, Beq, Bit, Bmi, Bne, Bpl, Brk, Bvc, Bvs, Clc,
+    Cld, Cli, Clv, Cmp, Cpx, Cpy, Dec, Dex, Dey, Eor, Inc, Inx, Iny, Jmp,
+    Jsr, Lda, Ldx, Ldy, Lsr, Nop, Ora, Pha, Php, Pla, Plp, Rol, Ror, Rti,
+    Rts, Sbc, Sec, Sed, Sei, Sta, Stx, Sty, Tax, Tay, Tsx, Txa, Txs, Tya,
+    // Undocumented NMOS instructions. USBC is the second SBC at 0xEB.
+    Alr, Anc, Ane, Arr, Dcp, Isc, Jam, Las, Lax, Lxa, Rla, Rra, Sax, Sbx,
+    Sha, Shx, Shy, Slo, Sre, Tas, Usbc,
+    // 65C02 additions, with the bit number for the Rockwell instructions.
+    Bra, Phx, Phy, Plx, Ply, Stz, Trb, Tsb, Stp, Wai,
+    Bbr(u8), Bbs(u8), Rmb(u8), Smb(u8),
+    // 65816 additions.
+    Brl, Cop, Jml, Jsl, Mvn, Mvp, Pea, Pei, Per, Phb, Phd, Phk, Plb, Pld,
+    Rep, Rtl, Sep, Tcd, Tcs, Tdc, Tsc, Txy, Tyx, Wdm, Xba, Xce,
+}
+
+// Mnemonics are written the usual way, in capitals.
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Mnemonic::Bbr(bit) => write!(f, "BBR{}", bit),
+            Mnemonic::Bbs(bit) => write!(f, "BBS{}", bit),
+            Mnemonic::Rmb(bit) => write!(f, "RMB{}", bit),
+            Mnemonic::Smb(bit) => write!(f, "SMB{}", bit),
+            _ => write!(f, "{}", format!("{:?}", self).to_uppercase()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ImmediateM, // 65816 only, 16 bits when M is clear
+    ImmediateX, // 65816 only, 16 bits when X is clear
+    ZeroPage, // Direct page on the 65816
+    ZeroPageX,
+    ZeroPageY,
+    ZeroPageIndirect, // 65C02 and 65816
+    ZeroPageRelative, // BBR and BBS, a zero page address and a branch offset
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    AbsoluteIndexedIndirect, // 65C02 and 65816, JMP (abs,X)
+    Indirect, // JMP (abs)
+    IndexedIndirect, // (zp,X)
+    IndirectIndexed, // (zp),Y
+    Relative,
+    RelativeLong, // 65816 only
+    IndirectLong, // 65816 only, [dp]
+    IndirectLongY, // 65816 only, [dp],Y
+    StackRelative, // 65816 only
+    StackRelativeIndirectY, // 65816 only
+    AbsoluteLong, // 65816 only
+    AbsoluteLongX, // 65816 only
+    AbsoluteIndirectLong, // 65816 only, JML [abs]
+    BlockMove, // 65816 only, MVN and MVP
+}
+
+// When an instruction takes more than its base cycle count.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Penalty {
+    Fixed, // Never
+    PageCross, // One more cycle when indexing crosses a page
+    Branch, // One more when taken, and another when the target is in another page
+}
+
+// Status register bits, for the flags an instruction can change.
+pub mod flags {
+    pub const NONE: u8 = 0;
+    pub const C: u8 = 0b00000001; // Carry
+    pub const Z: u8 = 0b00000010; // Zero
+    pub const I: u8 = 0b00000100; // Interrupt disable
+    pub const D: u8 = 0b00001000; // Decimal
+    pub const B: u8 = 0b00010000; // Break, or X on the 65816
+    pub const V: u8 = 0b01000000; // Overflow
+    pub const N: u8 = 0b10000000; // Negative
+    pub const ALL: u8 = C | Z | I | D | B | V | N;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Instruction {
+    pub mnemonic: Mnemonic,
+    pub mode: Mode,
+    pub length: u8, // Bytes including the opcode
+    pub cycles: u8, // Base cycle count
+    pub penalty: Penalty,
+    pub flags: u8, // Status bits the instruction can change, see `flags`
+}
+
+impl Instruction {
+    pub const fn new(mnemonic: Mnemonic, mode: Mode, length: u8, cycles: u8, penalty: Penalty, flags: u8) -> Self {
+        Self { mnemonic, mode, length, cycles, penalty, flags }
+    }
+
+    // Where the instruction at the address branches to when taken, given its
+    // bytes from the opcode on. Anything listing code works this out here, so
+    // they all agree. None if it isn't a branch.
+    pub fn branch_target(&self, address: u16, bytes: &[u8]) -> Option<u16> {
+        let byte = |index: usize| bytes.get(index).copied().unwrap_or(0);
+
+        // Offsets count from the instruction after the branch.
+        let next: u16 = address.wrapping_add(self.length as u16);
+
+        match self.mode {
+            Mode::Relative => Some(next.wrapping_add(byte(1) as i8 as u16)),
+            Mode::ZeroPageRelative => Some(next.wrapping_add(byte(2) as i8 as u16)),
+            Mode::RelativeLong => Some(next.wrapping_add(((byte(2) as u16) << 8) | byte(1) as u16)),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:?}", self.mnemonic, self.mode)
+    }
+}
@@ -1,5 +1,26 @@
-pub mod trie;
+pub mod table;
 pub mod disassembler;
 pub mod system;
 pub mod opcodes;
-pub mod addressing;
\ No newline at end of file
+pub mod addressing;
+pub mod stack;
+pub mod status;
+pub mod savestate;
+pub mod history;
+pub mod trace;
+pub mod w65816;
+pub mod cycles;
+pub mod bus;
+pub mod devices;
+pub mod mappers;
+pub mod error;
+pub mod cpu;
+pub mod instruction;
+
+// The parts needed to embed the emulator, see cpu.rs.
+pub use cpu::{Cpu, Step};
+pub use error::EmulatorError;
+pub use instruction::{Instruction, Mnemonic, Mode, Penalty};
+pub use status::{Flag, Status};
+pub use system::{Core, Event, Variant};
+pub use trace::{TraceFormat, Tracer};
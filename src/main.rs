@@ -1,7 +1,7 @@
 use lolei_6502::{
     disassembler::disassembler,
-    system::emulator,
-    trie::{gen_trie, Trie}
+    system::{emulator, run_test, Trap, Variant, CYCLE_LIMIT},
+    table::{gen_table, OpcodeTable}
 };
 
 use std::fs;
@@ -15,6 +15,13 @@ fn cli() -> Command {
         .subcommand_required(true)
         .arg_required_else_help(true)
         .allow_external_subcommands(true)
+        // CPU selection, shared by both subcommands.
+        .arg(
+            arg!(--cpu <CPU> "CPU variant: 6502, 65c02, r65c02, w65c02, 2a03 or 65816")
+                .value_parser(parse_variant)
+                .default_value("6502")
+                .global(true)
+        )
         // Subcommand for the disassembler.
         .subcommand( // Expects a path to a binary file.
             Command::new("disassemble")
@@ -31,6 +38,29 @@ fn cli() -> Command {
             Command::new("emulate")
                 .about("Emulate 6502")
         )
+        // Subcommand for running test binaries unattended, like Klaus Dormann's suites.
+        .subcommand(
+            Command::new("test")
+                .about("Run a test binary until it traps, exiting with an error unless it passed")
+                .arg(arg!(<PATH> "The binary to run"))
+                .arg(
+                    arg!(<LOAD> "Address to load the binary at")
+                        .value_parser(parse_hex)
+                )
+                .arg(
+                    arg!(<START> "Start address for program counter")
+                        .value_parser(parse_hex)
+                )
+                .arg(
+                    arg!(<SUCCESS> "Address the binary traps at when every test passed")
+                        .value_parser(parse_hex)
+                )
+                .arg(
+                    arg!(--cycles <CYCLES> "Cycles to run for before giving up on a trap")
+                        .value_parser(clap::value_parser!(u64))
+                )
+                .arg_required_else_help(true),
+        )
 }
 
 fn parse_hex(start: &str) -> Result<u16, String> {
@@ -42,11 +72,24 @@ fn parse_hex(start: &str) -> Result<u16, String> {
     }
 }
 
-fn main() -> std::io::Result<()> {
-    let prefix_tree: Trie = gen_trie();
+fn parse_variant(cpu: &str) -> Result<Variant, String> {
+    match cpu.to_lowercase().as_str() {
+        "6502" => Ok(Variant::Nmos6502),
+        "65c02" => Ok(Variant::Cmos65C02),
+        "r65c02" => Ok(Variant::Rockwell65C02),
+        "w65c02" | "w65c02s" => Ok(Variant::Wdc65C02),
+        "2a03" | "2a07" => Ok(Variant::Ricoh2A03),
+        "65816" | "65c816" | "w65c816" | "w65c816s" => Ok(Variant::W65816),
+        _ => Err(format!("Unknown CPU variant: {cpu}")),
+    }
+}
 
+fn main() {
     let matches = cli().get_matches();
 
+    let variant: Variant = *matches.get_one::<Variant>("cpu").expect("Defaulted");
+    let table: OpcodeTable = gen_table(variant);
+
     match matches.subcommand() {
         // Executing the disassembler subcommand.
         Some(("disassemble", sub_matches)) => {
@@ -61,17 +104,56 @@ fn main() -> std::io::Result<()> {
 
             let data: Vec<u8> = match fs::read(path) {
                 Ok(data) => data,
-                Err(error) => panic!("Problem opening file: {error:?}")
+                Err(error) => {
+                    println!("Problem opening file: {error}");
+                    std::process::exit(1);
+                }
             };
         
-            disassembler(&data, start, &prefix_tree)?;
+            if let Err(error) = disassembler(&data, start, &table) {
+                println!("{error}");
+                std::process::exit(1);
+            }
         }
         // Emulator subcommand.
         Some(("emulate", _)) => {
-            emulator(&prefix_tree);
+            emulator(&table, variant);
+        }
+        // Test subcommand.
+        Some(("test", sub_matches)) => {
+            let path: &String = sub_matches.get_one::<String>("PATH").expect("Required");
+            let load: &u16 = sub_matches.get_one::<u16>("LOAD").expect("Required");
+            let start: &u16 = sub_matches.get_one::<u16>("START").expect("Required");
+            let success: &u16 = sub_matches.get_one::<u16>("SUCCESS").expect("Required");
+            let limit: u64 = sub_matches.get_one::<u64>("cycles").copied().unwrap_or(CYCLE_LIMIT);
+
+            let data: Vec<u8> = match fs::read(path) {
+                Ok(data) => data,
+                Err(error) => {
+                    println!("Problem opening file: {error}");
+                    std::process::exit(1);
+                }
+            };
+
+            match run_test(&table, variant, &data, *load, *start, *success, limit) {
+                Ok((Trap::Passed(pc), cycles)) => {
+                    println!("PASSED, trapped at 0x{:04X} after {} cycles", pc, cycles);
+                }
+                Ok((Trap::Failed(pc), cycles)) => {
+                    println!("FAILED, trapped at 0x{:04X} after {} cycles", pc, cycles);
+                    std::process::exit(1);
+                }
+                // A different exit code, as nothing actually failed yet.
+                Ok((Trap::Timeout(pc), cycles)) => {
+                    println!("TIMED OUT at 0x{:04X} after {} cycles without a trap", pc, cycles);
+                    std::process::exit(2);
+                }
+                Err(error) => {
+                    println!("{error}");
+                    std::process::exit(1);
+                }
+            }
         }
         _ => {unreachable!()}
     }
-
-    Ok(())
 }
\ No newline at end of file
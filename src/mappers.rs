@@ -0,0 +1,458 @@
+use crate::{
+    bus::{Bus, Ram},
+    error::EmulatorError,
+    savestate::{StateReader, StateWriter},
+};
+
+/*
+    Bank switching.
+
+    The 6502 can only see 64kb at once, so anything bigger is split into
+    banks that get swapped in and out of windows in the address space. A
+    write to a control register picks which bank a window shows. Each mapper
+    here is a `Bus` with RAM behind everything the banked memory doesn't
+    cover, so a core can be built straight on top of one.
+
+    * `SwitchedWindow`, a single window of ROM switched by writing the bank
+      number to a register. Usually 16kb or 8kb.
+    * `UxRom`, `CnRom` and `Mmc1`, the NES cartridge boards. The NES uses the
+      CHR banks for graphics, which the CPU never sees, so they're only kept
+      track of for whatever draws them.
+    * `Port6510`, the 6510's I/O port at $00/$01 switching the Commodore 64
+      ROMs in and out over RAM.
+
+    Writes to ROM go to the RAM underneath, and ROM images don't have to be
+    a whole number of banks, the last bank is padded with zeroes. Save states
+    keep the RAM and the registers, but not the ROM images, which are part
+    of the cartridge rather than its state.
+*/
+
+// A ROM image split into equal sized banks. Bank numbers past the end
+// wrap around, the way unconnected address lines do.
+pub struct Banks {
+    data: Vec<u8>,
+    size: usize,
+}
+
+impl Banks {
+    pub fn new(mut data: Vec<u8>, size: usize) -> Result<Self, EmulatorError> {
+        if data.is_empty() { return Err(EmulatorError::EmptyRom) }
+        if size == 0 { return Err(EmulatorError::ZeroBankSize) }
+
+        data.resize(data.len().div_ceil(size) * size, 0);
+
+        Ok(Self { data, size })
+    }
+
+    pub fn count(&self) -> usize {
+        self.data.len() / self.size
+    }
+
+    pub fn read(&self, bank: usize, offset: u16) -> u8 {
+        self.data[(bank % self.count()) * self.size + offset as usize % self.size]
+    }
+}
+
+// ROM banks switched into a window by writing the bank number to a register.
+pub struct SwitchedWindow {
+    ram: Ram,
+    rom: Banks,
+    window: u16, // Start of the window, which is one bank long
+    register: u16, // Address of the bank select register
+    bank: usize,
+}
+
+impl SwitchedWindow {
+    pub fn new(rom: Vec<u8>, size: usize, window: u16, register: u16) -> Result<Self, EmulatorError> {
+        if window as usize + size > 0x10000 {
+            return Err(EmulatorError::WindowOutOfRange(window))
+        }
+
+        Ok(Self { ram: Ram::new(), rom: Banks::new(rom, size)?, window, register, bank: 0 })
+    }
+
+    fn in_window(&self, address: u16) -> bool {
+        address >= self.window && ((address - self.window) as usize) < self.rom.size
+    }
+}
+
+impl Bus for SwitchedWindow {
+    fn read(&mut self, address: u16) -> u8 {
+        self.peek(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if address == self.register { self.bank = value as usize }
+
+        self.ram.write(address, value);
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        if self.in_window(address) {
+            self.rom.read(self.bank, address - self.window)
+        } else {
+            self.ram.peek(address)
+        }
+    }
+
+    fn save(&self, state: &mut StateWriter) {
+        self.ram.save(state);
+        self.save_registers(state);
+    }
+
+    fn restore(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        self.ram.restore(state)?;
+        self.restore_registers(state)
+    }
+
+    fn stored(&self, address: u16) -> Option<u8> {
+        self.ram.stored(address)
+    }
+
+    fn put_back(&mut self, address: u16, value: u8) {
+        self.ram.put_back(address, value);
+    }
+
+    fn save_registers(&self, state: &mut StateWriter) {
+        state.u32(self.bank as u32);
+    }
+
+    fn restore_registers(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        self.bank = state.u32()? as usize;
+
+        Ok(())
+    }
+}
+
+// NES UxROM. The 16kb bank at $8000 is switched by writing anywhere in ROM,
+// and the last bank is fixed at $C000.
+pub struct UxRom {
+    ram: Ram,
+    prg: Banks,
+    bank: usize,
+}
+
+impl UxRom {
+    pub fn new(prg: Vec<u8>) -> Result<Self, EmulatorError> {
+        Ok(Self { ram: Ram::new(), prg: Banks::new(prg, 0x4000)?, bank: 0 })
+    }
+}
+
+impl Bus for UxRom {
+    fn read(&mut self, address: u16) -> u8 {
+        self.peek(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if address >= 0x8000 { self.bank = value as usize }
+        else { self.ram.write(address, value) }
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        match address {
+            0x8000..=0xBFFF => self.prg.read(self.bank, address),
+            0xC000..=0xFFFF => self.prg.read(self.prg.count() - 1, address),
+            _ => self.ram.peek(address),
+        }
+    }
+
+    fn save(&self, state: &mut StateWriter) {
+        self.ram.save(state);
+        self.save_registers(state);
+    }
+
+    fn restore(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        self.ram.restore(state)?;
+        self.restore_registers(state)
+    }
+
+    // Writes to ROM only reach the registers.
+    fn stored(&self, address: u16) -> Option<u8> {
+        if address < 0x8000 { self.ram.stored(address) } else { None }
+    }
+
+    fn put_back(&mut self, address: u16, value: u8) {
+        self.ram.put_back(address, value);
+    }
+
+    fn save_registers(&self, state: &mut StateWriter) {
+        state.u32(self.bank as u32);
+    }
+
+    fn restore_registers(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        self.bank = state.u32()? as usize;
+
+        Ok(())
+    }
+}
+
+// NES CNROM. Up to 32kb of fixed PRG ROM at $8000, a 16kb image is mirrored.
+// Writing anywhere in ROM switches the 8kb CHR bank.
+pub struct CnRom {
+    ram: Ram,
+    prg: Banks,
+    chr_bank: u8,
+}
+
+impl CnRom {
+    pub fn new(prg: Vec<u8>) -> Result<Self, EmulatorError> {
+        Ok(Self { ram: Ram::new(), prg: Banks::new(prg, 0x4000)?, chr_bank: 0 })
+    }
+
+    pub fn chr_bank(&self) -> u8 {
+        self.chr_bank
+    }
+}
+
+impl Bus for CnRom {
+    fn read(&mut self, address: u16) -> u8 {
+        self.peek(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if address >= 0x8000 { self.chr_bank = value & 0b11 }
+        else { self.ram.write(address, value) }
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => self.prg.read(((address - 0x8000) / 0x4000) as usize, address),
+            _ => self.ram.peek(address),
+        }
+    }
+
+    fn save(&self, state: &mut StateWriter) {
+        self.ram.save(state);
+        self.save_registers(state);
+    }
+
+    fn restore(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        self.ram.restore(state)?;
+        self.restore_registers(state)
+    }
+
+    // Writes to ROM only reach the registers.
+    fn stored(&self, address: u16) -> Option<u8> {
+        if address < 0x8000 { self.ram.stored(address) } else { None }
+    }
+
+    fn put_back(&mut self, address: u16, value: u8) {
+        self.ram.put_back(address, value);
+    }
+
+    fn save_registers(&self, state: &mut StateWriter) {
+        state.u8(self.chr_bank);
+    }
+
+    fn restore_registers(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        self.chr_bank = state.u8()?;
+
+        Ok(())
+    }
+}
+
+// NES MMC1. Registers are loaded one bit at a time through a shift register,
+// five writes to ROM with the data in bit 0. The address of the fifth write
+// picks the register: control at $8000, CHR banks at $A000 and $C000, and the
+// PRG bank at $E000. Writing a value with bit 7 set resets the shift register.
+pub struct Mmc1 {
+    ram: Ram, // Includes the 8kb of PRG RAM at $6000
+    prg: Banks,
+    shift: u8, // Starts as 0b10000, a full register has shifted the marker bit out
+    control: u8,
+    chr_banks: [u8; 2],
+    prg_bank: u8,
+}
+
+impl Mmc1 {
+    pub fn new(prg: Vec<u8>) -> Result<Self, EmulatorError> {
+        Ok(Self {
+            ram: Ram::new(),
+            prg: Banks::new(prg, 0x4000)?,
+            shift: 0b10000,
+            control: 0b01100, // Powers up with the last bank fixed at $C000
+            chr_banks: [0, 0],
+            prg_bank: 0,
+        })
+    }
+
+    pub fn chr_banks(&self) -> [u8; 2] {
+        self.chr_banks
+    }
+
+    // The 16kb PRG bank at an address in ROM.
+    fn prg_bank(&self, address: u16) -> usize {
+        let bank: usize = (self.prg_bank & 0x0F) as usize;
+        let high: bool = address >= 0xC000;
+
+        match (self.control >> 2) & 0b11 {
+            0 | 1 => (bank & !1) | high as usize, // 32kb switched as a pair
+            2 => if high { bank } else { 0 }, // First bank fixed at $8000
+            _ => if high { self.prg.count() - 1 } else { bank }, // Last bank fixed at $C000
+        }
+    }
+}
+
+impl Bus for Mmc1 {
+    fn read(&mut self, address: u16) -> u8 {
+        self.peek(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if address < 0x8000 { return self.ram.write(address, value) }
+
+        if value & 0x80 != 0 {
+            self.shift = 0b10000;
+            self.control |= 0b01100;
+            return
+        }
+
+        let full: bool = self.shift & 1 != 0;
+        self.shift = (self.shift >> 1) | ((value & 1) << 4);
+
+        if full {
+            match (address >> 13) & 0b11 {
+                0 => self.control = self.shift,
+                1 => self.chr_banks[0] = self.shift,
+                2 => self.chr_banks[1] = self.shift,
+                _ => self.prg_bank = self.shift,
+            }
+
+            self.shift = 0b10000;
+        }
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        match address {
+            0x8000..=0xFFFF => self.prg.read(self.prg_bank(address), address),
+            _ => self.ram.peek(address),
+        }
+    }
+
+    fn save(&self, state: &mut StateWriter) {
+        self.ram.save(state);
+        self.save_registers(state);
+    }
+
+    fn restore(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        self.ram.restore(state)?;
+        self.restore_registers(state)
+    }
+
+    // Writes to ROM only reach the registers.
+    fn stored(&self, address: u16) -> Option<u8> {
+        if address < 0x8000 { self.ram.stored(address) } else { None }
+    }
+
+    fn put_back(&mut self, address: u16, value: u8) {
+        self.ram.put_back(address, value);
+    }
+
+    fn save_registers(&self, state: &mut StateWriter) {
+        state.u8(self.shift);
+        state.u8(self.control);
+        state.bytes(&self.chr_banks);
+        state.u8(self.prg_bank);
+    }
+
+    fn restore_registers(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        self.shift = state.u8()?;
+        self.control = state.u8()?;
+        self.chr_banks.copy_from_slice(state.bytes(2)?);
+        self.prg_bank = state.u8()?;
+
+        Ok(())
+    }
+}
+
+// The 6510's built-in I/O port as wired up in the Commodore 64. $00 is the
+// data direction register and $01 the port. Bits 0-2 (LORAM, HIRAM and
+// CHAREN) choose between RAM and the BASIC ROM at $A000, the KERNAL ROM at
+// $E000 and the character ROM at $D000. Bits set as inputs are pulled up.
+// There's no I/O behind $D000, so when the I/O area is selected it's RAM.
+pub struct Port6510 {
+    ram: Ram,
+    basic: Vec<u8>,
+    kernal: Vec<u8>,
+    chargen: Vec<u8>,
+    direction: u8,
+    port: u8,
+}
+
+impl Port6510 {
+    pub fn new(basic: Vec<u8>, kernal: Vec<u8>, chargen: Vec<u8>) -> Result<Self, EmulatorError> {
+        for (name, rom, size) in [("BASIC", &basic, 0x2000), ("KERNAL", &kernal, 0x2000), ("character", &chargen, 0x1000)] {
+            if rom.len() != size {
+                return Err(EmulatorError::RomSize { rom: name, expected: size, found: rom.len() })
+            }
+        }
+
+        Ok(Self { ram: Ram::new(), basic, kernal, chargen, direction: 0, port: 0 })
+    }
+
+    // What the port pins are driving, inputs read high.
+    fn pins(&self) -> u8 {
+        (self.port & self.direction) | !self.direction
+    }
+}
+
+impl Bus for Port6510 {
+    fn read(&mut self, address: u16) -> u8 {
+        self.peek(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x0000 => self.direction = value,
+            0x0001 => self.port = value,
+            _ => {}
+        }
+
+        // The RAM underneath is written either way.
+        self.ram.write(address, value);
+    }
+
+    fn peek(&self, address: u16) -> u8 {
+        let pins: u8 = self.pins();
+        let (loram, hiram, charen) = (pins & 0b001 != 0, pins & 0b010 != 0, pins & 0b100 != 0);
+
+        match address {
+            0x0000 => self.direction,
+            0x0001 => pins,
+            0xA000..=0xBFFF if loram && hiram => self.basic[(address - 0xA000) as usize],
+            0xD000..=0xDFFF if (loram || hiram) && !charen => self.chargen[(address - 0xD000) as usize],
+            0xE000..=0xFFFF if hiram => self.kernal[(address - 0xE000) as usize],
+            _ => self.ram.peek(address),
+        }
+    }
+
+    fn save(&self, state: &mut StateWriter) {
+        self.ram.save(state);
+        self.save_registers(state);
+    }
+
+    fn restore(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        self.ram.restore(state)?;
+        self.restore_registers(state)
+    }
+
+    fn stored(&self, address: u16) -> Option<u8> {
+        self.ram.stored(address)
+    }
+
+    fn put_back(&mut self, address: u16, value: u8) {
+        self.ram.put_back(address, value);
+    }
+
+    fn save_registers(&self, state: &mut StateWriter) {
+        state.u8(self.direction);
+        state.u8(self.port);
+    }
+
+    fn restore_registers(&mut self, state: &mut StateReader) -> Result<(), EmulatorError> {
+        self.direction = state.u8()?;
+        self.port = state.u8()?;
+
+        Ok(())
+    }
+}
@@ -1,424 +1,210 @@
-use crate::system::Core;
-use crate::addressing::{zero_page, zero_page_x, zero_page_y,
-                        absolute, absolute_x, absolute_y,
-                        indirect, x_indirect, indirect_y, 
-                        relative};
-
-enum Value {
-    U8(u8),
-    U16(u16),
-}
+use crate::{status::{Flag, Status}, system::Core};
+use crate::addressing::{length, operand, operand_address, read_operand, Target};
+
+// Read-modify-write on the operand, in memory or the accumulator. Returns
+// the value read and the result written back.
+fn modify<F: FnOnce(u8) -> u8>(core: &mut Core, operation: F) -> (u8, u8) {
+    let target: Target = operand(core);
+
+    let value: u8 = match target {
+        Target::Accumulator => core.acc,
+        Target::Address(address) => core.read(address),
+        Target::Implied => unreachable!("{:?}", core.info)
+    };
 
-impl Value {
-    fn get_u8(&self) -> u8 {
-        match self {
-            Value::U8(value) => *value,
-            _ => unreachable!(),
-        }
+    let result: u8 = operation(value);
+
+    match target {
+        Target::Accumulator => core.acc = result,
+        Target::Address(address) => core.write(address, result),
+        Target::Implied => {}
     }
 
-    fn bcd_add(&self, other: &Self, borrow: u8) -> (u8, bool) {
-        let a: u8 = self.get_u8();
-        let b: u8 = other.get_u8();
-        let mut wrapped: bool = false;
+    (value, result)
+}
 
-        let mut result: u8 = a + b;
-        let mut adjust: u8 = 0;
-        
-        if (result & 0x0F) > 9 {
-            adjust |= 0x06;
-        }
-        
-        if result > 0x99 || (result & 0xF0) > 0x90 {
-            adjust |= 0x60;
-        }
-        
-        result = result.wrapping_add(adjust);
-
-        result += borrow;
-        
-        if result > 99 {
-            result = result - 99;
-            wrapped = true;
-        }
-        
-        (result, wrapped)
-    }
+// The arithmetic behind ADC, shared with the undocumented RRA opcode.
+fn add_with_carry(core: &mut Core, value: u8) {
+    // Check for the decimal mode flag, as it means we have to work with binary coded decimal.
+    // Parts without decimal mode keep the flag but always do binary arithmetic.
+    let decimal: bool = core.stat.get(Flag::Decimal) && core.variant.has_decimal_mode();
 
-    fn bcd_sub(&self, other: &Self, borrow: u8) -> (u8, bool) {
-        let a: u8 = self.get_u8();
-        let b: u8 = other.get_u8();
-        let mut wrapped: bool = false;
-        
-        let b_compliment: u8 = 99 - b;
-        let a_b_compliment: u8 = b_compliment + a;
+    let borrow: u8 = core.stat.carry(); // Equal to carry bit
 
-        let msb: u8 = a_b_compliment >> 7;
+    // The 65C02 spends an extra cycle correcting the flags in decimal mode.
+    if decimal && core.variant.is_cmos() { core.cycles += 1 }
 
-        let result: u8 = a_b_compliment + msb + borrow;
+    let binary: u16 = (core.acc as u16) + (value as u16) + (borrow as u16);
 
-        if result > a && result > b {
-            wrapped = true;
-        }
+    // Overflow flag logic, the operands share a sign that the result doesn't:
+    let overflow = |result: u16| (core.acc ^ result as u8) & (value ^ result as u8) & 0x80 != 0;
 
-        (result, wrapped)
-    }
-}
+    let (result, carry, v_flag, nz_source): (u8, bool, bool, u8) = if decimal {
+        // Add the low digits, adjusting into the high digit like the adder does.
+        // Invalid BCD digits go through the same adjustment, matching real parts.
+        let mut low: u16 = ((core.acc & 0x0F) as u16) + ((value & 0x0F) as u16) + (borrow as u16);
+        if low >= 0x0A { low = ((low + 0x06) & 0x0F) + 0x10 }
 
-pub fn adc(core: &mut Core) -> &mut Core {
-    // Check for the decimal mode flag, as it means we have to work with binary coded decimal.
-    let decimal: bool = if (core.stat >> 3) & 0b1 == 0 { false } else { true };
+        // N and V are taken from this sum, before the high digit is adjusted.
+        let mut sum: u16 = ((core.acc & 0xF0) as u16) + ((value & 0xF0) as u16) + low;
+        let v_flag: bool = overflow(sum);
+        let intermediate: u8 = sum as u8;
+        if sum >= 0xA0 { sum += 0x60 }
 
-    let value: u8;
-    let inc: u16;
+        // The NMOS part takes Z from the binary sum and N from the intermediate one,
+        // the 65C02 fixed both to follow the decimal result.
+        let nz_source: u8 = if core.variant.is_cmos() { sum as u8 } else { intermediate };
 
-    match core.ir {
-        0x69_u8 => { // ADC IMM
-            value = core.memory[core.pc as usize + 1];
-            inc = 2;
-        }
-        0x65_u8 => { // ADC ZP
-            let zp: u8 = zero_page(core);
-            value = core.memory[zp as usize];
-            inc = 2;
-        }
-        0x75_u8 => { // ADC ZPX
-            let zpx: u8 = zero_page_x(core);
-            value = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0x6d_u8 => { // ADC ABS
-            let abs: u16 = absolute(core);
-            value = core.memory[abs as usize];
-            inc = 3;
-        }
-        0x7d_u8 => { // ADC ABSX
-            let absx: u16 = absolute_x(core);
-            value = core.memory[absx as usize];
-            inc = 3;
-        }
-        0x79_u8 => { // ADC ABSY
-            let absx: u16 = absolute_y(core);
-            value = core.memory[absx as usize];
-            inc = 3;
-        }
-        0x61_u8 => { // ADC X_IND
-            value = x_indirect(core);
-            inc = 2;
-        }
-        0x71_u8 => { // ADC IND_Y
-            value = indirect_y(core);
-            inc = 2;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+        (sum as u8, sum >= 0x100, v_flag, nz_source)
+    } else {
+        (binary as u8, binary > 0xFF, overflow(binary), binary as u8)
+    };
 
-    let borrow: u8 = if core.stat & 0b1 != 0 { 1 } else { 0 }; // Equal to carry bit
+    core.acc = result;
 
-    if decimal {
-        let bcd_result: (u8, bool) = Value::bcd_add(&Value::U8(core.acc), &Value::U8(value), borrow);
+    core.stat.set(Flag::Carry, carry);
 
-        core.acc = bcd_result.0;
+    core.stat.set(Flag::Overflow, v_flag);
 
-        if bcd_result.1 { core.stat |= 0b00000001 } // set carry flag
-        else { core.stat &= !0b00000001 } // clear carry flag
-    } else {
-        let result: i16 = (core.acc as i16) + (value as i16) + (borrow as i16);
-        let acc_sign: bool = (core.acc >> 7) & 0b1 != 0; // Sign used for overflow check before addition.
-        
-        core.acc = result as u8; // Clamp to 8 bits and store result
+    // Only the NMOS decimal path splits where Z and N come from.
+    let z_source: u8 = if decimal && !core.variant.is_cmos() { binary as u8 } else { nz_source };
 
-        if result > 0xff { core.stat |= 0b00000001 } // set carry flag
-        else { core.stat &= !0b00000001 } // clear carry flag
-    
-        // Overflow flag logic:
-        let mem_sign: bool = (value >> 7) & 0b1 != 0;
-        let res_sign: bool = (core.acc >> 7) & 0b1 != 0;
-    
-        if acc_sign == mem_sign && acc_sign != res_sign { core.stat |= 0b01000000 } // set overflow flag
-        else { core.stat &= !0b01000000 } // clear overflow flag        
-    };
+    core.stat.set(Flag::Zero, z_source == 0);
+
+    core.stat.set(Flag::Negative, ((nz_source >> 7) & 0b1) == 0b1);
+}
 
-    if core.acc == 0 { core.stat |= 0b00000010 } // set zero flag
-    else { core.stat &= !0b00000010 } // clear zero flag
+pub fn adc(core: &mut Core) -> &mut Core {
+    let value: u8 = read_operand(core);
 
-    if ((core.acc >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // set negative flag
-    else { core.stat &= !0b10000000 } // clear negative flag
+    add_with_carry(core, value);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 }
 
 pub fn and(core: &mut Core) -> &mut Core {
-    let value: u8;
-    let inc: u16;
-
-    match core.ir {
-        0x29_u8 => { // AND IMM
-            value = core.memory[core.pc as usize + 1];
-            inc = 2;
-        }
-        0x25_u8 => { // AND ZP
-            let zp: u8 = zero_page(core);
-            value = core.memory[zp as usize];
-            inc = 2;
-        }
-        0x35_u8 => { // AND ZPX
-            let zpx: u8 = zero_page_x(core);
-            value = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0x2d_u8 => { // AND ABS
-            let abs: u16 = absolute(core);
-            value = core.memory[abs as usize];
-            inc = 3;
-        }
-        0x3d_u8 => { // AND ABSX
-            let absx: u16 = absolute_x(core);
-            value = core.memory[absx as usize];
-            inc = 3;
-        }
-        0x39_u8 => { // AND ABSY
-            let absy: u16 = absolute_y(core);
-            value = core.memory[absy as usize];
-            inc = 3;
-        }
-        0x21_u8 => { // AND X_IND
-            value = x_indirect(core);
-            inc = 2;
-        }
-        0x31_u8 => { // AND IND_Y
-            value = indirect_y(core);
-            inc = 2;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    let value: u8 = read_operand(core);
 
     core.acc &= value;
 
-    if core.acc == 0x00_u8 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // clear zero flag
-
-    if ((core.acc >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // clear negative flag
+    core.stat.set_zero_negative(core.acc);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
 pub fn asl(core: &mut Core) -> &mut Core {
-    let new_carry: bool;
-    let inc: u16;
-    let result: u8;
-
-    match core.ir {
-        0xa0_u8 => { // ASL ACC
-            new_carry = if (core.acc >> 7) & 0b1 != 0 { true } else { false };
-            core.acc <<= 1;
-            result = core.acc;
-            inc = 1;
-        }
-        0x06_u8 => { // ASL ZP
-            let zp: u8 = zero_page(core);
-            new_carry = if (core.memory[zp as usize] >> 7) & 0b1 != 0 { true } else { false };
-            core.memory[zp as usize] <<= 1;
-            result = core.memory[zp as usize];
-            inc = 2;
-        }
-        0x16_u8 => { // ASL ZPX
-            let zpx: u8 = zero_page_x(core);
-            new_carry = if (core.memory[zpx as usize] >> 7) & 0b1 != 0 { true } else { false };
-            core.memory[zpx as usize] <<= 1;
-            result = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0x0e_u8 => { // ASL ABS
-            let abs: u16 = absolute(core);
-            new_carry = if (core.memory[abs as usize] >> 7) & 0b1 != 0 { true } else { false };
-            core.memory[abs as usize] <<= 1;
-            result = core.memory[abs as usize];
-            inc = 3;
-        }
-        0x1e_u8 => { // ASL ABSX
-            let absx: u16 = absolute_x(core);
-            new_carry = if (core.memory[absx as usize] >> 7) & 0b1 != 0 { true } else { false };
-            core.memory[absx as usize] <<= 1;
-            result = core.memory[absx as usize];
-            inc = 3;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    let (operand, result): (u8, u8) = modify(core, |operand| operand << 1);
+    let new_carry: bool = (operand >> 7) & 0b1 != 0;
 
-    match result {
-        0x00 => { core.stat |= 0b00000010 } // Set zero flag
-        _ => { core.stat &= !0b00000010 } // Clear zero flag
-    }
+    core.stat.set_zero_negative(result);
 
-    if (result >> 7) & 0b1 == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+    core.stat.set(Flag::Carry, new_carry);
 
-    match new_carry {
-        true => { core.stat |= 0b00000001 } // Set carry flag
-        false => { core.stat &= !0b00000001 } // Clear carry flag
-    }
-    
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
-pub fn bcc(core: &mut Core) -> &mut Core {
-    // Grab signed offset safely casted as a signed 32 bit integer to handle overflow safely
-    // Add this value to program counter casted as an i32 safely to handle overflow
-    if core.stat & 0b1 == 0 {
-        core.pc = ((core.pc as i32) + (relative(core) as i32)) as u16;
-    }
+// Shared logic for the branch functions.
+// A taken branch costs one extra cycle, plus another if the target is on a
+// different page to the instruction following the branch.
+fn branch(core: &mut Core, condition: bool) -> &mut Core {
+    if condition {
+        core.pc = operand_address(core);
 
-    core.pc += 2;
+        core.cycles += 1;
+
+        if core.page_crossed { core.cycles += 1; }
+    } else {
+        core.pc = core.pc.wrapping_add(2);
+    }
 
     core
+}
+
+pub fn bcc(core: &mut Core) -> &mut Core {
+    let condition: bool = !core.stat.get(Flag::Carry);
+
+    branch(core, condition)
 } 
 
 pub fn bcs(core: &mut Core) -> &mut Core {
-    // Grab signed offset safely casted as a signed 32 bit integer to handle overflow safely
-    // Add this value to program counter casted as an i32 safely to handle overflow
-    if core.stat & 0b1 == 1 {
-        core.pc = ((core.pc as i32) + (relative(core) as i32)) as u16;
-    }
-    
-    core.pc += 2;
+    let condition: bool = core.stat.get(Flag::Carry);
 
-    core
+    branch(core, condition)
 } 
 
 pub fn beq(core: &mut Core) -> &mut Core {
-    // Grab signed offset safely casted as a signed 32 bit integer to handle overflow safely
-    // Add this value to program counter casted as an i32 safely to handle overflow
-    if (core.stat >> 1) & 0b1 == 1 {
-        core.pc = ((core.pc as i32) + (relative(core) as i32)) as u16;
-    }
-    
-    core.pc += 2;
+    let condition: bool = core.stat.get(Flag::Zero);
 
-    core
+    branch(core, condition)
 } 
 
 pub fn bmi(core: &mut Core) -> &mut Core {
-    // Grab signed offset safely casted as a signed 32 bit integer to handle overflow safely
-    // Add this value to program counter casted as an i32 safely to handle overflow
-    if (core.stat >> 7) & 0b1 == 1 {
-        core.pc = ((core.pc as i32) + (relative(core) as i32)) as u16;
-    }
-    
-    core.pc += 2;
+    let condition: bool = core.stat.get(Flag::Negative);
 
-    core
+    branch(core, condition)
 } 
 
 pub fn bne(core: &mut Core) -> &mut Core {
-    // Grab signed offset safely casted as a signed 32 bit integer to handle overflow safely
-    // Add this value to program counter casted as an i32 safely to handle overflow
-    if (core.stat >> 1) & 0b1 == 0 {
-        core.pc = ((core.pc as i32) + (relative(core) as i32)) as u16;
-    }
-    
-    core.pc += 2;
+    let condition: bool = !core.stat.get(Flag::Zero);
 
-    core
+    branch(core, condition)
 } 
 
 pub fn bpl(core: &mut Core) -> &mut Core {
-    // Grab signed offset safely casted as a signed 32 bit integer to handle overflow safely
-    // Add this value to program counter casted as an i32 safely to handle overflow
-    if (core.stat >> 7) & 0b1 == 0 {
-        core.pc = ((core.pc as i32) + (relative(core) as i32)) as u16;
-    }
-    
-    core.pc += 2;
+    let condition: bool = !core.stat.get(Flag::Negative);
 
-    core
+    branch(core, condition)
 } 
 
 pub fn bvc(core: &mut Core) -> &mut Core {
-    // Grab signed offset safely casted as a signed 32 bit integer to handle overflow safely
-    // Add this value to program counter casted as an i32 safely to handle overflow
-    if (core.stat >> 6) & 0b1 == 0 {
-        core.pc = ((core.pc as i32) + (relative(core) as i32)) as u16;
-    }
-    
-    core.pc += 2;
+    let condition: bool = !core.stat.get(Flag::Overflow);
 
-    core
+    branch(core, condition)
 } 
 
 pub fn bvs(core: &mut Core) -> &mut Core {
-        // Grab signed offset safely casted as a signed 32 bit integer to handle overflow safely
-    // Add this value to program counter casted as an i32 safely to handle overflow
-    if (core.stat >> 6) & 0b1 == 1 {
-        core.pc = ((core.pc as i32) + (relative(core) as i32)) as u16;
-    }
-    
-    core.pc += 2;
+    let condition: bool = core.stat.get(Flag::Overflow);
 
-    core
+    branch(core, condition)
 } 
 
 pub fn bit(core: &mut Core) -> &mut Core {
-    let negative: bool;
-    let overflow: bool;
-    let result: u8;
-
-    match core.ir {
-        0x24 => { // BIT ZP
-            let zp: u8 = zero_page(core);
-            result = core.acc & core.memory[zp as usize];
-            negative = if (core.memory[zp as usize] >> 7) & 0b1 == 1 { true } else { false };
-            overflow = if (core.memory[zp as usize] >> 6) & 0b1 == 1 { true } else { false };
-        }
-        0x2c => { // BIT ABS
-            let abs: u16 = absolute(core);
-            result = core.acc & core.memory[abs as usize];
-            negative = if (core.memory[abs as usize] >> 7) & 0b1 == 1 { true } else { false };
-            overflow = if (core.memory[abs as usize] >> 6) & 0b1 == 1 { true } else { false };
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    let value: u8 = read_operand(core);
+
+    core.stat.set(Flag::Zero, core.acc & value == 0x00);
 
-    if result == 0x00 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
+    // The immediate form has no memory operand, so it only affects the zero flag.
+    if core.ir != 0x89 {
+        core.stat.set(Flag::Negative, (value >> 7) & 0b1 == 1);
 
-    if negative { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+        core.stat.set(Flag::Overflow, (value >> 6) & 0b1 == 1);
+    }
 
-    if overflow { core.stat |= 0b01000000 } // Set overflow flag
-    else { core.stat &= !0b01000000 } // Clear overflow flag
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
 pub fn brk(core: &mut Core) -> &mut Core {
-    // Grab the full stack pointer address.
-    let stack_address: usize = 0x0100 | (core.sp as u16) as usize;
-
-    // Get the bytes of the return address, PC + 2.
-    let spcl: u8 = (core.pc + 2 & 0xFF00) as u8;
-    let spch: u8 = (core.pc + 2 >> 8) as u8;
+    // Store the return address, PC + 2, and the status register with the
+    // break flag set in the stack.
+    core.push_word(core.pc.wrapping_add(2));
+    core.push(core.stat.pushed(true));
 
-    // Set the break flag.
-    core.stat = core.stat | 0b00010000;
+    core.stat.set(Flag::Interrupt, true);
 
-    // Store the status register and return address in the stack.
-    core.memory[stack_address - 2..=stack_address]
-        .copy_from_slice(&[core.stat, spcl, spch]);
-
-    // Descend stack pointer.
-    core.sp -= 3;
+    // The 65C02 clears decimal mode on entering the handler.
+    if core.variant.is_cmos() { core.stat.set(Flag::Decimal, false) }
 
     // Set PC to interrupt vector. Just symbolic for now.
-    let pcl: u16 = core.memory[0xfffe] as u16;
-    let pch: u16 = (core.memory[0xffff] as u16) << 8;
+    let pcl: u16 = core.read(0xfffe) as u16;
+    let pch: u16 = (core.read(0xffff) as u16) << 8;
 
     core.pc = pch | pcl;
 
@@ -426,162 +212,118 @@ pub fn brk(core: &mut Core) -> &mut Core {
 } 
 
 pub fn clc(core: &mut Core) -> &mut Core {
-    core.stat &= !0b00000001; // Clear carry flag
+    core.stat.set(Flag::Carry, false);
 
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
 
 pub fn cld(core: &mut Core) -> &mut Core {
-    core.stat = core.stat & 0b11110111; // Clear decimal flag
+    core.stat.set(Flag::Decimal, false);
 
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
 
 pub fn cli(core: &mut Core) -> &mut Core {
-    core.stat = core.stat & 0b11111011; // Clear interrupt flag
+    core.stat.set(Flag::Interrupt, false);
 
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
 
 pub fn clv(core: &mut Core) -> &mut Core {
-    core.stat = core.stat & 0b10111111; // Clear overflow flag
+    core.stat.set(Flag::Overflow, false);
 
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
 
 pub fn nop(core: &mut Core) -> &mut Core {
-    core.pc += 1;
+    // Undocumented NOPs still fetch their operands, and the ABSX ones can
+    // take the page crossing cycle.
+    operand(core);
+
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
 pub fn pha(core: &mut Core) -> &mut Core {
     // Push accumulator to stack.
-    core.memory[(0x100 | core.sp as u16) as usize] = core.acc;
+    core.push(core.acc);
 
-    core.sp -= 1; // Descend stack pointer
-
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
 
 pub fn pla(core: &mut Core) -> &mut Core {
     // Set accumulator to value from the stack
-    core.acc = core.memory[(0x100 | (core.sp as u16 + 1)) as usize];
-
-    // Wipe the value from the stack
-    core.memory[(0x100 | (core.sp as u16 + 1)) as usize] = 0x00;
-
-    // Increment stack pointer
-    core.sp += 1;
-
-    if core.acc == 0x00_u8 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // clear zero flag
+    core.acc = core.pull();
 
-    if ((core.acc >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // clear negative flag
+    core.stat.set_zero_negative(core.acc);
 
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
 
 pub fn php(core: &mut Core) -> &mut Core {
-    // Set break flag and push status to stack.
-    core.memory[(0x100 | core.sp as u16) as usize] = core.stat | 0b00010000;
+    // Push status to stack, with the break flag set.
+    core.push(core.stat.pushed(true));
 
-    core.sp -= 1; // Descend stack pointer
-
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
     
     core
 } 
 
 pub fn plp(core: &mut Core) -> &mut Core {
-    // Set status to value from the stack
-    core.stat = core.memory[(0x100 | (core.sp as u16 + 1)) as usize];
-
-    // Wipe the value from the stack
-    core.memory[(0x100 | (core.sp as u16 + 1)) as usize] = 0x00;
+    // Set status to value from the stack, B and bit 5 are ignored.
+    core.stat = Status::pulled(core.pull());
 
-    // Increment stack pointer
-    core.sp += 1;
-
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
 
 pub fn rti(core: &mut Core) -> &mut Core {
-    let stack_address: usize = 0x0100 | (core.sp as u16) as usize;
-
-    let sr: u8 = core.memory[stack_address + 1];
-    core.stat = sr;
-    core.stat &= !0b00110000; // Clear break and unused flags
-
-    core.memory[stack_address + 1] = 0x00; // Wipe the value from the stack
-
-    core.sp += 1; // Ascend stack pointer
-
-    let pcl: u16 = core.memory[stack_address + 1] as u16;
-    let pch: u16 = (core.memory[stack_address + 2] as u16) << 8;
+    core.stat = Status::pulled(core.pull());
 
     // Set the new PC value.
-    core.pc = pch | pcl;
-
-    core.memory[(stack_address + 1)..=(stack_address + 2)]
-        .copy_from_slice(&[0x00, 0x00]); // Wipe the value from the stack
-
-    core.sp += 2; // Ascend stack pointer
+    core.pc = core.pull_word();
 
     core
 } 
 
 pub fn rts(core: &mut Core) -> &mut Core {
-    // Get the new PC value from the stack.
-    let stack_address: usize = 0x0100 | (core.sp as u16) as usize;
-
-    let pcl: u16 = core.memory[stack_address + 1] as u16;
-    let pch: u16 = (core.memory[stack_address + 2] as u16) << 8;
-
-    // Set the new PC value.
-    core.pc = pch | pcl;
-
-    // Adjust stack pointer to ascend with the stack.
-    core.sp += 2;
-
-    core.pc += 1;
+    // Get the new PC value from the stack, it's the return address - 1.
+    core.pc = core.pull_word().wrapping_add(1);
 
     core
 } 
 
 pub fn sec(core: &mut Core) -> &mut Core {
-    // Set carry flag
-    core.stat |= 0b00000001;
-    core.pc += 1;
+    core.stat.set(Flag::Carry, true);
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
 
 pub fn sed(core: &mut Core) -> &mut Core {
-    core.stat |= 0b00001000;
-    core.pc += 1;
+    core.stat.set(Flag::Decimal, true);
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
 
 pub fn sei(core: &mut Core) -> &mut Core {
-    core.stat |= 0b00000100; // Set interrupt flag
-    core.pc += 1;
+    core.stat.set(Flag::Interrupt, true);
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
@@ -589,13 +331,9 @@ pub fn sei(core: &mut Core) -> &mut Core {
 pub fn tax(core: &mut Core) -> &mut Core {
     core.ix = core.acc;
 
-    if core.ix == 0 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
+    core.stat.set_zero_negative(core.ix);
 
-    if ((core.ix >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
-
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
@@ -603,13 +341,9 @@ pub fn tax(core: &mut Core) -> &mut Core {
 pub fn txa(core: &mut Core) -> &mut Core {
     core.acc = core.ix;
 
-    if core.acc == 0 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
-
-    if ((core.acc >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+    core.stat.set_zero_negative(core.acc);
 
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
@@ -617,13 +351,9 @@ pub fn txa(core: &mut Core) -> &mut Core {
 pub fn tay(core: &mut Core) -> &mut Core {
     core.iy = core.acc;
 
-    if core.iy == 0 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
+    core.stat.set_zero_negative(core.iy);
 
-    if ((core.iy >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
-
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
@@ -631,13 +361,9 @@ pub fn tay(core: &mut Core) -> &mut Core {
 pub fn tya(core: &mut Core) -> &mut Core {
     core.acc = core.iy;
 
-    if core.acc == 0 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
-
-    if ((core.acc >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+    core.stat.set_zero_negative(core.acc);
 
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
@@ -645,13 +371,9 @@ pub fn tya(core: &mut Core) -> &mut Core {
 pub fn tsx(core: &mut Core) -> &mut Core {
     core.ix = core.sp;
 
-    if core.ix == 0 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
-
-    if ((core.ix >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+    core.stat.set_zero_negative(core.ix);
 
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
@@ -659,202 +381,71 @@ pub fn tsx(core: &mut Core) -> &mut Core {
 pub fn txs(core: &mut Core) -> &mut Core {
     core.sp = core.ix;
 
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
 
 pub fn cmp(core: &mut Core) -> &mut Core {
-    // Check for the decimal mode flag, as it means we have to work with binary coded decimal.
-    let _decimal: bool = if (core.stat >> 3) & 0b1 == 0 { false } else { true };
-
-    let value: u8;
-    let inc: u16;
-
-    match core.ir {
-        0xC9 => { // CMP IMM
-            value = core.memory[core.pc as usize + 1];
-            inc = 2;
-        }
-        0xC5 => { // CMP ZP
-            let zp: u8 = zero_page(core);
-            value = core.memory[zp as usize];
-            inc = 2;
-        }
-        0xD5 => { // CMP ZPX
-            let zpx: u8 = zero_page_x(core);
-            value = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0xCD => { // CMP ABS
-            let abs: u16 = absolute(core);
-            value = core.memory[abs as usize];
-            inc = 3;
-        }
-        0xDD => { // CMP ABSX
-            let absx: u16 = absolute_x(core);
-            value = core.memory[absx as usize];
-            inc = 3;
-        }
-        0xD9 => { // CMP ABSY
-            let absy: u16 = absolute_y(core);
-            value = core.memory[absy as usize];
-            inc = 3;
-        }
-        0xC1 => { // CMP X_IND
-            value = x_indirect(core);
-            inc = 2;
-        }
-        0xD1 => { // CMP IND_Y
-            value = indirect_y(core);
-            inc = 2;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    let value: u8 = read_operand(core);
 
     // Calculate A - M, zero extending both values:
     let result: i16 = (core.acc as i16) - (value as i16);
 
     // Check the result and set flags:
-    if core.acc >= value { core.stat |= 0b00000001 } // Carry flag
-    else { core.stat &= !0b00000001 } // Clear carry flag
+    core.stat.set(Flag::Carry, result >= 0);
 
-    if (result & 0xFF) == 0 { core.stat |= 0b00000010 } // Zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
+    core.stat.set(Flag::Zero, (result & 0xFF) == 0);
 
-    if (result & 0x80) != 0 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+    core.stat.set(Flag::Negative, (result & 0x80) != 0);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
 pub fn cpx(core: &mut Core) -> &mut Core {
-    // Check for the decimal mode flag, as it means we have to work with binary coded decimal.
-    let _decimal: bool = if (core.stat >> 3) & 0b1 == 0 { false } else { true };
-
-    let value: u8;
-    let inc: u16;
+    let value: u8 = read_operand(core);
 
-    match core.ir {
-        0xe0 => { // CPX IMM
-            value = core.memory[core.pc as usize + 1];
-            inc = 2;
-        }
-        0xe4 => { // CPX ZP
-            let zp: u8 = zero_page(core);
-            value = core.memory[zp as usize];
-            inc = 2;
-        }
-        0xec => { // CPX ABS
-            let abs: u16 = absolute(core);
-            value = core.memory[abs as usize];
-            inc = 3;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
-
-    // Calculate Y - M, zero extending both values:
+    // Calculate X - M, zero extending both values:
     let result: i16 = (core.ix as i16) - (value as i16);
 
     // Check the result and set flags:
-    if result >= 0 { core.stat |= 0b00000001 } // Carry flag
-    else { core.stat &= !0b00000001 } // Clear carry flag
+    core.stat.set(Flag::Carry, result >= 0);
 
-    if (result & 0xFF) == 0 { core.stat |= 0b00000010 } // Zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
+    core.stat.set(Flag::Zero, (result & 0xFF) == 0);
 
-    if (result & 0x80) != 0 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+    core.stat.set(Flag::Negative, (result & 0x80) != 0);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
 pub fn cpy(core: &mut Core) -> &mut Core {
-    // Check for the decimal mode flag, as it means we have to work with binary coded decimal.
-    let _decimal: bool = if (core.stat >> 3) & 0b1 == 0 { false } else { true };
-
-    let value: u8;
-    let inc: u16;
-
-    match core.ir {
-        0xC0 => { // CPY IMM
-            value = core.memory[core.pc as usize + 1];
-            inc = 2;
-        }
-        0xC4 => { // CPY ZP
-            let zp: u8 = zero_page(core);
-            value = core.memory[zp as usize];
-            inc = 2;
-        }
-        0xCC => { // CPY ABS
-            let abs: u16 = absolute(core);
-            value = core.memory[abs as usize];
-            inc = 3;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    let value: u8 = read_operand(core);
 
     // Calculate Y - M, zero extending both values:
     let result: i16 = (core.iy as i16) - (value as i16);
 
     // Check the result and set flags:
-    if result >= 0 { core.stat |= 0b00000001 } // Carry flag
-    else { core.stat &= !0b00000001 } // Clear carry flag
+    core.stat.set(Flag::Carry, result >= 0);
 
-    if (result & 0xFF) == 0 { core.stat |= 0b00000010 } // Zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
+    core.stat.set(Flag::Zero, (result & 0xFF) == 0);
 
-    if (result & 0x80) != 0 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+    core.stat.set(Flag::Negative, (result & 0x80) != 0);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
 pub fn dec(core: &mut Core) -> &mut Core {
-    let value: u8;
-    let inc: u16;
-
-    match core.ir {
-        0xc6 => { // DEC ZP
-            let zp: u8 = zero_page(core);
-            core.memory[zp as usize] = core.memory[zp as usize].wrapping_sub(1);
-            value = core.memory[zp as usize];
-            inc = 2;
-        }
-        0xd6 => { // DEC ZPX
-            let zpx: u8 = zero_page_x(core);
-            core.memory[zpx as usize] = core.memory[zpx as usize].wrapping_sub(1);
-            value = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0xce => { // DEC ABS
-            let abs: u16 = absolute(core);
-            core.memory[abs as usize] = core.memory[abs as usize].wrapping_sub(1);
-            value = core.memory[abs as usize];
-            inc = 3;
-        }
-        0xde => { // DEC ABSX
-            let absx: u16 = absolute_x(core);
-            core.memory[absx as usize] = core.memory[absx as usize].wrapping_sub(1);
-            value = core.memory[absx as usize];
-            inc = 3;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
-
-    if value == 0x00 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
+    let (_, value): (u8, u8) = modify(core, |operand| operand.wrapping_sub(1));
 
-    if (value >> 7) & 0b1 == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+    core.stat.set_zero_negative(value);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
@@ -862,13 +453,9 @@ pub fn dec(core: &mut Core) -> &mut Core {
 pub fn dex(core: &mut Core) -> &mut Core {
     core.ix = core.ix.wrapping_sub(1);
 
-    if core.ix == 0 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
+    core.stat.set_zero_negative(core.ix);
 
-    if ((core.ix >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
-
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
@@ -876,13 +463,9 @@ pub fn dex(core: &mut Core) -> &mut Core {
 pub fn dey(core: &mut Core) -> &mut Core {
     core.iy = core.iy.wrapping_sub(1);
 
-    if core.iy == 0 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
-
-    if ((core.iy >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+    core.stat.set_zero_negative(core.iy);
 
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
@@ -890,13 +473,9 @@ pub fn dey(core: &mut Core) -> &mut Core {
 pub fn inx(core: &mut Core) -> &mut Core {
     core.ix = core.ix.wrapping_add(1);
 
-    if core.ix == 0 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
+    core.stat.set_zero_negative(core.ix);
 
-    if ((core.ix >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
-
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
@@ -904,155 +483,51 @@ pub fn inx(core: &mut Core) -> &mut Core {
 pub fn iny(core: &mut Core) -> &mut Core {
     core.iy = core.iy.wrapping_add(1);
 
-    if core.iy == 0 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
-
-    if ((core.iy >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+    core.stat.set_zero_negative(core.iy);
 
-    core.pc += 1;
+    core.pc = core.pc.wrapping_add(1);
 
     core
 } 
 
 pub fn eor(core: &mut Core) -> &mut Core {
-    let value: u8;
-    let inc: u16;
-    
-    match core.ir {
-        0x49 => { // EOR IMM
-            value = core.memory[core.pc as usize + 1];
-            inc = 2;
-        }
-        0x45 => { // EOR ZP
-            let zp: u8 = zero_page(core);
-            value = core.memory[zp as usize];
-            inc = 2;
-        }
-        0x55 => { // EOR ZPX
-            let zpx: u8 = zero_page_x(core);
-            value = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0x4D => { // EOR ABS
-            let abs: u16 = absolute(core);
-            value = core.memory[abs as usize];
-            inc = 3;
-        }
-        0x5D => { // EOR ABSX
-            let absx: u16 = absolute_x(core);
-            value = core.memory[absx as usize];
-            inc = 3;
-        }
-        0x59 => { // EOR ABSY
-            let absy: u16 = absolute_y(core);
-            value = core.memory[absy as usize];
-            inc = 3;
-        }
-        0x41 => { // EOR X_IND
-            value = x_indirect(core);
-            inc = 2;
-        }
-        0x51 => { // EOR IND_Y
-            value = indirect_y(core);
-            inc = 2;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    let value: u8 = read_operand(core);
 
     // Calculate A ^ M
     core.acc ^= value;
 
-    if core.acc == 0x00_u8 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
-
-    if ((core.acc >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+    core.stat.set_zero_negative(core.acc);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
 pub fn inc(core: &mut Core) -> &mut Core {
-    let address: Value;
-    let inc: u16;
-    
-    match core.ir {
-        0xE6 => { // INC ZP
-            address = Value::U8(zero_page(core));
-            inc = 2;
-        }
-        0xF6 => { // INC ZPX
-            address = Value::U8(zero_page_x(core));
-            inc = 2;
-        }
-        0xEE => { // INC ABS
-            address = Value::U16(absolute(core));
-            inc = 3;
-        }
-        0xFE => { // INC ABSX
-            address = Value::U16(absolute_x(core));
-            inc = 3;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
-
-    match address {
-        Value::U8(val) => {
-            core.memory[val as usize] = core.memory[val as usize].wrapping_add(1);
-        }
-        Value::U16(val) => {
-            core.memory[val as usize] = core.memory[val as usize].wrapping_add(1);
-        }
-    }
+    let (_, value): (u8, u8) = modify(core, |operand| operand.wrapping_add(1));
 
-    if core.memory[address.get_u8() as usize] == 0x00_u8 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // Clear zero flag
-    
-    if ((core.memory[address.get_u8() as usize] >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // Clear negative flag
+    core.stat.set_zero_negative(value);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
-// NOTE refactor to use Value struct
 pub fn jmp(core: &mut Core) -> &mut Core {
-    let address: u16;
-
-    match core.ir {
-        0x4C => { // JMP ABS
-            address = absolute(core);
-        }
-        0x6C => { // JMP IND
-            address = indirect(core);
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
-
-    core.pc = address;
+    core.pc = operand_address(core);
 
     core
 } 
 
 pub fn jsr(core: &mut Core) -> &mut Core {
-    // The return address - 1 due to how RTS works.
-    let spl: u8 = ((core.pc + 2) & 0xFF) as u8; // Lower byte
-    let sph: u8 = ((core.pc + 2) >> 8) as u8; // Higher byte
+    // Subroutine address. The high byte is only read once the return
+    // address is on the stack, as on the hardware.
+    let pcl: u8 = core.read(core.pc.wrapping_add(1)); // Lower byte
 
-    // Subroutine address.
-    let pcl: u8 = core.memory[core.pc as usize + 1]; // Lower byte
-    let pch: u8 = core.memory[core.pc as usize + 2]; // Higher byte
+    // Store the return address - 1 in the stack, due to how RTS works.
+    core.push_word(core.pc.wrapping_add(2));
 
-    // Store the return address - 1 in memory. 
-    let stack_address: usize = 0x0100 | (core.sp as u16) as usize;
-    core.memory[(stack_address - 1)..=(stack_address)]
-        .copy_from_slice(&[spl, sph]);
-
-    // Adjust stack pointer to descend with the stack.
-    core.sp -= 2;
+    let pch: u8 = core.read(core.pc.wrapping_add(2)); // Higher byte
 
     // Adjust program counter.
     core.pc = ((pch as u16) << 8) | (pcl as u16);
@@ -1061,636 +536,646 @@ pub fn jsr(core: &mut Core) -> &mut Core {
 } 
 
 pub fn lda(core: &mut Core) -> &mut Core {
-    let value: u8;
-    let inc: u16;
-
-    match core.ir {
-        0xa9_u8 => { // LDA IMM
-            value = core.memory[core.pc as usize + 1];
-            inc = 2;
-        }
-        0xa5_u8 => { // LDA ZP
-            let zp: u8 = zero_page(core);
-            value = core.memory[zp as usize];
-            inc = 2;
-        }
-        0xb5_u8 => { // LDA ZPX
-            let zpx: u8 = zero_page_x(core);
-            value = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0xad_u8 => { // LDA ABS
-            let abs: u16 =  absolute(core);
-            value = core.memory[abs as usize];
-            inc = 3;
-        }
-        0xbd_u8 => { // LDA ABSX
-            let absx: u16 = absolute_x(core);
-            value = core.memory[absx as usize];
-            inc = 3;
-        }
-        0xb9_u8 => { // LDA ABSY
-            let absy: u16 = absolute_y(core);
-            value = core.memory[absy as usize];
-            inc = 3;
-        }
-        0xa1_u8 => { // LDA X_IND
-            value = x_indirect(core);
-            inc = 2;
-        }
-        0xb1_u8 => { // LDA IND_Y
-            value = indirect_y(core);
-            inc = 2;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    let value: u8 = read_operand(core);
 
     core.acc = value;
 
-    if core.acc == 0x00_u8 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // clear zero flag
+    core.stat.set_zero_negative(core.acc);
 
-    if ((core.acc >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // clear negative flag
-
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
 pub fn ldx(core: &mut Core) -> &mut Core {
-    let value: u8;
-    let inc: u16;
-
-    match core.ir {
-        0xA2_u8 => { // LDX IMM
-            value = core.memory[core.pc as usize + 1];
-            inc = 2;
-        }
-        0xA6_u8 => { // LDX ZP
-            let zp: u8 = zero_page(core);
-            value = core.memory[zp as usize];
-            inc = 2;
-        }
-        0xB6_u8 => { // LDX ZPY
-            let zpy: u8 = zero_page_y(core);
-            value = core.memory[zpy as usize];
-            inc = 2;
-        }
-        0xAE_u8 => { // LDX ABS
-            let abs: u16 = absolute(core);
-            value = core.memory[abs as usize];
-            inc = 3;
-        }
-        0xBE_u8 => { // LDX ABSY
-            let absy: u16 = absolute_y(core);
-            value = core.memory[absy as usize];
-            inc = 3;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    let value: u8 = read_operand(core);
 
     core.ix = value;
 
-    if core.ix == 0 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // clear zero flag
-
-    if ((core.ix >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // clear negative flag
+    core.stat.set_zero_negative(core.ix);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
 pub fn ldy(core: &mut Core) -> &mut Core {
-    let value: u8;
-    let inc: u16;
-
-    match core.ir {
-        0xa0_u8 => { // LDY IMM
-            value = core.memory[core.pc as usize + 1];
-            inc = 2;
-        }
-        0xa4_u8 => { // LDY ZP
-            let zp: u8 = zero_page(core);
-            value = core.memory[zp as usize];
-            inc = 2;
-        }
-        0xb4_u8 => { // LDY ZPX
-            let zpx: u8 = zero_page_x(core);
-            value = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0xac_u8 => { // LDY ABS
-            let abs: u16 = absolute(core);
-            value = core.memory[abs as usize];
-            inc = 3;
-        }
-        0xbc_u8 => { // LDY ABSX
-            let absx: u16 = absolute_x(core);
-            value = core.memory[absx as usize];
-            inc = 3;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    let value: u8 = read_operand(core);
 
     core.iy = value;
 
-    if core.iy == 0 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // clear zero flag
+    core.stat.set_zero_negative(core.iy);
 
-    if ((core.iy >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // clear negative flag
-
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
 pub fn lsr(core: &mut Core) -> &mut Core {
-    let new_carry: bool;
-    let result: u8;
-    let inc: u16;
+    let (operand, result): (u8, u8) = modify(core, |operand| operand >> 1);
+    let new_carry: bool = (operand & 0b1) != 0;
 
-    match core.ir {
-        0x4a => { // LSR ACC
-            new_carry = if (core.acc & 0b1) != 0 { true } else { false };
+    core.stat.set(Flag::Carry, new_carry);
 
-            core.acc >>= 1;
+    core.stat.set(Flag::Zero, result == 0x00_u8);
 
-            result = core.acc;
-            inc = 1;
-        }
-        0x46 => { // LSR ZP
-            let zp: u8 = zero_page(core);
+    core.stat.set(Flag::Negative, false);
 
-            new_carry = if (core.memory[zp as usize] & 0b1) != 0 { true } else { false };
+    core.pc = core.pc.wrapping_add(length(core));
 
-            core.memory[zp as usize] >>= 1;
+    core
+} 
 
-            result = core.memory[zp as usize];
-            inc = 2;
-        }
-        0x56 => { // LSR ZPX
-            let zpx: u8 = zero_page_x(core);
+pub fn ora(core: &mut Core) -> &mut Core {
+    let value: u8 = read_operand(core);
 
-            new_carry = if (core.memory[zpx as usize] & 0b1) != 0 { true } else { false };
+    core.acc |= value;
 
-            core.memory[zpx as usize] >>= 1;
+    core.stat.set_zero_negative(core.acc);
 
-            result = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0x4e => { // LSR ABS
-            let abs: u16 = absolute(core);
+    core.pc = core.pc.wrapping_add(length(core));
 
-            new_carry = if (core.memory[abs as usize] & 0b1) != 0 { true } else { false };
+    core
+} 
 
-            core.memory[abs as usize] >>= 1;
+pub fn rol(core: &mut Core) -> &mut Core {
+    let old_carry: u8 = core.stat.carry();
 
-            result = core.memory[abs as usize];
-            inc = 3;
-        }
-        0x5e => { // LSR ABSX
-            let absx: u16 = absolute_x(core);
+    let (operand, result): (u8, u8) = modify(core, |operand| (operand << 1) | old_carry);
+    let new_carry: bool = (operand >> 7) & 0b1 != 0;
 
-            new_carry = if (core.memory[absx as usize] & 0b1) != 0 { true } else { false };
+    core.stat.set_zero_negative(result);
 
-            core.memory[absx as usize] >>= 1;
+    core.stat.set(Flag::Carry, new_carry);
 
-            result = core.memory[absx as usize];
-            inc = 3;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    core.pc = core.pc.wrapping_add(length(core));
+
+    core
+} 
+
+pub fn ror(core: &mut Core) -> &mut Core {
+    let old_carry: u8 = core.stat.carry();
 
-    if new_carry { core.stat |= 0b00000001 } // Set carry flag
-    else { core.stat &= !0b00000001 } // clear carry flag
+    let (operand, result): (u8, u8) = modify(core, |operand| (operand >> 1) | old_carry << 7);
+    let new_carry: bool = (operand & 0b1) != 0;
 
-    if result == 0x00_u8 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // clear zero flag
+    core.stat.set_zero_negative(result);
 
-    core.stat &= !0b10000000; // clear negative flag
+    core.stat.set(Flag::Carry, new_carry);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
-pub fn ora(core: &mut Core) -> &mut Core {
-    let value: u8;
-    let inc: u16;
+// The arithmetic behind SBC, shared with the undocumented ISC and USBC opcodes.
+fn subtract_with_borrow(core: &mut Core, value: u8) {
+    // Check for the decimal mode flag, as it means we have to work with binary coded decimal.
+    // Parts without decimal mode keep the flag but always do binary arithmetic.
+    let decimal: bool = core.stat.get(Flag::Decimal) && core.variant.has_decimal_mode();
 
-    match core.ir {
-        0x09_u8 => { // ORA IMM
-            value = core.memory[core.pc as usize + 1];
-            inc = 2;
-        }
-        0x05_u8 => { // ORA ZP
-            let zp: u8 = zero_page(core);
-            value = core.memory[zp as usize];
-            inc = 2;
-        }
-        0x15_u8 => { // ORA ZPX
-            let zpx: u8 = zero_page_x(core);
-            value = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0x0d_u8 => { // ORA ABS
-            let abs: u16 = absolute(core);
-            value = core.memory[abs as usize];
-            inc = 3;
-        }
-        0x1d_u8 => { // ORA ABSX
-            let absx: u16 = absolute_x(core);
-            value = core.memory[absx as usize];
-            inc = 3;
-        }
-        0x19_u8 => { // ORA ABSY
-            let absy: u16 = absolute_y(core);
-            value = core.memory[absy as usize];
-            inc = 3;
-        }
-        0x01_u8 => { // ORA X_IND
-            value = x_indirect(core);
-            inc = 2;
-        }
-        0x11_u8 => { // ORA IND_Y
-            value = indirect_y(core);
-            inc = 2;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    let borrow: i16 = if core.stat.get(Flag::Carry) { 0 } else { 1 }; // inverse of carry flag
+            
+    // The 65C02 spends an extra cycle correcting the flags in decimal mode.
+    if decimal && core.variant.is_cmos() { core.cycles += 1 }
+
+    // Carry and overflow always come from the binary difference, in either mode.
+    let binary: i16 = (core.acc as i16) - (value as i16) - borrow;
+    let overflow: bool = (core.acc ^ value) & (core.acc ^ binary as u8) & 0x80 != 0;
+
+    core.stat.set(Flag::Carry, binary >= 0);
+
+    core.stat.set(Flag::Overflow, overflow);
+
+    let low: i16 = ((core.acc & 0x0F) as i16) - ((value & 0x0F) as i16) - borrow;
+
+    let result: u8 = if decimal && core.variant.is_cmos() {
+        // The 65C02 adjusts the binary difference, which also makes it set N and Z
+        // from the decimal result below.
+        let mut difference: i16 = binary;
+        if difference < 0 { difference -= 0x60 }
+        if low < 0 { difference -= 0x06 }
+        difference as u8
+    } else if decimal {
+        // The NMOS part adjusts each digit on its own. Invalid BCD digits go through
+        // the same adjustment, matching real parts.
+        let mut low: i16 = low;
+        if low < 0 { low = ((low - 0x06) & 0x0F) - 0x10 }
+        let mut difference: i16 = ((core.acc & 0xF0) as i16) - ((value & 0xF0) as i16) + low;
+        if difference < 0 { difference -= 0x60 }
+        difference as u8
+    } else {
+        binary as u8
+    };
 
-    core.acc |= value;
+    core.acc = result;
+
+    // N and Z follow the binary difference on NMOS parts, even in decimal mode.
+    let nz_source: u8 = if core.variant.is_cmos() { result } else { binary as u8 };
 
-    if core.acc == 0x00_u8 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // clear zero flag
+    core.stat.set_zero_negative(nz_source);
+}
 
-    if ((core.acc >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // clear negative flag
+pub fn sbc(core: &mut Core) -> &mut Core {
+    let value: u8 = read_operand(core);
 
-    core.pc += inc;
+    subtract_with_borrow(core, value);
+
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
 } 
 
-pub fn rol(core: &mut Core) -> &mut Core {
-    let old_carry: u8 = core.stat & 0b00000001;
+pub fn sta(core: &mut Core) -> &mut Core {
+    let address: u16 = operand_address(core);
 
-    let new_carry: bool;
-    let result: u8;
-    let inc: u16;
+    core.write(address, core.acc);
 
-    match core.ir {
-        0x2a => { // ROL ACC
-            new_carry = if (core.acc >> 7) & 0b1 != 0 { true } else { false };
+    core.pc = core.pc.wrapping_add(length(core));
 
-            core.acc <<= 1;
+    core
+} 
 
-            core.acc |= old_carry;
+pub fn stx(core: &mut Core) -> &mut Core {
+    let address: u16 = operand_address(core);
 
-            result = core.acc;
-            inc = 1;
-        }
-        0x26 => { // ROL ZP
-            let zp: u8 = zero_page(core);
+    core.write(address, core.ix);
 
-            new_carry = if (core.memory[zp as usize] >> 7) & 0b1 != 0 { true } else { false };
+    core.pc = core.pc.wrapping_add(length(core));
+
+    core
+} 
 
-            core.memory[zp as usize] <<= 1;
+pub fn sty(core: &mut Core) -> &mut Core {
+    let address: u16 = operand_address(core);
 
-            core.memory[zp as usize] |= old_carry;
+    core.write(address, core.iy);
 
-            result = core.memory[zp as usize];
-            inc = 2;
-        }
-        0x36 => {
-            let zpx: u8 = zero_page_x(core);
+    core.pc = core.pc.wrapping_add(length(core));
 
-            new_carry = if (core.memory[zpx as usize] >> 7) & 0b1 != 0 { true } else { false };
+    core
+} 
 
-            core.memory[zpx as usize] <<= 1;
+/*
+    Undocumented NMOS opcodes.
 
-            core.memory[zpx as usize] |= old_carry;
+    These fall out of the way the NMOS decode logic combines the signals of
+    neighbouring official instructions, and plenty of real software relies on
+    them. Behaviour follows the "NMOS 6510 Unintended Opcodes" document.
+    ANE, LXA, SHA, SHX, SHY and TAS are unstable on real hardware, so they use
+    the behaviour most emulators settle on.
+*/
 
-            result = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0x2e => { // ROL ABS
-            let abs: u16 = absolute(core);
+pub fn slo(core: &mut Core) -> &mut Core {
+    // ASL memory, then ORA the result into the accumulator.
+    let (value, result): (u8, u8) = modify(core, |value| value << 1);
 
-            new_carry = if (core.memory[abs as usize] >> 7) & 0b1 != 0 { true } else { false };
+    core.acc |= result;
 
-            core.memory[abs as usize] <<= 1;
+    core.stat.set(Flag::Carry, (value >> 7) & 0b1 != 0);
+    core.stat.set_zero_negative(core.acc);
 
-            core.memory[abs as usize] |= old_carry;
+    core.pc = core.pc.wrapping_add(length(core));
 
-            result = core.memory[abs as usize];
-            inc = 3;
-        }
-        0x3e => { // ROL ABSX
-            let absx: u16 = absolute_x(core);
+    core
+}
 
-            new_carry = if (core.memory[absx as usize] >> 7) & 0b1 != 0 { true } else { false };
+pub fn rla(core: &mut Core) -> &mut Core {
+    // ROL memory, then AND the result into the accumulator.
+    let old_carry: u8 = core.stat.carry();
+    let (value, result): (u8, u8) = modify(core, |value| (value << 1) | old_carry);
 
-            core.memory[absx as usize] <<= 1;
+    core.acc &= result;
 
-            core.memory[absx as usize] |= old_carry;
+    core.stat.set(Flag::Carry, (value >> 7) & 0b1 != 0);
+    core.stat.set_zero_negative(core.acc);
 
-            result = core.memory[absx as usize];
-            inc = 3;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    core.pc = core.pc.wrapping_add(length(core));
+
+    core
+}
 
-    if result == 0x00_u8 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // clear zero flag
+pub fn sre(core: &mut Core) -> &mut Core {
+    // LSR memory, then EOR the result into the accumulator.
+    let (value, result): (u8, u8) = modify(core, |value| value >> 1);
 
-    if ((result >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // clear negative flag
+    core.acc ^= result;
 
-    if new_carry { core.stat |= 0b00000001 } // Set carry flag
-    else { core.stat &= !0b00000001 } // clear carry flag
+    core.stat.set(Flag::Carry, value & 0b1 != 0);
+    core.stat.set_zero_negative(core.acc);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
-} 
+}
 
-pub fn ror(core: &mut Core) -> &mut Core {
-    let old_carry: u8 = core.stat & 0b00000001;
+pub fn rra(core: &mut Core) -> &mut Core {
+    // ROR memory, then ADC the result using the carry rotated out.
+    let old_carry: u8 = core.stat.carry();
+    let (value, result): (u8, u8) = modify(core, |value| (value >> 1) | (old_carry << 7));
 
-    let new_carry: bool;
-    let result: u8;
-    let inc: u16;
+    core.stat.set(Flag::Carry, value & 0b1 != 0);
+    add_with_carry(core, result);
 
-    match core.ir {
-        0x6a => { // ROR ACC
-            new_carry = if (core.acc & 0b1) != 0 { true } else { false };
+    core.pc = core.pc.wrapping_add(length(core));
 
-            core.acc >>= 1;
+    core
+}
 
-            core.acc |= old_carry << 7;
+pub fn dcp(core: &mut Core) -> &mut Core {
+    // DEC memory, then CMP the result with the accumulator.
+    let (_, result): (u8, u8) = modify(core, |value| value.wrapping_sub(1));
 
-            result = core.acc;
-            inc = 1;
-        }
-        0x66 => { // ROR ZP
-            let zp: u8 = zero_page(core);
+    let acc: u8 = core.acc;
+    core.stat.set(Flag::Carry, acc >= result);
+    core.stat.set_zero_negative(acc.wrapping_sub(result));
 
-            new_carry = if (core.memory[zp as usize] & 0b1) != 0 { true } else { false };
+    core.pc = core.pc.wrapping_add(length(core));
 
-            core.memory[zp as usize] >>= 1;
+    core
+}
 
-            core.memory[zp as usize] |= old_carry << 7;
+pub fn isc(core: &mut Core) -> &mut Core {
+    // INC memory, then SBC the result from the accumulator.
+    let (_, result): (u8, u8) = modify(core, |value| value.wrapping_add(1));
 
-            result = core.memory[zp as usize];
-            inc = 2;
-        }
-        0x76 => { // ROR ZPX   
-            let zpx: u8 = zero_page_x(core);
+    subtract_with_borrow(core, result);
 
-            new_carry = if (core.memory[zpx as usize] & 0b1) != 0 { true } else { false };
+    core.pc = core.pc.wrapping_add(length(core));
 
-            core.memory[zpx as usize] >>= 1;
+    core
+}
 
-            core.memory[zpx as usize] |= old_carry << 7;
+pub fn sax(core: &mut Core) -> &mut Core {
+    // Store A AND X, no flags affected.
+    let address: u16 = operand_address(core);
 
-            result = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0x7e => { // ROR ABSX
-            let abs: u16 = absolute(core);
+    core.write(address, core.acc & core.ix);
 
-            new_carry = if (core.memory[abs as usize] & 0b1) != 0 { true } else { false };
+    core.pc = core.pc.wrapping_add(length(core));
 
-            core.memory[abs as usize] >>= 1;
+    core
+}
 
-            core.memory[abs as usize] |= old_carry << 7;
+pub fn lax(core: &mut Core) -> &mut Core {
+    // LDA and LDX with the same value.
+    let value: u8 = read_operand(core);
 
-            result = core.memory[abs as usize];
-            inc = 3;
-        }
-        0x6e => { // ROR ABSX
-            let absx: u16 = absolute_x(core);
+    core.acc = value;
+    core.ix = value;
+    core.stat.set_zero_negative(value);
 
-            new_carry = if (core.memory[absx as usize] & 0b1) != 0 { true } else { false };
+    core.pc = core.pc.wrapping_add(length(core));
 
-            core.memory[absx as usize] >>= 1;
+    core
+}
 
-            core.memory[absx as usize] |= old_carry << 7;
+pub fn anc(core: &mut Core) -> &mut Core {
+    // AND immediate, then copy the negative flag into carry.
+    core.acc &= core.read(core.pc.wrapping_add(1));
 
-            result = core.memory[absx as usize];
-            inc = 3;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    core.stat.set_zero_negative(core.acc);
+    core.stat.set(Flag::Carry, (core.acc >> 7) & 0b1 != 0);
+
+    core.pc = core.pc.wrapping_add(2);
+
+    core
+}
 
-    if result == 0x00_u8 { core.stat |= 0b00000010 } // Set zero flag
-    else { core.stat &= !0b00000010 } // clear zero flag
+pub fn alr(core: &mut Core) -> &mut Core {
+    // AND immediate, then LSR the accumulator.
+    let value: u8 = core.acc & core.read(core.pc.wrapping_add(1));
 
-    if ((result >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // Set negative flag
-    else { core.stat &= !0b10000000 } // clear negative flag
+    core.acc = value >> 1;
 
-    if new_carry { core.stat |= 0b00000001 } // Set carry flag
-    else { core.stat &= !0b00000001 } // clear carry flag
+    core.stat.set(Flag::Carry, value & 0b1 != 0);
+    core.stat.set_zero_negative(core.acc);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(2);
 
     core
-} 
+}
 
-pub fn sbc(core: &mut Core) -> &mut Core {
-    // Check for the decimal mode flag, as it means we have to work with binary coded decimal.
-    let decimal: bool = if (core.stat >> 3) & 0b1 == 0 { false } else { true };
+pub fn arr(core: &mut Core) -> &mut Core {
+    // AND immediate, then ROR the accumulator. The flags come from the adder
+    // rather than the shifter, so C and V end up following bits 6 and 5.
+    let decimal: bool = core.stat.get(Flag::Decimal) && core.variant.has_decimal_mode();
+    let old_carry: u8 = core.stat.carry();
+    let value: u8 = core.acc & core.read(core.pc.wrapping_add(1));
+    let mut result: u8 = (value >> 1) | (old_carry << 7);
+
+    core.stat.set_zero_negative(result);
+
+    core.stat.set(Flag::Overflow, (result ^ (result << 1)) & 0b01000000 != 0);
 
-    let value: u8;
-    let inc: u16;
-    
-    match core.ir {
-        0xE9_u8 => { // SBC IMM
-            value = core.memory[core.pc as usize + 1];
-            inc = 2;
-        }
-        0xE5_u8 => { // SBC ZP
-            let zp: u8 = zero_page(core);
-            value = core.memory[zp as usize];
-            inc = 2;
-        }
-        0xF5_u8 => { // SBC ZPX
-            let zpx: u8 = zero_page_x(core);
-            value = core.memory[zpx as usize];
-            inc = 2;
-        }
-        0xED_u8 => { // SBC ABS
-            let abs: u16 = absolute(core);
-            value = core.memory[abs as usize];
-            inc = 3;
-        }
-        0xFD_u8 => { // SBC ABSX
-            let absx: u16 = absolute_x(core);
-            value = core.memory[absx as usize];
-            inc = 3;
-        }
-        0xF9_u8 => { // SBC ABSY
-            let absy: u16 = absolute_y(core);
-            value = core.memory[absy as usize];
-            inc = 3;
-        }
-        0xE1_u8 => { // SBC X_IND
-            value = x_indirect(core);
-            inc = 2;
-        }
-        0xF1_u8 => { // SBC IND_Y
-            value = indirect_y(core);
-            inc = 2;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
-    
-    let borrow: u8 = if core.stat & 0b1 == 0 { 1 } else { 0 }; // inverse of carry flag
-            
     if decimal {
-        let bcd_result: (u8, bool) = Value::bcd_sub(&Value::U8(core.acc), &Value::U8(value), borrow);
+        // Decimal mode applies a BCD style fixup to each nibble.
+        if (value & 0x0F) + (value & 0x01) > 0x05 {
+            result = (result & 0xF0) | (result.wrapping_add(0x06) & 0x0F);
+        }
+
+        let high_fixup: bool = (value as u16 & 0xF0) + (value as u16 & 0x10) > 0x50;
 
-        core.acc = bcd_result.0;
+        if high_fixup { result = result.wrapping_add(0x60) }
 
-        if bcd_result.1 { core.stat |= 0b00000001 } // set carry flag
-        else { core.stat &= !0b00000001 } // clear carry flag
+        core.stat.set(Flag::Carry, high_fixup);
     } else {
-        let result: i16 = (core.acc as i16) - (value as i16) - (borrow as i16);
-        let acc_sign: bool = (core.acc >> 7) & 0b1 != 0; // Sign used for overflow check before addition.
-        
-        core.acc = result as u8; // Clamp to 8 bits and store result
+        core.stat.set(Flag::Carry, (result >> 6) & 0b1 != 0);
+    }
 
-        if result > 0xff { core.stat |= 0b00000001 } // set carry flag
-        else { core.stat &= !0b00000001 } // clear carry flag
-    
-        // Overflow flag logic:
-        let mem_sign: bool = (value >> 7) & 0b1 != 0;
-        let res_sign: bool = (core.acc >> 7) & 0b1 != 0;
-    
-        if acc_sign == mem_sign && acc_sign != res_sign { core.stat |= 0b01000000 } // set overflow flag
-        else { core.stat &= !0b01000000 } // clear overflow flag        
+    core.acc = result;
+
+    core.pc = core.pc.wrapping_add(2);
+
+    core
+}
+
+pub fn sbx(core: &mut Core) -> &mut Core {
+    // X = (A AND X) - immediate, setting flags like CMP.
+    let value: u8 = core.read(core.pc.wrapping_add(1));
+    let and: u8 = core.acc & core.ix;
+
+    core.ix = and.wrapping_sub(value);
+
+    core.stat.set(Flag::Carry, and >= value);
+    core.stat.set_zero_negative(core.ix);
+
+    core.pc = core.pc.wrapping_add(2);
+
+    core
+}
+
+pub fn las(core: &mut Core) -> &mut Core {
+    // Memory AND stack pointer into A, X and the stack pointer.
+    let value: u8 = read_operand(core) & core.sp;
+
+    core.acc = value;
+    core.ix = value;
+    core.sp = value;
+    core.stat.set_zero_negative(value);
+
+    core.pc = core.pc.wrapping_add(length(core));
+
+    core
+}
+
+pub fn ane(core: &mut Core) -> &mut Core {
+    // Unstable. (A OR magic) AND X AND immediate, with the usual 0xEE magic.
+    core.acc = (core.acc | 0xEE) & core.ix & core.read(core.pc.wrapping_add(1));
+
+    core.stat.set_zero_negative(core.acc);
+
+    core.pc = core.pc.wrapping_add(2);
+
+    core
+}
+
+pub fn lxa(core: &mut Core) -> &mut Core {
+    // Unstable. (A OR magic) AND immediate into A and X, with the usual 0xEE magic.
+    core.acc = (core.acc | 0xEE) & core.read(core.pc.wrapping_add(1));
+    core.ix = core.acc;
+
+    core.stat.set_zero_negative(core.acc);
+
+    core.pc = core.pc.wrapping_add(2);
+
+    core
+}
+
+// Shared by the unstable SHA, SHX, SHY and TAS stores. The value is ANDed with
+// the high byte of the base address plus one, and when indexing crosses a
+// page the stored value also replaces the high byte of the target address.
+fn unstable_store(core: &mut Core, address: u16, index: u8, value: u8) {
+    let base_high: u8 = (address.wrapping_sub(index as u16) >> 8) as u8;
+    let result: u8 = value & base_high.wrapping_add(1);
+
+    let target: u16 = if core.page_crossed {
+        ((result as u16) << 8) | (address & 0x00FF)
+    } else {
+        address
     };
 
-    if core.acc == 0 { core.stat |= 0b00000010 } // set zero flag
-    else { core.stat &= !0b00000010 } // clear zero flag
+    core.write(target, result);
+}
+
+pub fn sha(core: &mut Core) -> &mut Core {
+    let address: u16 = operand_address(core);
 
-    if ((core.acc >> 7) & 0b1) == 0b1 { core.stat |= 0b10000000 } // set negative flag
-    else { core.stat &= !0b10000000 } // clear negative flag
+    let (value, index): (u8, u8) = (core.acc & core.ix, core.iy);
+    unstable_store(core, address, index, value);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
-} 
+}
 
-pub fn sta(core: &mut Core) -> &mut Core {
-    let address: Value;
-    let inc: u16;
+pub fn shx(core: &mut Core) -> &mut Core {
+    let address: u16 = operand_address(core);
 
-    match core.ir {
-        0x85_u8 => { // STA ZP
-            address = Value::U8(zero_page(core));
-            inc = 2;
-        }
-        0x95_u8 => { // STA ZPX
-            address = Value::U8(zero_page_x(core));
-            inc = 2;
-        }
-        0x8d_u8 => { // STA ABS
-            address = Value::U16(absolute(core));
-            inc = 3;
-        }
-        0x9d_u8 => { // STA ABSX
-            address = Value::U16(absolute_x(core));
-            inc = 3;
-        }
-        0x99_u8 => { // STA ABSY
-            address = Value::U16(absolute_y(core));
-            inc = 3;
-        }
-        0x81_u8 => { // STA X_IND
-            address = Value::U8(x_indirect(core));
-            inc = 2;
-        }
-        0x91_u8 => { // STA IND_Y
-            address = Value::U8(indirect_y(core));
-            inc = 2;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    let (value, index): (u8, u8) = (core.ix, core.iy);
+    unstable_store(core, address, index, value);
 
-    match address {
-        Value::U8(addr) => core.memory[addr as usize] = core.acc,
-        Value::U16(addr) => core.memory[addr as usize] = core.acc,
-    }
+    core.pc = core.pc.wrapping_add(length(core));
+
+    core
+}
 
-    core.pc += inc;
+pub fn shy(core: &mut Core) -> &mut Core {
+    let address: u16 = operand_address(core);
+
+    let (value, index): (u8, u8) = (core.iy, core.ix);
+    unstable_store(core, address, index, value);
+
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
-} 
+}
 
-pub fn stx(core: &mut Core) -> &mut Core {
-    let address: Value;
-    let inc: u16;
+pub fn tas(core: &mut Core) -> &mut Core {
+    // Stack pointer = A AND X, then stored like SHA.
+    let address: u16 = operand_address(core);
 
-    match core.ir {
-        0x86_u8 => { // STY ZP
-            address = Value::U8(zero_page(core));
-            inc = 2;
-        }
-        0x96_u8 => { // STY ZPX
-            address = Value::U8(zero_page_y(core));
-            inc = 2;
-        }
-        0x8e_u8 => { // STY ABS
-            address = Value::U16(absolute(core));
-            inc = 3;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    core.sp = core.acc & core.ix;
 
-    match address {
-        Value::U8(addr) => core.memory[addr as usize] = core.ix,
-        Value::U16(addr) => core.memory[addr as usize] = core.ix,
-    }
+    let (value, index): (u8, u8) = (core.sp, core.iy);
+    unstable_store(core, address, index, value);
 
-    core.pc += inc;
+    core.pc = core.pc.wrapping_add(length(core));
 
     core
-} 
+}
 
-pub fn sty(core: &mut Core) -> &mut Core {
-    let address: Value;
-    let inc: u16;
+pub fn jam(core: &mut Core) -> &mut Core {
+    // Locks up the processor. The program counter stays put and nothing
+    // but a reset brings the core back.
+    core.halted = true;
 
-    match core.ir {
-        0x84_u8 => { // STY ZP
-            address = Value::U8(zero_page(core));
-            inc = 2;
-        }
-        0x94_u8 => { // STY ZPX
-            address = Value::U8(zero_page_x(core));
-            inc = 2;
-        }
-        0x8c_u8 => { // STY ABS
-            address = Value::U16(absolute(core));
-            inc = 3;
-        }
-        _ => unreachable!("{:?}", core.info)
-    }
+    core
+}
+
+/*
+    65C02 additions.
+
+    The CMOS parts fill most of the NMOS undocumented opcodes with new
+    instructions, and turn the rest into NOPs of various lengths.
+*/
+
+pub fn bra(core: &mut Core) -> &mut Core {
+    // Branch always.
+    branch(core, true)
+}
+
+pub fn phx(core: &mut Core) -> &mut Core {
+    // Push X register to stack.
+    core.push(core.ix);
+
+    core.pc = core.pc.wrapping_add(1);
+
+    core
+}
+
+pub fn phy(core: &mut Core) -> &mut Core {
+    // Push Y register to stack.
+    core.push(core.iy);
+
+    core.pc = core.pc.wrapping_add(1);
+
+    core
+}
+
+pub fn plx(core: &mut Core) -> &mut Core {
+    // Set X register to value from the stack
+    core.ix = core.pull();
+
+    core.stat.set_zero_negative(core.ix);
+
+    core.pc = core.pc.wrapping_add(1);
+
+    core
+}
 
-    match address {
-        Value::U8(addr) => core.memory[addr as usize] = core.iy,
-        Value::U16(addr) => core.memory[addr as usize] = core.iy,
+pub fn ply(core: &mut Core) -> &mut Core {
+    // Set Y register to value from the stack
+    core.iy = core.pull();
+
+    core.stat.set_zero_negative(core.iy);
+
+    core.pc = core.pc.wrapping_add(1);
+
+    core
+}
+
+pub fn stz(core: &mut Core) -> &mut Core {
+    let address: u16 = operand_address(core);
+
+    core.write(address, 0x00);
+
+    core.pc = core.pc.wrapping_add(length(core));
+
+    core
+}
+
+// Shared by TRB and TSB. Updates memory like any read-modify-write, and
+// sets the zero flag from A AND M as it was read.
+fn test_bits<F: FnOnce(u8, u8) -> u8>(core: &mut Core, operation: F) {
+    let acc: u8 = core.acc;
+    let (value, _): (u8, u8) = modify(core, |value| operation(value, acc));
+
+    core.stat.set(Flag::Zero, acc & value == 0);
+}
+
+pub fn trb(core: &mut Core) -> &mut Core {
+    // Test and reset bits, clearing the bits of M that are set in A.
+    test_bits(core, |value, acc| value & !acc);
+
+    core.pc = core.pc.wrapping_add(length(core));
+
+    core
+}
+
+pub fn tsb(core: &mut Core) -> &mut Core {
+    // Test and set bits, setting the bits of M that are set in A.
+    test_bits(core, |value, acc| value | acc);
+
+    core.pc = core.pc.wrapping_add(length(core));
+
+    core
+}
+
+pub fn rmb(core: &mut Core) -> &mut Core {
+    // Reset memory bit. The bit number is in the high nibble of the opcode.
+    let bit: u8 = (core.ir >> 4) & 0b111;
+
+    modify(core, |value| value & !(1 << bit));
+
+    core.pc = core.pc.wrapping_add(length(core));
+
+    core
+}
+
+pub fn smb(core: &mut Core) -> &mut Core {
+    // Set memory bit. The bit number is in the high nibble of the opcode.
+    let bit: u8 = (core.ir >> 4) & 0b111;
+
+    modify(core, |value| value | 1 << bit);
+
+    core.pc = core.pc.wrapping_add(length(core));
+
+    core
+}
+
+// Shared by BBR and BBS. These take a zero page operand then a relative
+// offset, so the offset is one byte further on than the other branches.
+fn branch_on_bit(core: &mut Core, set: bool) -> &mut Core {
+    let bit: u8 = (core.ir >> 4) & 0b111;
+    let address: u16 = operand_address(core);
+    let condition: bool = ((core.read(address) >> bit) & 0b1 == 1) == set;
+
+    let next: u16 = core.pc.wrapping_add(3);
+
+    if condition {
+        let offset: i8 = core.read(core.pc.wrapping_add(2)) as i8;
+        let target: u16 = next.wrapping_add(offset as u16);
+
+        core.cycles += 1;
+
+        if (next & 0xFF00) != (target & 0xFF00) { core.cycles += 1; }
+
+        core.pc = target;
+    } else {
+        core.pc = next;
     }
 
-    core.pc += inc;
+    core
+}
+
+pub fn bbr(core: &mut Core) -> &mut Core {
+    // Branch if the memory bit is reset.
+    branch_on_bit(core, false)
+}
+
+pub fn bbs(core: &mut Core) -> &mut Core {
+    // Branch if the memory bit is set.
+    branch_on_bit(core, true)
+}
+
+pub fn wai(core: &mut Core) -> &mut Core {
+    // Wait for an interrupt. The core sleeps until an interrupt line is
+    // asserted, see `waiting` in `Core`.
+    core.waiting = true;
+
+    core.pc = core.pc.wrapping_add(1);
 
     core
-} 
+}
+
+pub fn stp(core: &mut Core) -> &mut Core {
+    // Stop the clock until the next reset.
+    core.halted = true;
+
+    core.pc = core.pc.wrapping_add(1);
+
+    core
+}
 
 // I was going to write tests but I'm really unsure how to go about it it's very overwhelming.
\ No newline at end of file
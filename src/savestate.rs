@@ -0,0 +1,368 @@
+use crate::{
+    bus::RomWrites,
+    error::EmulatorError,
+    status::Status,
+    system::{Core, Variant},
+};
+
+use std::fs;
+
+/*
+    Save states.
+
+    A save state is everything needed to carry on running a machine from
+    where it was saved: the registers, the cycle count, the interrupt lines
+    and anything latched on them, what's marked as ROM, the 65816's extra
+    banks, and whatever the bus holds. It's a small binary format, a magic
+    number and a version followed by the fields in a fixed order, little
+    endian. Anything that changes the layout bumps the version, and states
+    with a version this build doesn't know are refused rather than misread.
+
+    The bus saves itself through `Bus::save()` and `Bus::restore()`, so RAM,
+    mapper registers and devices all come along with the core. A state is
+    restored onto a machine built the same way as the one that saved it. ROM
+    images given to a mapper are part of how it was built, so they aren't
+    saved, only which banks were switched in. A bus that doesn't override
+    the two methods saves nothing, see bus.rs.
+
+    States are saved between instructions. Stepping by cycle runs a whole
+    instruction at once and hands out its bus cycles afterwards, so a core
+    partway through handing them out can't be saved.
+*/
+
+const MAGIC: [u8; 8] = *b"LOLEI65S";
+const VERSION: u16 = 1;
+
+// Builds up the bytes of a save state.
+#[derive(Default)]
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self { data: Vec::new() }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn u8(&mut self, value: u8) {
+        self.data.push(value);
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.data.push(value as u8);
+    }
+
+    pub fn u16(&mut self, value: u16) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u32(&mut self, value: u32) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn u64(&mut self, value: u64) {
+        self.data.extend_from_slice(&value.to_le_bytes());
+    }
+
+    // Bytes of a length both sides already know, like a 64kb RAM.
+    pub fn bytes(&mut self, data: &[u8]) {
+        self.data.extend_from_slice(data);
+    }
+
+    // Bytes that could be any length, with the length in front.
+    pub fn block(&mut self, data: &[u8]) {
+        self.u32(data.len() as u32);
+        self.bytes(data);
+    }
+}
+
+// Takes the bytes of a save state back apart, in the order they were written.
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    // True once everything has been read.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn bytes(&mut self, length: usize) -> Result<&'a [u8], EmulatorError> {
+        if length > self.data.len() {
+            return Err(EmulatorError::SaveState("Save state is cut short".to_string()))
+        }
+
+        let (bytes, rest) = self.data.split_at(length);
+        self.data = rest;
+
+        Ok(bytes)
+    }
+
+    pub fn u8(&mut self) -> Result<u8, EmulatorError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    pub fn bool(&mut self) -> Result<bool, EmulatorError> {
+        Ok(self.u8()? != 0)
+    }
+
+    pub fn u16(&mut self) -> Result<u16, EmulatorError> {
+        Ok(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    pub fn u32(&mut self) -> Result<u32, EmulatorError> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    pub fn u64(&mut self) -> Result<u64, EmulatorError> {
+        Ok(u64::from_le_bytes(self.bytes(8)?.try_into().unwrap()))
+    }
+
+    pub fn block(&mut self) -> Result<&'a [u8], EmulatorError> {
+        let length: u32 = self.u32()?;
+
+        self.bytes(length as usize)
+    }
+}
+
+fn variant_code(variant: Variant) -> u8 {
+    match variant {
+        Variant::Nmos6502 => 0,
+        Variant::Cmos65C02 => 1,
+        Variant::Rockwell65C02 => 2,
+        Variant::Wdc65C02 => 3,
+        Variant::Ricoh2A03 => 4,
+        Variant::W65816 => 5,
+    }
+}
+
+// Everything on the core a step can change, besides memory. Save states
+// carry it in the middle, and the history keeps one from before every step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Registers {
+    acc: u8,
+    stat: u8,
+    pc: u16,
+    sp: u8,
+    ix: u8,
+    iy: u8,
+    ir: u8,
+    pub cycles: u64,
+    lines: [bool; 8], // IRQ, NMI, the latched NMI and RESET, halted, waiting, and the bus IRQ and NMI
+    emulation: bool,
+    acc_hi: u8,
+    ix_hi: u8,
+    iy_hi: u8,
+    sp_hi: u8,
+    dp: u16,
+    dbr: u8,
+    pbr: u8,
+}
+
+impl Registers {
+    pub fn of(core: &Core) -> Self {
+        Self {
+            acc: core.acc,
+            stat: core.stat.bits(),
+            pc: core.pc,
+            sp: core.sp,
+            ix: core.ix,
+            iy: core.iy,
+            ir: core.ir,
+            cycles: core.cycles,
+            lines: [
+                core.irq, core.nmi, core.nmi_pending, core.reset_pending,
+                core.halted, core.waiting, core.bus_irq, core.bus_nmi,
+            ],
+            emulation: core.emulation,
+            acc_hi: core.acc_hi,
+            ix_hi: core.ix_hi,
+            iy_hi: core.iy_hi,
+            sp_hi: core.sp_hi,
+            dp: core.dp,
+            dbr: core.dbr,
+            pbr: core.pbr,
+        }
+    }
+
+    // Puts the registers back on the core, dropping anything left over from
+    // the instruction it was partway through.
+    pub fn apply(&self, core: &mut Core) {
+        core.acc = self.acc;
+        core.stat = Status::from_bits(self.stat);
+        core.pc = self.pc;
+        core.sp = self.sp;
+        core.ix = self.ix;
+        core.iy = self.iy;
+        core.ir = self.ir;
+        core.cycles = self.cycles;
+        [
+            core.irq, core.nmi, core.nmi_pending, core.reset_pending,
+            core.halted, core.waiting, core.bus_irq, core.bus_nmi,
+        ] = self.lines;
+        core.emulation = self.emulation;
+        core.acc_hi = self.acc_hi;
+        core.ix_hi = self.ix_hi;
+        core.iy_hi = self.iy_hi;
+        core.sp_hi = self.sp_hi;
+        core.dp = self.dp;
+        core.dbr = self.dbr;
+        core.pbr = self.pbr;
+
+        // Whatever was decoded last belongs to the old state.
+        core.decoded = None;
+        core.info = None;
+        core.page_crossed = false;
+        core.bus_queue.clear();
+        core.write_fault = None;
+        core.stack_fault = None;
+    }
+
+    fn save(&self, state: &mut StateWriter) {
+        state.u8(self.acc);
+        state.u8(self.stat);
+        state.u16(self.pc);
+        state.u8(self.sp);
+        state.u8(self.ix);
+        state.u8(self.iy);
+        state.u8(self.ir);
+        state.u64(self.cycles);
+
+        for line in self.lines {
+            state.bool(line);
+        }
+
+        state.bool(self.emulation);
+        state.u8(self.acc_hi);
+        state.u8(self.ix_hi);
+        state.u8(self.iy_hi);
+        state.u8(self.sp_hi);
+        state.u16(self.dp);
+        state.u8(self.dbr);
+        state.u8(self.pbr);
+    }
+
+    fn restore(state: &mut StateReader) -> Result<Self, EmulatorError> {
+        let (acc, stat, pc, sp) = (state.u8()?, state.u8()?, state.u16()?, state.u8()?);
+        let (ix, iy, ir, cycles) = (state.u8()?, state.u8()?, state.u8()?, state.u64()?);
+
+        let mut lines: [bool; 8] = [false; 8];
+        for line in &mut lines {
+            *line = state.bool()?;
+        }
+
+        let (emulation, acc_hi, ix_hi, iy_hi) = (state.bool()?, state.u8()?, state.u8()?, state.u8()?);
+        let (sp_hi, dp, dbr, pbr) = (state.u8()?, state.u16()?, state.u8()?, state.u8()?);
+
+        Ok(Self { acc, stat, pc, sp, ix, iy, ir, cycles, lines, emulation, acc_hi, ix_hi, iy_hi, sp_hi, dp, dbr, pbr })
+    }
+}
+
+impl Core {
+    // The whole machine as a save state.
+    pub fn save_state(&self) -> Result<Vec<u8>, EmulatorError> {
+        if !self.bus_queue.is_empty() {
+            return Err(EmulatorError::SaveState("Can't save partway through an instruction".to_string()))
+        }
+
+        let mut state: StateWriter = StateWriter::new();
+
+        state.bytes(&MAGIC);
+        state.u16(VERSION);
+        state.u8(variant_code(self.variant));
+
+        Registers::of(self).save(&mut state);
+
+        state.u16(self.rom.len() as u16);
+        for range in &self.rom {
+            state.u16(*range.start());
+            state.u16(*range.end());
+        }
+        state.bool(self.rom_writes == RomWrites::Trap);
+        state.bool(self.stack_checks);
+
+        state.block(&self.extended);
+
+        let mut bus: StateWriter = StateWriter::new();
+        self.bus.save(&mut bus);
+        state.block(&bus.into_bytes());
+
+        Ok(state.into_bytes())
+    }
+
+    // Puts the machine back how a save state left it. The state has to be
+    // for the same variant, and the bus is expected to be built the same way.
+    // The core is left alone unless the whole state could be read, though a
+    // bus that doesn't match can be left partway restored.
+    pub fn restore_state(&mut self, data: &[u8]) -> Result<(), EmulatorError> {
+        let mut state: StateReader = StateReader::new(data);
+
+        if state.bytes(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+            return Err(EmulatorError::SaveState("Not a save state".to_string()))
+        }
+
+        let version: u16 = state.u16()?;
+        if version != VERSION {
+            return Err(EmulatorError::SaveState(format!("Unsupported version {}, expected {}", version, VERSION)))
+        }
+
+        let variant: u8 = state.u8()?;
+        if variant != variant_code(self.variant) {
+            return Err(EmulatorError::SaveState(format!("Saved from a different CPU than the {:?}", self.variant)))
+        }
+
+        let registers: Registers = Registers::restore(&mut state)?;
+
+        let mut rom: Vec<std::ops::RangeInclusive<u16>> = Vec::new();
+        for _ in 0..state.u16()? {
+            rom.push(state.u16()?..=state.u16()?);
+        }
+        let rom_writes: RomWrites = if state.bool()? { RomWrites::Trap } else { RomWrites::Ignore };
+        let stack_checks: bool = state.bool()?;
+
+        let extended: &[u8] = state.block()?;
+        if extended.len() != self.extended.len() {
+            return Err(EmulatorError::SaveState("Extended memory is the wrong size".to_string()))
+        }
+
+        let mut bus: StateReader = StateReader::new(state.block()?);
+
+        if !state.is_empty() {
+            return Err(EmulatorError::SaveState("Unexpected data at the end".to_string()))
+        }
+
+        self.bus.restore(&mut bus)?;
+
+        if !bus.is_empty() {
+            return Err(EmulatorError::SaveState("Bus state doesn't match the bus".to_string()))
+        }
+
+        registers.apply(self);
+        self.rom = rom;
+        self.rom_writes = rom_writes;
+        self.stack_checks = stack_checks;
+        self.extended.copy_from_slice(extended);
+
+        Ok(())
+    }
+
+    pub fn save_file(&self, path: &str) -> Result<(), EmulatorError> {
+        fs::write(path, self.save_state()?)?;
+
+        Ok(())
+    }
+
+    pub fn restore_file(&mut self, path: &str) -> Result<(), EmulatorError> {
+        let data: Vec<u8> = fs::read(path)?;
+
+        self.restore_state(&data)
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{error::EmulatorError, system::Core};
+
+/*
+    The hardware stack.
+
+    The 8-bit parts keep their stack in page one, 0x0100-0x01FF, and it grows
+    down. The stack pointer is the low byte of the next free slot, so a push
+    writes and then decrements it, and a pull increments it and then reads.
+    Both wrap within page one rather than running into the zero page or
+    page two, the same as the hardware.
+
+    Wrapping is almost always a bug in the program though, like runaway
+    recursion or unbalanced pushes and pulls. With `stack_checks` set a push
+    with the stack pointer at 0x00 or a pull with it at 0xFF is reported as a
+    `StackOverflow` or `StackUnderflow`, with the PC of the instruction doing
+    it. Like a trapped ROM write, the instruction still finishes and the
+    error comes back from the step.
+
+    The 65816 uses these in emulation mode. Its native 16-bit stack is in
+    w65816.rs.
+*/
+
+impl Core {
+    // Where the next push goes.
+    pub fn stack_address(&self) -> u16 {
+        0x0100 | self.sp as u16
+    }
+
+    pub fn push(&mut self, value: u8) {
+        if self.sp == 0x00 { self.stack_fault(EmulatorError::StackOverflow { pc: self.pc }) }
+
+        self.write(self.stack_address(), value);
+        self.sp = self.sp.wrapping_sub(1);
+    }
+
+    pub fn pull(&mut self) -> u8 {
+        if self.sp == 0xFF { self.stack_fault(EmulatorError::StackUnderflow { pc: self.pc }) }
+
+        self.sp = self.sp.wrapping_add(1);
+        self.read(self.stack_address())
+    }
+
+    // Pushes the high byte first, so the word sits in memory little endian.
+    pub fn push_word(&mut self, value: u16) {
+        self.push((value >> 8) as u8);
+        self.push(value as u8);
+    }
+
+    pub fn pull_word(&mut self) -> u16 {
+        let low: u8 = self.pull();
+        let high: u8 = self.pull();
+
+        ((high as u16) << 8) | low as u16
+    }
+
+    // Only the first fault of an instruction is kept.
+    fn stack_fault(&mut self, error: EmulatorError) {
+        if self.stack_checks && self.stack_fault.is_none() {
+            self.stack_fault = Some(error);
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::instruction::flags;
+
+use std::fmt;
+
+/*
+    The processor status register, P.
+
+    Six of its bits are flags with storage behind them: N, V, D, I, Z and C.
+    Bits 5 and 4 have none on the 8-bit parts. They only show up in the copy
+    of the status pushed to the stack, where bit 5 is always set and bit 4,
+    B, is set by BRK and PHP and clear for IRQ and NMI. That's the only way
+    a handler can tell them apart. PLP and RTI ignore both bits when the
+    status is pulled back.
+
+    In 65816 native mode bits 5 and 4 become the M and X width flags, which
+    are stored like the others. Emulation mode pins both widths to 8 bits,
+    so there they go back to only existing on the stack.
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Flag {
+    Carry,
+    Zero,
+    Interrupt, // IRQs are ignored while set
+    Decimal,
+    Index, // X, 65816 native mode only. Set for 8-bit index registers
+    Memory, // M, 65816 native mode only. Set for an 8-bit accumulator
+    Overflow,
+    Negative,
+}
+
+impl Flag {
+    pub const fn mask(self) -> u8 {
+        match self {
+            Flag::Carry => flags::C,
+            Flag::Zero => flags::Z,
+            Flag::Interrupt => flags::I,
+            Flag::Decimal => flags::D,
+            Flag::Index => flags::B,
+            Flag::Memory => Status::UNUSED,
+            Flag::Overflow => flags::V,
+            Flag::Negative => flags::N,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Status(u8);
+
+impl Status {
+    const UNUSED: u8 = 0b00100000; // Bit 5, always set when pushed
+    const BREAK: u8 = 0b00010000; // Bit 4, set when pushed by BRK or PHP
+
+    // Every bit as given, for the 65816 in native mode where all eight are flags.
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    // A status pulled from the stack by PLP or RTI, dropping B and bit 5.
+    pub const fn pulled(value: u8) -> Self {
+        Self(value & !(Self::UNUSED | Self::BREAK))
+    }
+
+    // The copy pushed to the stack, with bit 5 set and B set for BRK and PHP.
+    pub const fn pushed(self, brk: bool) -> u8 {
+        let bits: u8 = (self.0 & !(Self::UNUSED | Self::BREAK)) | Self::UNUSED;
+
+        if brk { bits | Self::BREAK } else { bits }
+    }
+
+    pub const fn get(self, flag: Flag) -> bool {
+        self.0 & flag.mask() != 0
+    }
+
+    pub fn set(&mut self, flag: Flag, set: bool) {
+        if set { self.0 |= flag.mask() } else { self.0 &= !flag.mask() }
+    }
+
+    // Zero and negative follow a result, as they do for most instructions.
+    pub fn set_zero_negative(&mut self, value: u8) {
+        self.set(Flag::Zero, value == 0);
+        self.set(Flag::Negative, value & 0x80 != 0);
+    }
+
+    // The carry as a number, for adding in.
+    pub const fn carry(self) -> u8 {
+        self.0 & flags::C
+    }
+}
+
+// The usual NV-BDIZC line, in capitals when set. Bits 5 and 4 only show up
+// as M and X when the 65816 has set them.
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: [(Flag, char); 8] = [
+            (Flag::Negative, 'n'), (Flag::Overflow, 'v'), (Flag::Memory, '-'), (Flag::Index, '-'),
+            (Flag::Decimal, 'd'), (Flag::Interrupt, 'i'), (Flag::Zero, 'z'), (Flag::Carry, 'c'),
+        ];
+
+        for (flag, name) in names {
+            let name: char = match (flag, self.get(flag)) {
+                (Flag::Memory, true) => 'M',
+                (Flag::Index, true) => 'X',
+                (_, true) => name.to_ascii_uppercase(),
+                (_, false) => name,
+            };
+
+            write!(f, "{}", name)?;
+        }
+
+        Ok(())
+    }
+}
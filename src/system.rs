@@ -1,31 +1,113 @@
-use crate::trie::Trie;
-
+use crate::{
+    bus::{Bus, Ram, RomWrites, WriteFault},
+    cycles::{self, BusCycle, Replay},
+    error::EmulatorError,
+    history::{History, Written},
+    instruction::{Instruction, Penalty},
+    status::{Flag, Status},
+    table::{Entry, Function, OpcodeTable},
+    trace::{TraceFormat, Tracer},
+    w65816,
+};
+
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
 use std::io::{self, Write};
 use regex::Regex;
 use std::fs;
 
+// The CPU being emulated. This picks the opcode table built by `gen_table()`
+// along with a handful of behaviour differences in the opcodes themselves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Variant {
+    #[default]
+    Nmos6502, // Original NMOS 6502, including the undocumented opcodes
+    Cmos65C02, // Original 65C02, without the Rockwell bit instructions
+    Rockwell65C02, // Rockwell R65C02, adds BBR, BBS, RMB and SMB
+    Wdc65C02, // WDC W65C02S, the Rockwell set plus WAI and STP
+    Ricoh2A03, // NES CPU, an NMOS 6502 with the decimal mode circuitry cut
+    W65816, // WDC W65C816S, 16-bit registers and 24-bit addressing
+}
+
+impl Variant {
+    // True for any of the CMOS 65C02 parts.
+    pub fn is_cmos(&self) -> bool {
+        !matches!(self, Variant::Nmos6502 | Variant::Ricoh2A03)
+    }
+
+    // False for parts where the D flag can be set but ADC and SBC stay binary.
+    pub fn has_decimal_mode(&self) -> bool {
+        *self != Variant::Ricoh2A03
+    }
+
+    // True for parts with the Rockwell bit manipulation instructions.
+    pub fn has_bit_instructions(&self) -> bool {
+        matches!(self, Variant::Rockwell65C02 | Variant::Wdc65C02)
+    }
+}
+
 pub struct Core {
     pub acc: u8, // 8-bit accumulator register
-    pub stat: u8, // 7-bit status register, stored as u8
-    // Negative, Overflow, Ignored, Break, Decimal, Interrupt, Zero, Carry.
+    pub stat: Status, // Status register, see status.rs
     pub pc: u16, // 16-bit program counter
     pub sp: u8, // 8-bit stack pointer
     // The stack is conventionally at 0x0100-0x01FF in memory
     pub ix: u8, // 8-bit index register
     pub iy: u8, // 8-bit index register
     pub ir: u8, // 8-bit instruction register
-    pub decoded: Option<fn(&mut Core) -> &mut Core>, // Stores opcode funciton pointer
-    pub info: Option<String>, // Opcode infor string, mainly for core dump function.
-    // Note: This doesn't align with any particular systems, it is just enough to 
-    // load specific 6502 test binaries.
-    pub memory: [u8; 65536], // 64kb of memory
+    pub decoded: Option<Function>, // Stores opcode funciton pointer
+    pub info: Option<Instruction>, // What the opcode is, see instruction.rs
+    pub cycles: u64, // Clock cycles elapsed since the core was initialized
+    pub page_crossed: bool, // Set by indexed addressing when the effective address crosses a page
+    // Interrupt inputs, checked between instructions.
+    pub irq: bool, // IRQ line level, serviced while asserted and the interrupt flag is clear
+    pub nmi: bool, // NMI line level, only a rising edge requests an interrupt
+    pub nmi_pending: bool, // Latched NMI edge waiting to be serviced
+    pub reset_pending: bool, // RESET requested, takes priority over everything else
+    pub halted: bool, // Set by JAM on NMOS parts and STP on the WDC 65C02, cleared by RESET
+    pub waiting: bool, // Set by WAI on the WDC 65C02, cleared by any interrupt line
+    pub bus_irq: bool, // IRQ level driven by the bus last time it was checked
+    pub bus_nmi: bool, // NMI level driven by the bus last time it was checked
+    pub bus_queue: VecDeque<Option<BusCycle>>, // Rest of the current instruction when stepping by cycle
+    pub(crate) replay: Option<Replay>, // Bus accesses planned for the instruction running, see cycles.rs
+    pub(crate) journal: Option<Vec<Written>>, // Bytes written by the step running, while the history keeps them
+    pub variant: Variant, // Which CPU is being emulated
+    // 65816 registers, see w65816.rs. The 8-bit variants leave these alone.
+    pub emulation: bool, // E flag, set while the 65816 is in 6502 emulation mode
+    pub acc_hi: u8, // B, the high byte of the 16-bit accumulator
+    pub ix_hi: u8, // High byte of X, zero while the X flag is set
+    pub iy_hi: u8, // High byte of Y, zero while the X flag is set
+    pub sp_hi: u8, // High byte of the stack pointer, 0x01 in emulation mode
+    pub dp: u16, // Direct page register
+    pub dbr: u8, // Data bank register
+    pub pbr: u8, // Program bank register
+    // Note: The default bus doesn't align with any particular systems, it is just
+    // 64kb of RAM, enough to load specific 6502 test binaries.
+    pub bus: Box<dyn Bus>, // Everything the core reads and writes goes through here, see bus.rs
+    pub rom: Vec<RangeInclusive<u16>>, // Address ranges the CPU can't write to
+    pub rom_writes: RomWrites, // Whether writes to ROM are ignored or trapped
+    pub write_fault: Option<WriteFault>, // First trapped write to ROM, until it's taken
+    pub stack_checks: bool, // Report pushes and pulls that wrap the stack pointer, see stack.rs
+    pub stack_fault: Option<EmulatorError>, // First stack overflow or underflow, until it's taken
+    pub extended: Vec<u8>, // Banks 0x01-0xFF for 24-bit addressing, empty unless 65816
+}
+
+impl Default for Core {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Core {
     pub fn new() -> Self {
+        Self::with_bus(Box::new(Ram::new()))
+    }
+
+    // A core wired up to the given bus instead of plain RAM.
+    pub fn with_bus(bus: Box<dyn Bus>) -> Self {
         Self {
             acc: 0,
-            stat: 0,
+            stat: Status::default(),
             pc: 0,
             sp: 0,
             ix: 0,
@@ -33,8 +115,159 @@ impl Core {
             ir: 0,
             decoded: None,
             info: None,
-            memory: [0; 65536],
+            cycles: 0,
+            page_crossed: false,
+            irq: false,
+            nmi: false,
+            nmi_pending: false,
+            reset_pending: false,
+            halted: false,
+            waiting: false,
+            bus_irq: false,
+            bus_nmi: false,
+            bus_queue: VecDeque::new(),
+            replay: None,
+            journal: None,
+            variant: Variant::Nmos6502,
+            emulation: true,
+            acc_hi: 0,
+            ix_hi: 0,
+            iy_hi: 0,
+            sp_hi: 0x01,
+            dp: 0,
+            dbr: 0,
+            pbr: 0,
+            bus,
+            rom: Vec::new(),
+            rom_writes: RomWrites::Ignore,
+            write_fault: None,
+            stack_checks: false,
+            stack_fault: None,
+            extended: Vec::new(),
+        }
+    }
+
+    // Read a byte from the bus.
+    pub fn read(&mut self, address: u16) -> u8 {
+        let Some(mut replay) = self.replay.take() else { return self.bus.read(address) };
+
+        let value: u8 = replay.read(self, address);
+        self.replay = Some(replay);
+
+        value
+    }
+
+    // Write a byte to the bus, unless it's in ROM.
+    pub fn write(&mut self, address: u16, value: u8) {
+        match self.replay.take() {
+            Some(mut replay) => {
+                replay.write(self, address, value);
+                self.replay = Some(replay);
+            }
+            None => self.write_bus(address, value),
+        }
+    }
+
+    // The write itself, without the cycle model seeing it.
+    pub(crate) fn write_bus(&mut self, address: u16, value: u8) {
+        if self.rom.iter().any(|range| range.contains(&address)) {
+            if self.rom_writes == RomWrites::Trap && self.write_fault.is_none() {
+                self.write_fault = Some(WriteFault { address, value, pc: self.pc });
+            }
+
+            return
+        }
+
+        // The history keeps the byte the write lands in, see history.rs.
+        let old: Option<u8> = if self.journal.is_some() { self.bus.stored(address) } else { None };
+
+        self.bus.write(address, value);
+
+        if let Some(old) = old {
+            if let (Some(journal), Some(new)) = (&mut self.journal, self.bus.stored(address)) {
+                journal.push(Written { address: address as u32, old, new });
+            }
+        }
+    }
+
+    // Look at a byte on the bus without reading it, for dumps and debugging.
+    pub fn peek(&self, address: u16) -> u8 {
+        self.bus.peek(address)
+    }
+
+    // Write a block of bytes from the start address onwards, wrapping at the
+    // top of memory. This goes straight to the bus, so ROM can be loaded.
+    pub fn load(&mut self, start: u16, data: &[u8]) {
+        for (offset, value) in data.iter().enumerate() {
+            self.bus.write(start.wrapping_add(offset as u16), *value);
+        }
+    }
+
+    // Load a block of bytes and mark it as ROM.
+    pub fn load_rom(&mut self, start: u16, data: &[u8]) {
+        if data.is_empty() { return }
+
+        self.load(start, data);
+
+        // A block running off the top of memory wraps around to the bottom.
+        let end: usize = start as usize + data.len().min(0x10000) - 1;
+
+        if end > 0xFFFF {
+            self.rom.push(start..=0xFFFF);
+            self.rom.push(0x0000..=(end - 0x10000) as u16);
+        } else {
+            self.rom.push(start..=end as u16);
+        }
+    }
+
+    // Read from a 24-bit address. Bank zero is the bus,
+    // anything above it that isn't backed by `extended` reads as zero.
+    pub fn read_long(&mut self, address: u32) -> u8 {
+        match address {
+            0x0000..=0xFFFF => self.read(address as u16),
+            _ => self.extended.get(address as usize - 0x10000).copied().unwrap_or(0),
+        }
+    }
+
+    // Write to a 24-bit address. Writes above bank zero without `extended` are lost.
+    pub fn write_long(&mut self, address: u32, value: u8) {
+        match address {
+            0x0000..=0xFFFF => self.write(address as u16, value),
+            _ => if let Some(byte) = self.extended.get_mut(address as usize - 0x10000) {
+                if let Some(journal) = &mut self.journal {
+                    journal.push(Written { address, old: *byte, new: value });
+                }
+
+                *byte = value
+            },
+        }
+    }
+
+    // Assert or release the IRQ line. It is level triggered, so the interrupt
+    // keeps firing until the line is released or the interrupt flag is set.
+    pub fn set_irq(&mut self, asserted: bool) {
+        self.irq = asserted;
+    }
+
+    // Assert or release the NMI line. NMI is edge triggered, so only the
+    // transition to asserted latches an interrupt.
+    pub fn set_nmi(&mut self, asserted: bool) {
+        if asserted && !self.nmi {
+            self.nmi_pending = true;
         }
+
+        self.nmi = asserted;
+    }
+
+    // Pulse the NMI line, for devices that don't hold it.
+    pub fn trigger_nmi(&mut self) {
+        self.set_nmi(true);
+        self.set_nmi(false);
+    }
+
+    // Request a RESET, which runs before the next instruction.
+    pub fn trigger_reset(&mut self) {
+        self.reset_pending = true;
     }
 
     // Function for dumping the state of the core.
@@ -46,13 +279,25 @@ impl Core {
 
         println!("-->core dump<--");
         println!("acc:     0x{:02X}", core.acc);
-        println!("stat:    0b{:08b}", core.stat);
+        println!("stat:    {}", core.stat);
         println!("pc:      0x{:04X}", core.pc);
         println!("sp:      0x{:02X}", core.sp);
         println!("ix:      0x{:02X}", core.ix);
         println!("iy:      0x{:02X}", core.iy);
         println!("ir:      0x{:02X}", core.ir);
-        println!("infor:   {:?}", core.info);
+        match core.info {
+            Some(info) => println!("infor:   {}", info),
+            None => println!("infor:   None"),
+        }
+        println!("cycles:  {}", core.cycles);
+        println!("lines:   irq {} nmi {}", core.irq as u8, core.nmi as u8);
+        if core.variant == Variant::W65816 {
+            println!(
+                "65816:   e {} b 0x{:02X} xh 0x{:02X} yh 0x{:02X} sh 0x{:02X} dp 0x{:04X} dbr 0x{:02X} pbr 0x{:02X}",
+                core.emulation as u8, core.acc_hi, core.ix_hi, core.iy_hi,
+                core.sp_hi, core.dp, core.dbr, core.pbr,
+            );
+        }
         // Looking at a bare function pointer isn't very helpful.
         //println!("decoded: {:?}", core.decoded);
 
@@ -91,45 +336,316 @@ impl Core {
 
 // The load, fetch and decode functions are short, but are separated for clarity.
 // Initializing the core.
-fn init() -> Core {
-    let mut core: Core = Core::new();
+fn init(variant: Variant) -> Core {
+    power_on(Core::new(), variant)
+}
+
+// Power on holds RESET, so the core comes up through the reset sequence. The
+// stack pointer starts at 0x00 and the sequence leaves it at 0xFD, with the
+// interrupt flag set and PC taken from the reset vector on the core's bus.
+pub(crate) fn power_on(mut core: Core, variant: Variant) -> Core {
+    core.variant = variant;
 
-    core.sp = 0xFF; // Initialize stack pointer
+    // The 65816 gets the other 255 banks of its 16MB address space.
+    if variant == Variant::W65816 { core.extended = vec![0; 0xFF0000] }
+
+    reset(&mut core);
 
     core
 }
 
+// Press the reset button. Registers and memory are kept and execution starts
+// from whatever the reset vector in memory points to.
+fn reset(core: &mut Core) {
+    core.reset_pending = false;
+    core.nmi_pending = false;
+    core.halted = false;
+    core.waiting = false;
+
+    reset_sequence(core);
+}
+
+// Checks the interrupt lines between instructions. RESET comes first, then
+// NMI, then IRQ if the interrupt flag is clear.
+// Returns true if an interrupt sequence was run instead of an instruction.
+fn interrupts(core: &mut Core) -> bool {
+    if core.reset_pending {
+        reset(core);
+    } else if core.nmi_pending {
+        core.nmi_pending = false;
+        interrupt_sequence(core, 0xFFFA);
+    } else if core.irq && !core.stat.get(Flag::Interrupt) {
+        interrupt_sequence(core, 0xFFFE);
+    } else {
+        return false
+    }
+
+    true
+}
+
+// Pushes the return address and status, then jumps through the given vector.
+// Unlike BRK, the status is pushed with the break flag clear.
+fn interrupt_sequence(core: &mut Core, vector: u16) {
+    // The 65816 also has to deal with the program bank and native mode.
+    if core.variant == Variant::W65816 { return w65816::interrupt(core, vector) }
+
+    core.push_word(core.pc);
+    core.push(core.stat.pushed(false));
+
+    core.stat.set(Flag::Interrupt, true);
+
+    // The 65C02 also clears decimal mode on entering a handler.
+    if core.variant.is_cmos() { core.stat.set(Flag::Decimal, false) }
+
+    let low: u16 = core.read(vector) as u16;
+    core.pc = ((core.read(vector + 1) as u16) << 8) | low;
+    core.cycles += 7;
+}
+
+// RESET goes through the same steps as an interrupt, but the stack writes
+// are suppressed so only the stack pointer moves.
+fn reset_sequence(core: &mut Core) {
+    if core.variant == Variant::W65816 { w65816::reset(core) }
+
+    core.sp = core.sp.wrapping_sub(3);
+
+    core.stat.set(Flag::Interrupt, true);
+
+    if core.variant.is_cmos() { core.stat.set(Flag::Decimal, false) } // Decimal mode is cleared on the 65C02
+
+    let low: u16 = core.read(0xFFFC) as u16;
+    core.pc = ((core.read(0xFFFD) as u16) << 8) | low;
+    core.cycles += 7;
+}
+
+// What a single pass of the fetch/decode/execute loop did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Instruction(u8), // Ran the instruction with this opcode
+    Interrupt, // Ran the RESET, NMI or IRQ sequence instead
+    Waiting, // Still asleep after a WAI
+    Halted, // Stopped by JAM or STP, nothing ran
+}
+
+// One pass of the fetch/decode/execute loop, with the bus ticked for every
+// cycle it took.
+pub(crate) fn run_step(core: &mut Core, table: &OpcodeTable) -> Result<Event, EmulatorError> {
+    let before: u64 = core.cycles;
+    let result: Result<Event, EmulatorError> = step(core, table);
+
+    settle(core, before, result)
+}
+
+// Everything after a step: ticking the bus for the cycles it took, following
+// the interrupt lines and reporting faults.
+fn settle(core: &mut Core, before: u64, result: Result<Event, EmulatorError>) -> Result<Event, EmulatorError> {
+    let event: Event = result?;
+
+    for _ in before..core.cycles {
+        core.bus.tick();
+    }
+
+    poll_bus(core);
+
+    // The instruction still finishes when it writes to ROM or wraps the
+    // stack, the fault is reported once it's done.
+    let stack_fault: Option<EmulatorError> = core.stack_fault.take();
+
+    match core.write_fault.take() {
+        Some(fault) => Err(EmulatorError::BusFault(fault)),
+        None => stack_fault.map_or(Ok(event), Err),
+    }
+}
+
+// Follows the interrupt lines driven by the bus. Only changes are passed on,
+// so the lines can still be driven by hand through `set_irq()` and `set_nmi()`.
+fn poll_bus(core: &mut Core) {
+    let irq: bool = core.bus.irq();
+    let nmi: bool = core.bus.nmi();
+
+    if irq != core.bus_irq { core.set_irq(irq) }
+    if nmi != core.bus_nmi { core.set_nmi(nmi) }
+
+    core.bus_irq = irq;
+    core.bus_nmi = nmi;
+}
+
+// A pending interrupt is serviced in place of the next instruction.
+fn step(core: &mut Core, table: &OpcodeTable) -> Result<Event, EmulatorError> {
+    // A halted core ignores everything except RESET.
+    if core.halted && !core.reset_pending { return Ok(Event::Halted) }
+
+    // WAI sleeps until an interrupt line is asserted. A masked IRQ still wakes
+    // the core, which then carries on with the next instruction.
+    if core.waiting {
+        if core.irq || core.nmi_pending || core.reset_pending {
+            core.waiting = false;
+        } else {
+            core.cycles += 1;
+            return Ok(Event::Waiting)
+        }
+    }
+
+    if interrupts(core) { return Ok(Event::Interrupt) }
+
+    fetch(core);
+
+    decode(core, table);
+
+    execute(core)?;
+
+    Ok(Event::Instruction(core.ir))
+}
+
+// Where a program ended up when it trapped, see `run_until_trap()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trap {
+    Passed(u16), // Trapped at the success address
+    Failed(u16), // Trapped anywhere else, or halted
+    Timeout(u16), // Ran out of cycles without trapping, PC where it got to
+}
+
+// Cycles a test gets to trap in unless it's given a limit. Klaus Dormann's
+// functional test needs under a tenth of this.
+pub const CYCLE_LIMIT: u64 = 1_000_000_000;
+
+// True when the last step left PC where it was, like `JMP *` or a branch to
+// itself. Waiting for an interrupt and the 65816 block moves, which repeat
+// until they're done, leave PC alone without being stuck.
+fn trapped(core: &Core, pc: u16) -> bool {
+    let block_move: bool = core.variant == Variant::W65816 && matches!(core.ir, 0x44 | 0x54);
+
+    core.pc == pc && !core.waiting && !block_move
+}
+
+// Runs until the program traps itself. Test suites like Klaus Dormann's
+// report their results this way, finishing on a trap at a known success
+// address or stopping on a trap anywhere else when a test fails.
+// A program that never traps times out once it has run for the given number
+// of cycles.
+pub fn run_until_trap(core: &mut Core, table: &OpcodeTable, success: u16, limit: u64) -> Result<Trap, EmulatorError> {
+    let end: u64 = core.cycles.saturating_add(limit);
+
+    loop {
+        let pc: u16 = core.pc;
+
+        if core.cycles >= end { return Ok(Trap::Timeout(pc)) }
+
+        run_step(core, table)?;
+
+        if core.halted { return Ok(Trap::Failed(core.pc)) }
+
+        if trapped(core, pc) {
+            return Ok(if pc == success { Trap::Passed(pc) } else { Trap::Failed(pc) })
+        }
+    }
+}
+
+// Loads a test binary and runs it from the start address until it traps,
+// without the shell. The binary has to fit in memory.
+pub fn run_test(
+    table: &OpcodeTable, variant: Variant, data: &[u8], load: u16, start: u16, success: u16, limit: u64,
+) -> Result<(Trap, u64), EmulatorError> {
+    if load as usize + data.len() > 0x10000 {
+        return Err(EmulatorError::Load("Binary exceeds memory bounds".to_string()))
+    }
+
+    let mut core: Core = init(variant);
+
+    core.load(load, data);
+    core.pc = start;
+    core.cycles = 0;
+
+    let trap: Trap = run_until_trap(&mut core, table, success, limit)?;
+
+    Ok((trap, core.cycles))
+}
+
+// Steps a single clock cycle, returning the bus access made on it, or None
+// for a cycle without one. The first cycle of an instruction runs all of it
+// and queues up the rest of its accesses, see cycles.rs. The cycle counter
+// follows the queue, so it only counts cycles that have been handed out.
+// An error comes back on the instruction's first cycle, and anything it got
+// through before the error is still handed out by the following calls.
+// The 65816 isn't modelled and can't be stepped by cycle.
+pub fn step_cycle(core: &mut Core, table: &OpcodeTable) -> Result<Option<BusCycle>, EmulatorError> {
+    if core.bus_queue.is_empty() {
+        let planned: Vec<cycles::Planned> = cycles::plan(core, table)?;
+        let before: u64 = core.cycles;
+
+        // The accesses are made while the instruction runs, before the bus
+        // is ticked for its cycles.
+        core.replay = Some(Replay::new(planned));
+        let result: Result<Event, EmulatorError> = step(core, table);
+        let made: Vec<BusCycle> = cycles::finish(core, result.is_ok());
+        let result: Result<Event, EmulatorError> = settle(core, before, result);
+
+        // A halted or waiting core keeps the clock running without the bus.
+        let taken: u64 = (core.cycles - before).max(1);
+        core.cycles = before;
+
+        if made.is_empty() {
+            core.bus_queue.extend((0..taken).map(|_| None));
+        } else {
+            core.bus_queue.extend(made.into_iter().map(Some));
+        }
+
+        result?;
+    }
+
+    core.cycles += 1;
+
+    Ok(core.bus_queue.pop_front().flatten())
+}
+
 // Function for loading the next instruction from memory.
 fn fetch(core: &mut Core) {
-    core.ir = core.memory[core.pc as usize];
+    core.ir = core.read_long(((core.pbr as u32) << 16) | core.pc as u32);
 }
 
-// Decoding the instruction using the prefix tree.
-fn decode(core: &mut Core, prefix_tree: &Trie) {
-    core.decoded = prefix_tree.get_function(core.ir);
-    core.info = prefix_tree.get_instruction(core.ir);
+// Decoding the instruction with a lookup in the opcode table.
+fn decode(core: &mut Core, table: &OpcodeTable) {
+    let entry: Option<&Entry> = table.get(core.ir);
+
+    core.decoded = entry.map(|entry| entry.function);
+    core.info = entry.map(|entry| entry.info);
 }
 
-// Parses function pointer from prefix tree and executes it.
-fn execute(core: &mut Core) {
+// Runs the function pointer from the opcode table.
+fn execute(core: &mut Core) -> Result<(), EmulatorError> {
+    core.page_crossed = false;
+
     match core.decoded {
         Some(func) => {
             func(core);
         }
         None => {
-            panic!("Invalid opcode: {:02X}", core.ir)
+            return Err(EmulatorError::InvalidOpcode { opcode: core.ir, address: core.pc })
         }
     }
+
+    core.cycles += instruction_cycles(core);
+
+    Ok(())
 }
 
-fn set_pc(core: &mut Core, target: u16) -> &mut Core {
-    let pcl: u8 = (target & 0xFF) as u8; // Lower byte
-    let pch: u8 = (target >> 8) as u8; // Higher byte
+// Works out the cycles taken by the last instruction from its base count,
+// plus one for instructions that take an extra cycle when indexing crosses a
+// page. Branches add their own penalties, so only the base count is used here.
+fn instruction_cycles(core: &Core) -> u64 {
+    let info: Instruction = match core.info {
+        Some(info) => info,
+        None => return 0,
+    };
+
+    let base: u64 = info.cycles as u64;
+
+    if info.penalty == Penalty::PageCross && core.page_crossed { base + 1 } else { base }
+}
 
-    // Set initial reset vector then use it to set program counter.
-    // A bit redundant, it's just symbolic I suppose for now.
-    core.memory[0xFFFC..=0xFFFD].copy_from_slice(&[pcl, pch]);
-    core.pc = ((core.memory[0xFFFD] as u16) << 8) | (core.memory[0xFFFC] as u16);
+// Jump straight to an address, leaving the reset vector alone.
+fn set_pc(core: &mut Core, target: u16) -> &mut Core {
+    core.pc = target;
 
     core
 }
@@ -146,11 +662,12 @@ fn parse_hex(start: &str) -> Result<u16, String> {
 fn help_out(args: Option<&str>) {
     match args {
         Some("load") | Some("LOAD") => {
-            println!("load <binary> <start>, LOAD <binary> <start> :");
+            println!("load <binary> <start> [rom], LOAD <binary> <start> [ROM] :");
             println!(" + Loads the binary into memory from the start address onwards.");
+            println!(" + Adding `rom` loads it as ROM, see `help rom` for what happens to writes.");
             println!(" + <binary> must be a file name without spaces, with the `.bin` extension.");
             println!(" + <target> must be a hexadecimal address starting with 0x.");
-            println!("Examples: load some_file.bin 0x200");
+            println!("Examples: load some_file.bin 0x200, load firmware.bin 0xE000 rom");
             println!("NOTE: Shorthand file names exist for some test files:");
             println!(" + `functest` = `6502_functional_test.bin`");
             println!(" + `dectest` = `6502_decimal_test.bin`");
@@ -160,8 +677,20 @@ fn help_out(args: Option<&str>) {
         Some("exec") | Some("EXEC") => {
             println!("exec <target>, EXEC <target> :");
             println!(" + Runs the passed binary starting at the given address.");
-            println!(" + <target> must be a hexadecimal address starting with 0x.");
-            println!("Examples: exec 0x200, exec 0x0200, exec 0x0");
+            println!(" + <target> must be a hexadecimal address starting with 0x,");
+            println!("   or `reset` to press reset and start from the reset vector.");
+            println!("Examples: exec 0x200, exec 0x0200, exec 0x0, exec reset");
+        }
+
+        Some("test") | Some("TEST") => {
+            println!("test <start> <success> [cycles], TEST <start> <success> [cycles] :");
+            println!(" + Runs from the start address until the program traps itself on");
+            println!("   something like `JMP *`, without stepping or drawing the core.");
+            println!(" + Passes when the trap is at the success address, fails anywhere else.");
+            println!(" + Both must be hexadecimal addresses starting with 0x.");
+            println!(" + Times out after [cycles] cycles without a trap, 1000000000 by default.");
+            println!("Examples: test 0x400 0x3469 (functest), test 0x200 <DONE> (dectest), test 0x200 0x210 5000");
+            println!("NOTE: dectest traps at DONE either way, dump 0xB to see its result (0 = pass).");
         }
 
         Some("dump") | Some("DUMP") => {
@@ -173,12 +702,62 @@ fn help_out(args: Option<&str>) {
         }
 
         Some("reset") | Some("RESET") => {
-            println!("reset, RESET :");
-            println!("Reinitializes the core.");
-            println!("NOTE: This does not currently involve reset vectors, just reinitializing.");
+            println!("reset [hard], RESET [HARD] :");
+            println!(" + Presses reset. Memory is kept and PC is loaded from the reset vector at 0xFFFC.");
+            println!(" + `reset hard` reinitializes the core, clearing memory like a power cycle.");
             println!("This is just useful for running binaries without having to rerun the emulator.")
         }
 
+        Some("rom") | Some("ROM") => {
+            println!("rom <ignore|trap>, ROM <IGNORE|TRAP> :");
+            println!(" + Sets what happens when the CPU writes to memory loaded as ROM.");
+            println!(" + `ignore` drops the write, like real hardware.");
+            println!(" + `trap` also stops execution and reports the address and PC.");
+            println!("Examples: rom ignore, rom trap");
+        }
+
+        Some("stack") | Some("STACK") => {
+            println!("stack <wrap|check>, STACK <WRAP|CHECK> :");
+            println!(" + Sets what happens when a push or pull wraps the stack pointer.");
+            println!(" + `wrap` carries on within page one, like real hardware.");
+            println!(" + `check` also stops execution and reports an overflow or underflow and the PC.");
+            println!("Examples: stack wrap, stack check");
+        }
+
+        Some("save") | Some("SAVE") => {
+            println!("save <file>, SAVE <file> :");
+            println!(" + Saves the whole machine to a file: registers, memory, cycle count and interrupt lines.");
+            println!(" + <file> must be a file name without spaces, and is overwritten if it exists.");
+            println!("Examples: save booted.state");
+        }
+
+        Some("restore") | Some("RESTORE") => {
+            println!("restore <file>, RESTORE <file> :");
+            println!(" + Puts the machine back how `save` left it. Carry on with `exec` from the PC it reports.");
+            println!(" + The file must have been saved with the same --cpu.");
+            println!("Examples: restore booted.state");
+        }
+
+        Some("trace") | Some("TRACE") => {
+            println!("trace <nestest|json|off> [file], TRACE <NESTEST|JSON|OFF> [file] :");
+            println!(" + Writes a line for every instruction `exec` runs, with PC, the instruction's bytes,");
+            println!("   its disassembly, A, X, Y, P, SP and the cycle count from before it ran.");
+            println!(" + `nestest` lines up with nestest.log, `json` writes a JSON object per line.");
+            println!(" + [file] is overwritten if it exists. Without one the trace replaces the core dump.");
+            println!(" + `off` stops tracing.");
+            println!("Examples: trace nestest my.log, trace json, trace off");
+        }
+
+        Some("rewind") | Some("REWIND") => {
+            println!("rewind [cycles], REWIND [cycles] :");
+            println!(" + Goes back one instruction, or at least the given number of cycles.");
+            println!(" + Works back through everything run with `exec`, until a `reset hard`.");
+            println!(" + Stops after each load, exec, test, reset and restore on the way.");
+            println!(" + While stepping, `b` and `b <cycles>` at the prompt do the same.");
+            println!(" + <cycles> must be a decimal number.");
+            println!("Examples: rewind, rewind 1000");
+        }
+
         Some("clear") | Some("CLEAR") => {
             println!("clear, CLEAR :");
             println!("Clears the screen.")
@@ -198,8 +777,15 @@ fn help_out(args: Option<&str>) {
             println!("Commands:");
             println!(" + load, LOAD - Loads the provided file into memory from a given start address.");
             println!(" + exec, EXEC - Runs a program from a given start address.");
+            println!(" + test, TEST - Runs a test binary until it traps, and reports pass or fail.");
             println!(" + dump, DUMP - Dump memory form a list of addresses");
-            println!(" + reset, RESET - Reinitialize the core.");
+            println!(" + rom, ROM - Ignore or trap writes to memory loaded as ROM.");
+            println!(" + stack, STACK - Wrap the stack pointer, or stop on overflow and underflow.");
+            println!(" + reset, RESET - Press reset, or reinitialize the core with `reset hard`.");
+            println!(" + save, SAVE - Save the whole machine to a file.");
+            println!(" + restore, RESTORE - Restore the machine from a saved file.");
+            println!(" + rewind, REWIND - Step back an instruction, or rewind a number of cycles.");
+            println!(" + trace, TRACE - Log every instruction run to a file or the screen.");
             println!(" + clear, CLEAR - Clear the screen.");
             println!(" + quit, QUIT, q - Quit, pretty self explanatory.");
             println!(" + help, HELP, h - Prints this message.");
@@ -209,7 +795,7 @@ fn help_out(args: Option<&str>) {
 }
 
 // Separated the main loop for clarity
-fn main_loop(core: &mut Core, prefix_tree: &Trie) {
+fn main_loop(core: &mut Core, table: &OpcodeTable, history: &mut History, tracer: &mut Option<Tracer>) {
     let mut step: bool = false;
 
     loop {
@@ -239,36 +825,125 @@ fn main_loop(core: &mut Core, prefix_tree: &Trie) {
     // Starting to step through test binary to implement opcodes.
     // This is getting cumbersome. Need to implement stepping through loop now.
     loop {
-        fetch(core);
+        let pc: u16 = core.pc;
 
-        decode(core, prefix_tree);
-        
-        execute(core);
+        if let Err(error) = history.record(core) {
+            println!("{error}.");
+            break;
+        }
 
-        print!("\x1B[11A");
-        Core::core_dump(&core);
-        println!("Iteration: {}", i);
-        io::stdout().flush().unwrap();
+        let result: Result<Event, EmulatorError> = match tracer {
+            Some(tracer) => tracer.step(core, table),
+            None => run_step(core, table),
+        };
+
+        // A trace on stdout takes the place of the core dump.
+        if !tracer.as_ref().is_some_and(Tracer::is_stdout) { redraw(core, i) }
+
+        if let Err(error) = &result {
+            println!("{error}.");
+            break;
+        }
+
+        if let Some(error) = tracer.as_mut().and_then(Tracer::take_error) {
+            println!("Tracing stopped, {error}.");
+            *tracer = None;
+        }
+
+        if core.halted {
+            println!("Core halted at 0x{:04X}, reset to recover.", core.pc);
+            break;
+        }
+
+        if trapped(core, pc) {
+            println!("Trapped at 0x{:04X}.", pc);
+            break;
+        }
+
+        // B only exists on the stack, so BRK is spotted by its opcode.
+        let brk: bool = result == Ok(Event::Instruction(0x00));
 
         // Skipping over iterations I've looked at closely
-        if core.stat & 0b00010000 != 0b00010000 && step  {
-            print!("Press Enter to step, or type 'q' to quit: ");
-            io::stdout().flush().unwrap();
+        if !brk && step  {
+            // Going back redraws the core and asks again, rather than stepping.
+            let quit: bool = loop {
+                print!("Press Enter to step, 'b [cycles]' to go back, 'i' to toggle IRQ, 'n' for NMI, 'r' for RESET, or 'q' to quit: ");
+                io::stdout().flush().unwrap();
+
+                let mut input = String::new();
+                io::stdin().read_line(&mut input).unwrap();
+                let input_vec = input.split_whitespace().collect::<Vec<_>>();
+
+                match input_vec.as_slice() {
+                    ["q"] => {
+                        println!("Halting...");
+                        break true;
+                    }
+                    ["i"] => { let irq: bool = core.irq; core.set_irq(!irq) }
+                    ["n"] => { core.trigger_nmi() }
+                    ["r"] => { core.trigger_reset() }
+                    ["b"] | ["b", _] => {
+                        let cycles: Option<u64> = input_vec.get(1).and_then(|cycles| cycles.parse().ok());
+
+                        match go_back(core, history, cycles) {
+                            Ok(None) => println!("Nothing to go back to."),
+                            Ok(Some(_)) => {
+                                print!("\x1B[2J\x1B[1;1H");
+                                redraw(core, i);
+                                continue
+                            }
+                            Err(error) => println!("{error}."),
+                        }
+                    }
+                    _ => {}
+                }
 
-            let mut input = String::new();
-            io::stdin().read_line(&mut input).unwrap();
+                break false
+            };
 
-            if input.trim() == "q" {
-                println!("Halting...");
-                break;
-            }
-        } else if core.stat & 0b00010000 == 0b00010000 {
+            if quit { break }
+        } else if brk {
             println!();
             break;
         }
 
         i += 1;
     }
+
+    if let Some(Err(error)) = tracer.as_mut().map(Tracer::flush) {
+        println!("{error}.");
+    }
+}
+
+// Draws the core dump over the last one.
+fn redraw(core: &Core, i: u32) {
+    // The 65816 has an extra line in the core dump.
+    print!("\x1B[{}A", if core.variant == Variant::W65816 { 14 } else { 13 });
+    Core::core_dump(core);
+    println!("Iteration: {}", i);
+    io::stdout().flush().unwrap();
+}
+
+// Steps back a single instruction, or rewinds at least the given number of
+// cycles. Returns the cycles gone back, None when there was nothing to go
+// back to.
+fn go_back(core: &mut Core, history: &mut History, cycles: Option<u64>) -> Result<Option<u64>, EmulatorError> {
+    let before: u64 = core.cycles;
+
+    let went: bool = match cycles {
+        Some(_) if history.is_empty() => false,
+        Some(cycles) => history.rewind_cycles(core, cycles).map(|_| true)?,
+        None => history.step_back(core)?,
+    };
+
+    Ok(went.then(|| before.saturating_sub(core.cycles)))
+}
+
+// Lets the history know the shell changed the core, see history.rs.
+fn checkpoint(core: &mut Core, history: &mut History) {
+    if let Err(error) = history.checkpoint(core) {
+        println!("{error}");
+    }
 }
 
 // Quick memory dump function for checking functionality.
@@ -276,47 +951,53 @@ fn mem_dump(core: &mut Core, targets: &Vec<u16>) {
     println!(" Address │ Contents ");
     println!("─────────┼─────────");
     for target in targets {
-        println!(" 0x{:04X}  │ 0x{:02X} ", target, core.memory[*target as usize])
+        println!(" 0x{:04X}  │ 0x{:02X} ", target, core.peek(*target))
     }
 }
 
-fn load_data(core: &mut Core, path: String, start: u16) -> &mut Core {
+fn load_data(core: &mut Core, path: String, start: u16, rom: bool) -> Result<(), EmulatorError> {
     print!(
-        "Loading {} from 0x{:04X}: ",
+        "Loading {} from 0x{:04X}{}: ",
         path,
         start,
+        if rom { " as ROM" } else { "" },
     );
-    
-    let data: Vec<u8> = match fs::read(path) {
-        Ok(data) => data,
+
+    let result: Result<(), EmulatorError> = read_binary(&path, start).map(|data| {
+        if rom { core.load_rom(start, &data) }
+        else { core.load(start, &data) }
+    });
+
+    match &result {
+        Ok(()) => println!("OK! "),
         Err(error) => {
-            print!("ERROR \n");
-            println!("Problem opening file: {error:?}");
+            println!("ERROR ");
+            println!("{error}");
             println!("No file loaded");
-            return core
         }
-    };
-
-    // Load the data into memory.
-    let start_index: usize = start as usize;
-    let end_index: usize = start_index + data.len();
-
-    if end_index > core.memory.len() {
-        print!("ERROR \n");
-        println!("ROM data exceeds memory bounds!");
-        println!("No file loaded");
-        return core
     }
 
-    core.memory[start_index..end_index].copy_from_slice(&data);
+    result
+}
 
-    print!("OK! \n");
+// Reads a binary, checking it fits in memory from the start address.
+pub(crate) fn read_binary(path: &str, start: u16) -> Result<Vec<u8>, EmulatorError> {
+    let data: Vec<u8> = fs::read(path)
+        .map_err(|error| EmulatorError::Load(format!("Problem opening file: {error}")))?;
 
-    core
+    if start as usize + data.len() > 0x10000 {
+        return Err(EmulatorError::Load("ROM data exceeds memory bounds!".to_string()))
+    }
+
+    Ok(data)
 }
 
-pub fn emulator(prefix_tree: &Trie) {
-    let mut core: Core = init();
+pub fn emulator(table: &OpcodeTable, variant: Variant) {
+    let mut core: Core = init(variant);
+    let mut history: History = History::default();
+    let mut tracer: Option<Tracer> = None;
+
+    let re: Regex = Regex::new(r"^[^\s]*\.bin$").unwrap();
 
     print!("\x1B[2J\x1B[1;1H");
     println!("Run `help` to see the commands!");
@@ -334,16 +1015,16 @@ pub fn emulator(prefix_tree: &Trie) {
         io::stdin().read_line(&mut input).unwrap();
         let input_vec = input.split_whitespace().collect::<Vec<_>>();
 
-        if input_vec.len() == 0 { continue }
+        if input_vec.is_empty() { continue }
 
         match input_vec[0].trim() {
             "load" | "LOAD" => {
-                if input_vec.len() == 3 {
+                let rom: bool = input_vec.len() == 4 && (input_vec[3] == "rom" || input_vec[3] == "ROM");
+
+                if input_vec.len() == 3 || rom {
                     let mut path: String = input_vec[1].to_string();
                     let load: Result<u16, String>  = parse_hex(input_vec[2]);
 
-                    let re: Regex = Regex::new(r"^[^\s]*\.bin$").unwrap();
-
                     // typing the full path got painful
                     if path == "functest" { path = "6502_functional_test.bin".to_string() }
                     else if path == "dectest" { path = "6502_decimal_test.bin".to_string() }
@@ -354,7 +1035,10 @@ pub fn emulator(prefix_tree: &Trie) {
 
                     match load {
                         Ok(val) => {
-                            load_data(&mut core, path, val);
+                            // Problems are reported as it loads.
+                            if load_data(&mut core, path, val, rom).is_ok() {
+                                checkpoint(&mut core, &mut history);
+                            }
                         }
                         Err(error) => {
                             println!("{error}");
@@ -371,6 +1055,8 @@ pub fn emulator(prefix_tree: &Trie) {
                 if input_vec.len() == 1 {
                     help_out(Some("exec"));
                     continue
+                } else if input_vec[1] == "reset" || input_vec[1] == "RESET" {
+                    reset(&mut core);
                 } else {
                     let target: Result<u16, String> = parse_hex(input_vec[1]);
                     match target {
@@ -384,52 +1070,212 @@ pub fn emulator(prefix_tree: &Trie) {
                     }
                 }
 
-                print!("\x1B[2J\x1B[1;1H");
+                checkpoint(&mut core, &mut history);
 
-                main_loop(&mut core, prefix_tree);
-            }
+                print!("\x1B[2J\x1B[1;1H");
 
-            "reset" | "RESET" => {
-                print!("Reinitializing core... ");
-                core = init();
-                print!("Done \n")
+                main_loop(&mut core, table, &mut history, &mut tracer);
             }
 
-            "dump" | "DUMP" => {
-                if input_vec.len() == 1 {
-                    help_out(Some("dump"));
+            "test" | "TEST" => {
+                if input_vec.len() != 3 && input_vec.len() != 4 {
+                    help_out(Some("test"));
                     continue
-                } else {
-                    let mut targets: Vec<u16> = Vec::new();
+                }
 
-                    for i in 1..input_vec.len() {
-                        let target: Result<u16, String> = parse_hex(input_vec[i]);
-                        match target {
-                            Ok(val) => {
-                                targets.push(val);
-                            }
-                            Err(error) => {
-                                println!("{error}");
-                                continue;
-                            }
-                        }
+                let limit: u64 = match input_vec.get(3).map(|cycles| cycles.parse::<u64>()) {
+                    None => CYCLE_LIMIT,
+                    Some(Ok(cycles)) => cycles,
+                    Some(Err(_)) => {
+                        help_out(Some("test"));
+                        continue
                     }
+                };
 
-                    mem_dump(&mut core, &targets);
-                }
-            }
+                let (start, success) = match (parse_hex(input_vec[1]), parse_hex(input_vec[2])) {
+                    (Ok(start), Ok(success)) => (start, success),
+                    (Err(error), _) | (_, Err(error)) => {
+                        println!("{error}");
+                        continue;
+                    }
+                };
 
-            "clear" | "CLEAR" => { print!("\x1B[2J\x1B[1;1H"); }
+                println!("Running from 0x{:04X}...", start);
 
-            "quit" | "QUIT" | "q" => {
-                println!("Exiting...");
-                break;
+                set_pc(&mut core, start);
+                checkpoint(&mut core, &mut history);
+                let before: u64 = core.cycles;
+
+                match run_until_trap(&mut core, table, success, limit) {
+                    Ok(Trap::Passed(pc)) => println!("PASSED, trapped at 0x{:04X}", pc),
+                    Ok(Trap::Failed(pc)) => println!("FAILED, trapped at 0x{:04X}", pc),
+                    Ok(Trap::Timeout(pc)) => println!("TIMED OUT, got to 0x{:04X}", pc),
+                    Err(error) => println!("ERROR, {error}"),
+                }
+
+                println!("{} cycles", core.cycles - before);
+                checkpoint(&mut core, &mut history);
             }
 
-            "help" | "HELP" | "h" => {
-                if input_vec.len() == 1 { help_out(None); }
-                else if input_vec.len() == 2 { help_out(Some(input_vec[1])); }
-                else { println!("Please provide a maximum of one argument.") }
+            "reset" | "RESET" => {
+                if input_vec.len() == 2 && (input_vec[1] == "hard" || input_vec[1] == "HARD") {
+                    print!("Reinitializing core... ");
+                    core = init(variant);
+                    history.clear();
+                    checkpoint(&mut core, &mut history);
+                    println!("Done ")
+                } else if input_vec.len() == 1 {
+                    reset(&mut core);
+                    checkpoint(&mut core, &mut history);
+                    println!("Reset, PC is 0x{:04X}", core.pc)
+                } else {
+                    help_out(Some("reset"));
+                }
+            }
+
+            "rom" | "ROM" => {
+                if input_vec.len() == 2 && (input_vec[1] == "ignore" || input_vec[1] == "IGNORE") {
+                    core.rom_writes = RomWrites::Ignore;
+                    println!("Writes to ROM are ignored")
+                } else if input_vec.len() == 2 && (input_vec[1] == "trap" || input_vec[1] == "TRAP") {
+                    core.rom_writes = RomWrites::Trap;
+                    println!("Writes to ROM stop execution")
+                } else {
+                    help_out(Some("rom"));
+                }
+            }
+
+            "stack" | "STACK" => {
+                if input_vec.len() == 2 && (input_vec[1] == "wrap" || input_vec[1] == "WRAP") {
+                    core.stack_checks = false;
+                    println!("The stack wraps within page one")
+                } else if input_vec.len() == 2 && (input_vec[1] == "check" || input_vec[1] == "CHECK") {
+                    core.stack_checks = true;
+                    println!("Stack overflow and underflow stop execution")
+                } else {
+                    help_out(Some("stack"));
+                }
+            }
+
+            "save" | "SAVE" => {
+                if input_vec.len() != 2 {
+                    help_out(Some("save"));
+                    continue
+                }
+
+                match core.save_file(input_vec[1]) {
+                    Ok(()) => println!("Saved to {}", input_vec[1]),
+                    Err(error) => println!("{error}"),
+                }
+            }
+
+            "restore" | "RESTORE" => {
+                if input_vec.len() != 2 {
+                    help_out(Some("restore"));
+                    continue
+                }
+
+                match core.restore_file(input_vec[1]) {
+                    Ok(()) => {
+                        checkpoint(&mut core, &mut history);
+                        println!("Restored from {}, PC is 0x{:04X}", input_vec[1], core.pc)
+                    }
+                    Err(error) => println!("{error}"),
+                }
+            }
+
+            "trace" | "TRACE" => {
+                let format: TraceFormat = match input_vec.get(1).copied() {
+                    Some("nestest") | Some("NESTEST") => TraceFormat::Nestest,
+                    Some("json") | Some("JSON") => TraceFormat::JsonLines,
+                    Some("off") | Some("OFF") if input_vec.len() == 2 => {
+                        tracer = None;
+                        println!("Tracing off");
+                        continue
+                    }
+                    _ => {
+                        help_out(Some("trace"));
+                        continue
+                    }
+                };
+
+                match input_vec.len() {
+                    2 => {
+                        tracer = Some(Tracer::stdout(format));
+                        println!("Tracing to stdout")
+                    }
+                    3 => match Tracer::file(format, input_vec[2]) {
+                        Ok(file) => {
+                            tracer = Some(file);
+                            println!("Tracing to {}", input_vec[2])
+                        }
+                        Err(error) => println!("{error}"),
+                    },
+                    _ => help_out(Some("trace")),
+                }
+            }
+
+            "rewind" | "REWIND" => {
+                let cycles: Option<u64> = match input_vec.len() {
+                    1 => None,
+                    2 => match input_vec[1].parse() {
+                        Ok(cycles) => Some(cycles),
+                        Err(_) => {
+                            help_out(Some("rewind"));
+                            continue
+                        }
+                    },
+                    _ => {
+                        help_out(Some("rewind"));
+                        continue
+                    }
+                };
+
+                match go_back(&mut core, &mut history, cycles) {
+                    Ok(None) => println!("Nothing to rewind, run something with `exec` first."),
+                    Ok(Some(cycles)) => {
+                        println!("Went back {} cycles, PC is 0x{:04X}", cycles, core.pc);
+                        Core::core_dump(&core);
+                    }
+                    Err(error) => println!("{error}"),
+                }
+            }
+
+            "dump" | "DUMP" => {
+                if input_vec.len() == 1 {
+                    help_out(Some("dump"));
+                    continue
+                } else {
+                    let mut targets: Vec<u16> = Vec::new();
+
+                    for arg in &input_vec[1..] {
+                        let target: Result<u16, String> = parse_hex(arg);
+                        match target {
+                            Ok(val) => {
+                                targets.push(val);
+                            }
+                            Err(error) => {
+                                println!("{error}");
+                                continue;
+                            }
+                        }
+                    }
+
+                    mem_dump(&mut core, &targets);
+                }
+            }
+
+            "clear" | "CLEAR" => { print!("\x1B[2J\x1B[1;1H"); }
+
+            "quit" | "QUIT" | "q" => {
+                println!("Exiting...");
+                break;
+            }
+
+            "help" | "HELP" | "h" => {
+                if input_vec.len() == 1 { help_out(None); }
+                else if input_vec.len() == 2 { help_out(Some(input_vec[1])); }
+                else { println!("Please provide a maximum of one argument.") }
             }
 
             _ => { println!("Unrecognized input: {:?}", input_vec); }
@@ -440,12 +1286,13 @@ pub fn emulator(prefix_tree: &Trie) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::instruction::{Mnemonic, Mode};
 
     #[test]
     fn test_core_initialization() {
         let core = Core::new();
         assert_eq!(core.acc, 0);
-        assert_eq!(core.stat, 0);
+        assert_eq!(core.stat, Status::default());
         assert_eq!(core.pc, 0);
         assert_eq!(core.sp, 0);
         assert_eq!(core.ix, 0);
@@ -453,16 +1300,1667 @@ mod tests {
         assert_eq!(core.ir, 0);
         assert!(core.decoded.is_none());
         assert!(core.info.is_none());
-        assert_eq!(core.memory, [0; 65536]);
+        assert_eq!(core.cycles, 0);
+        assert!((0..=0xFFFF).all(|address| core.peek(address) == 0));
     }
 
     #[test]
     fn test_set_pc() {
         let mut core = Core::new();
+        core.load(0xFFFC, &[0x00, 0xE0]);
         set_pc(&mut core, 0x200);
         assert_eq!(core.pc, 0x200);
-        assert_eq!(core.memory[0xFFFC], 0x00);
-        assert_eq!(core.memory[0xFFFD], 0x02);
+        assert_eq!(core.peek(0xFFFC), 0x00);
+        assert_eq!(core.peek(0xFFFD), 0xE0);
+    }
+
+    #[test]
+    fn test_power_on_and_reset() {
+        let mut core = init(Variant::Nmos6502);
+        assert_eq!(core.sp, 0xFD);
+        assert_eq!(core.stat.bits(), 0b00000100);
+        assert_eq!(core.pc, 0x0000);
+
+        // A loaded ROM's vector is respected by reset.
+        core.load(0xFFFC, &[0x00, 0xE0]);
+        core.acc = 0x42;
+        reset(&mut core);
+        assert_eq!(core.pc, 0xE000);
+        assert_eq!(core.sp, 0xFA);
+        assert_eq!(core.acc, 0x42);
+        assert_eq!([core.peek(0xFFFC), core.peek(0xFFFD)], [0x00, 0xE0]);
+    }
+
+    // Runs a program from 0x0200 for the given number of instructions.
+    fn run_program(program: &[u8], steps: usize) -> Core {
+        run_variant(program, steps, Variant::Nmos6502)
+    }
+
+    fn run_variant(program: &[u8], steps: usize, variant: Variant) -> Core {
+        let table: OpcodeTable = crate::table::gen_table(variant);
+        let mut core = init(variant);
+        core.load(0x0200, program);
+        core.pc = 0x0200;
+        core.cycles = 0; // Only count the program's cycles
+
+        for _ in 0..steps {
+            run_step(&mut core, &table).unwrap();
+        }
+
+        core
+    }
+
+    // Runs more of a program started by `run_variant()`.
+    fn run_variant_steps(core: &mut Core, steps: usize) {
+        let table: OpcodeTable = crate::table::gen_table(core.variant);
+
+        for _ in 0..steps {
+            run_step(core, &table).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_cycle_counting() {
+        // LDA #$01 (2), STA $10 (3), LDX #$FF (2), LDA $1001,X (4 + 1 page cross)
+        let core = run_program(&[0xA9, 0x01, 0x85, 0x10, 0xA2, 0xFF, 0xBD, 0x01, 0x10], 4);
+        assert_eq!(core.cycles, 12);
+
+        // STA $1001,X never takes the page crossing penalty.
+        let core = run_program(&[0xA2, 0xFF, 0x9D, 0x01, 0x10], 2);
+        assert_eq!(core.cycles, 7);
+    }
+
+    #[test]
+    fn test_branch_cycles() {
+        // CLC, BCS not taken (2), BCC taken within the page (3).
+        let core = run_program(&[0x18, 0xB0, 0x02, 0x90, 0x00], 3);
+        assert_eq!(core.cycles, 7);
+        assert_eq!(core.pc, 0x0205);
+
+        // SEC, BCS taken backwards across a page boundary (4).
+        let core = run_program(&[0x38, 0xB0, 0x80], 2);
+        assert_eq!(core.cycles, 6);
+        assert_eq!(core.pc, 0x0183);
+    }
+
+    #[test]
+    fn test_irq() {
+        // SEI, NOP, CLI, NOP with the IRQ line held after SEI.
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let mut core = init(Variant::Nmos6502);
+        core.load(0x0200, &[0x78, 0xEA, 0x58, 0xEA]);
+        core.load(0xFFFE, &[0x00, 0x30]);
+        core.pc = 0x0200;
+
+        // Masked once the interrupt flag is set.
+        run_step(&mut core, &table).unwrap();
+        core.set_irq(true);
+        run_step(&mut core, &table).unwrap();
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.pc, 0x0203);
+
+        // Serviced after CLI, pushing the return address and status with B clear.
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.pc, 0x3000);
+        assert_eq!(core.sp, 0xFA);
+        assert_eq!([core.peek(0x01FB), core.peek(0x01FC), core.peek(0x01FD)], [0b00100000, 0x03, 0x02]);
+        assert_eq!(core.stat.bits() & 0b00000100, 0b00000100);
+    }
+
+    #[test]
+    fn test_nmi_edge() {
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let mut core = init(Variant::Nmos6502);
+        core.write(0x0200, 0xEA);
+        core.write(0x3000, 0xEA);
+        core.load(0xFFFA, &[0x00, 0x30]);
+        core.pc = 0x0200;
+        core.stat.set(Flag::Interrupt, true); // NMI ignores the interrupt flag
+
+        core.set_nmi(true);
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.pc, 0x3000);
+
+        // Holding the line doesn't trigger it again.
+        core.set_nmi(true);
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.pc, 0x3001);
+    }
+
+    #[test]
+    fn test_reset_line() {
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let mut core = init(Variant::Nmos6502);
+        core.load(0xFFFC, &[0x00, 0x04]);
+        core.trigger_reset();
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.pc, 0x0400);
+        assert_eq!(core.sp, 0xFA);
+        assert_eq!(core.cycles, 14);
+    }
+
+    #[test]
+    fn test_undocumented_opcodes() {
+        // LAX $10, LDX #$3C, SAX $11, DCP $12, ISC $13, run from the zero page.
+        let mut program: Vec<u8> = vec![0xA7, 0x10, 0xA2, 0x3C, 0x87, 0x11, 0xC7, 0x12, 0xE7, 0x13];
+        program.resize(0x20, 0xEA);
+        program[0x10..0x14].copy_from_slice(&[0xF0, 0x00, 0xF1, 0x7F]);
+
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let mut core = init(Variant::Nmos6502);
+        core.load(0x0000, &program);
+        core.pc = 0x0000;
+
+        run_step(&mut core, &table).unwrap(); // LAX $10
+        assert_eq!((core.acc, core.ix), (0xF0, 0xF0));
+        run_step(&mut core, &table).unwrap(); // LDX #$3C
+        run_step(&mut core, &table).unwrap(); // SAX $11
+        assert_eq!(core.peek(0x11), 0x30);
+        run_step(&mut core, &table).unwrap(); // DCP $12
+        assert_eq!(core.peek(0x12), 0xF0);
+        assert_eq!(core.stat.bits() & 0b00000011, 0b00000011); // Carry and zero, A == M
+        run_step(&mut core, &table).unwrap(); // ISC $13, A = 0xF0 - 0x80
+        assert_eq!(core.peek(0x13), 0x80);
+        assert_eq!(core.acc, 0x70);
+    }
+
+    #[test]
+    fn test_undocumented_immediates() {
+        // LDA #$FF, ANC #$81, ALR #$03, SEC, ARR #$C0, LDX #$0F, SBX #$02
+        let core = run_program(&[0xA9, 0xFF, 0x0B, 0x81, 0x4B, 0x03, 0x38, 0x6B, 0xC0, 0xA2, 0x0F, 0xCB, 0x02], 7);
+        assert_eq!(core.acc, 0x80);
+        assert_eq!(core.ix, 0xFE);
+        assert_eq!(core.stat.bits() & 0b00000001, 0b00000000); // 0x00 - 0x02 borrows
+    }
+
+    #[test]
+    fn test_jam() {
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let mut core = run_program(&[0xEA, 0x02, 0xEA], 3);
+        assert!(core.halted);
+        assert_eq!(core.pc, 0x0201);
+
+        core.set_irq(true);
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.pc, 0x0201);
+
+        core.trigger_reset();
+        run_step(&mut core, &table).unwrap();
+        assert!(!core.halted);
+    }
+
+    #[test]
+    fn test_variant_tables() {
+        for variant in [Variant::Nmos6502, Variant::Cmos65C02, Variant::Rockwell65C02, Variant::Wdc65C02, Variant::Ricoh2A03, Variant::W65816] {
+            let table: OpcodeTable = crate::table::gen_table(variant);
+            assert!((0..=255).all(|opcode| table.contains(opcode)), "{variant:?}");
+        }
+
+        assert!((0..=255).all(|opcode| OpcodeTable::new().get(opcode).is_none()));
+
+        let info = |variant: Variant, opcode: u8| crate::table::gen_table(variant).get_instruction(opcode).unwrap();
+        assert_eq!(info(Variant::Nmos6502, 0x07).mnemonic, Mnemonic::Slo);
+        assert_eq!(info(Variant::Cmos65C02, 0x07), Instruction::new(Mnemonic::Nop, Mode::Implied, 1, 1, Penalty::Fixed, 0));
+        assert_eq!(info(Variant::Rockwell65C02, 0x07).mnemonic, Mnemonic::Rmb(0));
+        assert_eq!(info(Variant::Rockwell65C02, 0xCB).mnemonic, Mnemonic::Nop);
+        assert_eq!(info(Variant::Wdc65C02, 0xCB).mnemonic, Mnemonic::Wai);
+        assert_eq!(info(Variant::W65816, 0xEB).mnemonic, Mnemonic::Xba);
+
+        // The rest of the description, and how it's written out.
+        let adc: Instruction = info(Variant::Nmos6502, 0x7D);
+        assert_eq!((adc.mode, adc.length, adc.cycles, adc.penalty), (Mode::AbsoluteX, 3, 4, Penalty::PageCross));
+        assert_eq!(adc.flags, 0b11000011);
+        assert_eq!(info(Variant::Nmos6502, 0xD0).penalty, Penalty::Branch);
+        assert_eq!(info(Variant::Wdc65C02, 0x8F).to_string(), "BBS0 ZeroPageRelative");
+        assert_eq!(info(Variant::Nmos6502, 0x28).flags, 0b11011111);
+    }
+
+    #[test]
+    fn test_65c02_instructions() {
+        // LDX #$12, PHX, PLY, STZ $10, LDA #$0F, TSB $10, INC A, BIT #$00,
+        // BRA +1, (skipped NOP), STA ($20)
+        let program: [u8; 20] = [
+            0xA2, 0x12, 0xDA, 0x7A, 0x64, 0x10, 0xA9, 0x0F, 0x04, 0x10,
+            0x1A, 0x89, 0x00, 0x80, 0x01, 0xEA, 0x92, 0x20, 0x00, 0x00,
+        ];
+        let table: OpcodeTable = crate::table::gen_table(Variant::Cmos65C02);
+        let mut core = init(Variant::Cmos65C02);
+        core.load(0x0200, &program);
+        core.write(0x10, 0xF0);
+        core.load(0x20, &[0x00, 0x30]);
+        core.pc = 0x0200;
+        core.stat.set(Flag::Overflow, true);
+
+        for _ in 0..10 {
+            run_step(&mut core, &table).unwrap();
+        }
+
+        assert_eq!(core.iy, 0x12);
+        assert_eq!(core.peek(0x10), 0x0F);
+        assert_eq!(core.acc, 0x10);
+        assert_eq!(core.peek(0x3000), 0x10);
+        assert_eq!(core.stat.bits() & 0b01000010, 0b01000010); // BIT #imm only touches Z
+        assert_eq!(core.pc, 0x0212);
+    }
+
+    #[test]
+    fn test_rockwell_bit_instructions() {
+        // SMB3 $10, BBS3 $10 +2, (skipped), RMB3 $10, BBR3 $10 -2 loops back to RMB3.
+        let core = run_variant(&[0xB7, 0x10, 0xBF, 0x10, 0x02, 0xEA, 0xEA, 0x37, 0x10, 0x3F, 0x10, 0xFB], 4, Variant::Rockwell65C02);
+        assert_eq!(core.peek(0x10), 0x00);
+        assert_eq!(core.pc, 0x0207);
+        assert_eq!(core.cycles, 5 + 6 + 5 + 6);
+    }
+
+    #[test]
+    fn test_65c02_brk_clears_decimal() {
+        let nmos = run_variant(&[0xF8, 0x00], 2, Variant::Nmos6502);
+        assert_eq!(nmos.stat.bits() & 0b00001000, 0b00001000);
+
+        let cmos = run_variant(&[0xF8, 0x00], 2, Variant::Cmos65C02);
+        assert_eq!(cmos.stat.bits() & 0b00001000, 0);
+    }
+
+    #[test]
+    fn test_wai_and_stp() {
+        // CLI, WAI, NOP, STP
+        let table: OpcodeTable = crate::table::gen_table(Variant::Wdc65C02);
+        let mut core = init(Variant::Wdc65C02);
+        core.load(0x0200, &[0x78, 0xCB, 0xEA, 0xDB]);
+        core.pc = 0x0200;
+
+        run_step(&mut core, &table).unwrap();
+        run_step(&mut core, &table).unwrap();
+        assert!(core.waiting);
+
+        // Sleeping until an interrupt line is asserted.
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.pc, 0x0202);
+
+        // IRQ is masked, so the core wakes and carries on.
+        core.set_irq(true);
+        run_step(&mut core, &table).unwrap();
+        assert!(!core.waiting);
+        assert_eq!(core.pc, 0x0203);
+
+        run_step(&mut core, &table).unwrap();
+        run_step(&mut core, &table).unwrap();
+        assert!(core.halted);
+        assert_eq!(core.pc, 0x0204);
+    }
+
+    #[test]
+    fn test_2a03_ignores_decimal() {
+        // SED, LDA #$09, CLC, ADC #$01, PHP, PLA
+        let program: [u8; 8] = [0xF8, 0xA9, 0x09, 0x18, 0x69, 0x01, 0x08, 0x68];
+
+        let nmos = run_variant(&program, 4, Variant::Nmos6502);
+        assert_eq!(nmos.acc, 0x10);
+
+        let ricoh = run_variant(&program, 4, Variant::Ricoh2A03);
+        assert_eq!(ricoh.acc, 0x0A);
+
+        // The flag itself can still be set and pushed.
+        let ricoh = run_variant(&program, 6, Variant::Ricoh2A03);
+        assert_eq!(ricoh.acc & 0b00001000, 0b00001000);
+    }
+
+    // Runs SED, a carry setting instruction, LDA #a and the given ADC/SBC #b.
+    fn decimal_op(variant: Variant, carry: bool, opcode: u8, a: u8, b: u8) -> Core {
+        let set_carry: u8 = if carry { 0x38 } else { 0x18 }; // SEC or CLC
+        run_variant(&[0xF8, set_carry, 0xA9, a, opcode, b], 4, variant)
+    }
+
+    #[test]
+    fn test_decimal_flags() {
+        // 99 + 01 = 00 with carry. The NMOS part takes Z from the binary sum ($9A)
+        // and N from the sum before the high digit is adjusted ($A0).
+        let nmos = decimal_op(Variant::Nmos6502, false, 0x69, 0x99, 0x01);
+        assert_eq!(nmos.acc, 0x00);
+        assert_eq!(nmos.stat.bits() & 0b11000011, 0b10000001);
+
+        let cmos = decimal_op(Variant::Cmos65C02, false, 0x69, 0x99, 0x01);
+        assert_eq!(cmos.acc, 0x00);
+        assert_eq!(cmos.stat.bits() & 0b11000011, 0b00000011);
+
+        // 79 + 00 + 1 = 80, V comes from the signed sum of the digits.
+        let nmos = decimal_op(Variant::Nmos6502, true, 0x69, 0x79, 0x00);
+        assert_eq!(nmos.acc, 0x80);
+        assert_eq!(nmos.stat.bits() & 0b11000011, 0b11000000);
+
+        // 00 - 01 = 99 with a borrow. Both parts agree on N here.
+        let nmos = decimal_op(Variant::Nmos6502, true, 0xE9, 0x00, 0x01);
+        assert_eq!(nmos.acc, 0x99);
+        assert_eq!(nmos.stat.bits() & 0b11000011, 0b10000000);
+
+        // 80 - 01 = 79, V comes from the binary difference in either mode.
+        let nmos = decimal_op(Variant::Nmos6502, true, 0xE9, 0x80, 0x01);
+        assert_eq!(nmos.acc, 0x79);
+        assert_eq!(nmos.stat.bits() & 0b11000011, 0b01000001);
+
+        // 00 - 21 = 79 with a borrow, N follows the binary $DF on NMOS and the decimal result on 65C02.
+        let nmos = decimal_op(Variant::Nmos6502, true, 0xE9, 0x00, 0x21);
+        assert_eq!(nmos.acc, 0x79);
+        assert_eq!(nmos.stat.bits() & 0b11000011, 0b10000000);
+
+        let cmos = decimal_op(Variant::Cmos65C02, true, 0xE9, 0x00, 0x21);
+        assert_eq!(cmos.acc, 0x79);
+        assert_eq!(cmos.stat.bits() & 0b11000011, 0b00000000);
+    }
+
+    #[test]
+    fn test_invalid_bcd() {
+        // Invalid digits go through the same adjustments as on real parts.
+        assert_eq!(decimal_op(Variant::Nmos6502, false, 0x69, 0x0F, 0x0F).acc, 0x14);
+        assert_eq!(decimal_op(Variant::Nmos6502, false, 0x69, 0xFF, 0xFF).acc, 0x54);
+        assert_eq!(decimal_op(Variant::Nmos6502, true, 0xE9, 0x10, 0x0F).acc, 0x0B);
+        assert_eq!(decimal_op(Variant::Cmos65C02, true, 0xE9, 0x10, 0x0F).acc, 0xFB);
+
+        // Every input, valid or not, has to run without overflowing.
+        for variant in [Variant::Nmos6502, Variant::Cmos65C02] {
+            let table: OpcodeTable = crate::table::gen_table(variant);
+            let mut core = init(variant);
+
+            for opcode in [0x69, 0xE9] {
+                for a in 0..=0xFF_u8 {
+                    for b in 0..=0xFF_u8 {
+                        for carry in [0x00, 0x01] {
+                            // LDA #a, ADC/SBC #b with D and the given carry already set.
+                            core.load(0x0200, &[0xA9, a, opcode, b]);
+                            core.pc = 0x0200;
+                            core.stat = Status::from_bits(0b00001000 | carry);
+                            run_step(&mut core, &table).unwrap();
+                            run_step(&mut core, &table).unwrap();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Sets up a core for the bus cycle tests, with a zero page full of pointers.
+    fn cycle_core(variant: Variant, opcode: u8, operand: [u8; 2], index: u8, stat: u8) -> Core {
+        cycle_core_on(Box::new(Ram::new()), variant, opcode, operand, index, stat)
+    }
+
+    fn cycle_core_on(bus: Box<dyn Bus>, variant: Variant, opcode: u8, operand: [u8; 2], index: u8, stat: u8) -> Core {
+        let mut core = power_on(Core::with_bus(bus), variant);
+        for address in 0..0x100 {
+            core.write(address, address as u8);
+        }
+        core.load(0x0200, &[opcode, operand[0], operand[1]]);
+        core.pc = 0x0200;
+        core.sp = 0x80;
+        core.cycles = 0;
+        core.ix = index;
+        core.iy = index;
+        core.stat = Status::from_bits(stat);
+        core
+    }
+
+    #[test]
+    fn test_bus_cycles_match_cycle_counts() {
+        let variants = [Variant::Nmos6502, Variant::Cmos65C02, Variant::Rockwell65C02, Variant::Wdc65C02, Variant::Ricoh2A03];
+        let setups = [([0x10, 0x20], 0x01, 0b00000000), ([0x80, 0x20], 0xFF, 0b11000111)];
+
+        for variant in variants {
+            let table: OpcodeTable = crate::table::gen_table(variant);
+
+            for opcode in 0..=255 {
+                for (operand, index, stat) in setups {
+                    let mut reference = cycle_core(variant, opcode, operand, index, stat);
+                    let before = reference.cycles;
+                    run_step(&mut reference, &table).unwrap();
+
+                    let mut core = cycle_core(variant, opcode, operand, index, stat);
+                    let mut accesses = vec![step_cycle(&mut core, &table).unwrap()];
+                    while !core.bus_queue.is_empty() {
+                        accesses.push(step_cycle(&mut core, &table).unwrap());
+                    }
+
+                    let info = table.get_instruction(opcode);
+                    assert_eq!(accesses.len() as u64, reference.cycles - before, "{variant:?} {info:?} {stat:08b}");
+                    assert!(accesses.iter().all(|access| access.is_some()), "{variant:?} {info:?}");
+                    assert_eq!(core.cycles, reference.cycles);
+                    assert_eq!(core.pc, reference.pc);
+                }
+            }
+        }
+    }
+
+    // Runs one instruction by cycle and collects its bus accesses.
+    fn bus_accesses(core: &mut Core, table: &OpcodeTable) -> Vec<(u16, u8, cycles::Access)> {
+        let mut accesses = vec![step_cycle(core, table).unwrap()];
+        while !core.bus_queue.is_empty() {
+            accesses.push(step_cycle(core, table).unwrap());
+        }
+        accesses.into_iter().flatten().map(|cycle| (cycle.address, cycle.value, cycle.access)).collect()
+    }
+
+    // RAM that logs every access the CPU makes.
+    struct Recorder {
+        ram: Ram,
+        log: std::rc::Rc<std::cell::RefCell<Vec<(u16, u8, cycles::Access)>>>,
+    }
+
+    impl Bus for Recorder {
+        fn read(&mut self, address: u16) -> u8 {
+            let value: u8 = self.ram.read(address);
+            self.log.borrow_mut().push((address, value, cycles::Access::Read));
+            value
+        }
+
+        fn write(&mut self, address: u16, value: u8) {
+            self.log.borrow_mut().push((address, value, cycles::Access::Write));
+            self.ram.write(address, value);
+        }
+
+        fn peek(&self, address: u16) -> u8 {
+            self.ram.peek(address)
+        }
+    }
+
+    // Where the planned accesses go and which way, to line up with a log.
+    fn planned_accesses(core: &Core, table: &OpcodeTable) -> Vec<(u16, cycles::Access)> {
+        use cycles::Planned;
+
+        cycles::plan(core, table).unwrap().into_iter().map(|planned| match planned {
+            Planned::Read(address) | Planned::Dummy(address) => (address, cycles::Access::Read),
+            Planned::Write(address) | Planned::WriteBack(address) => (address, cycles::Access::Write),
+        }).collect()
+    }
+
+    fn logged_accesses(log: &[(u16, u8, cycles::Access)]) -> Vec<(u16, cycles::Access)> {
+        log.iter().map(|&(address, _, access)| (address, access)).collect()
+    }
+
+    // Every planned access is made on the bus in order, and nothing else is.
+    #[test]
+    fn test_bus_cycles_are_made_on_the_bus() {
+        let variants = [Variant::Nmos6502, Variant::Cmos65C02, Variant::Rockwell65C02, Variant::Wdc65C02, Variant::Ricoh2A03];
+        let setups = [([0x10, 0x20], 0x01, 0b00000000), ([0x80, 0x20], 0xFF, 0b11000111)];
+
+        for variant in variants {
+            let table: OpcodeTable = crate::table::gen_table(variant);
+
+            for opcode in 0..=255 {
+                for (operand, index, stat) in setups {
+                    let log = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+                    let bus = Recorder { ram: Ram::new(), log: log.clone() };
+                    let mut core = cycle_core_on(Box::new(bus), variant, opcode, operand, index, stat);
+                    log.borrow_mut().clear();
+
+                    let planned = planned_accesses(&core, &table);
+                    let accesses = bus_accesses(&mut core, &table);
+                    let info = table.get_instruction(opcode);
+                    assert_eq!(accesses, *log.borrow(), "{variant:?} {info:?} {stat:08b}");
+                    assert_eq!(planned, logged_accesses(&log.borrow()), "{variant:?} {info:?} {stat:08b}");
+                }
+            }
+        }
+
+        // The same goes for the interrupt sequences.
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let log = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let bus = Recorder { ram: Ram::new(), log: log.clone() };
+        let mut core = cycle_core_on(Box::new(bus), Variant::Nmos6502, 0xEA, [0, 0], 0, 0);
+        for interrupt in [Core::trigger_nmi, Core::trigger_reset] {
+            interrupt(&mut core);
+            log.borrow_mut().clear();
+
+            let planned = planned_accesses(&core, &table);
+            assert_eq!(bus_accesses(&mut core, &table), *log.borrow());
+            assert_eq!(planned, logged_accesses(&log.borrow()));
+            assert_eq!(planned.len(), 7);
+        }
+    }
+
+    #[test]
+    fn test_65816_cycle_stepping_refused() {
+        let mut core = init(Variant::W65816);
+        let table: OpcodeTable = crate::table::gen_table(Variant::W65816);
+        assert_eq!(step_cycle(&mut core, &table), Err(EmulatorError::NoCycleModel(Variant::W65816)));
+    }
+
+    // Memory that counts the reads and writes made to it.
+    struct Counter {
+        memory: Vec<u8>,
+        counts: std::rc::Rc<std::cell::Cell<(u32, u32)>>,
+    }
+
+    impl crate::devices::Device for Counter {
+        fn read(&mut self, offset: u16) -> u8 {
+            let (reads, writes) = self.counts.get();
+            self.counts.set((reads + 1, writes));
+            self.memory[offset as usize]
+        }
+
+        fn write(&mut self, offset: u16, value: u8) {
+            let (reads, writes) = self.counts.get();
+            self.counts.set((reads, writes + 1));
+            self.memory[offset as usize] = value;
+        }
+
+        fn peek(&self, offset: u16) -> u8 {
+            self.memory[offset as usize]
+        }
+    }
+
+    #[test]
+    fn test_bus_cycles_reach_devices() {
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let counts = std::rc::Rc::new(std::cell::Cell::new((0, 0)));
+        let counter = |counts: &std::rc::Rc<std::cell::Cell<(u32, u32)>>| -> Box<dyn Bus> {
+            let mut bus = crate::devices::DeviceBus::new();
+            let device = Counter { memory: vec![0x41; 0x200], counts: counts.clone() };
+            bus.register(0x3000..=0x31FF, Box::new(device)).unwrap();
+            Box::new(bus)
+        };
+
+        // INC $3010 reads, writes the old value back, then writes the result.
+        let mut core = cycle_core_on(counter(&counts), Variant::Nmos6502, 0xEE, [0x10, 0x30], 0, 0);
+        let accesses = bus_accesses(&mut core, &table);
+        assert_eq!(counts.get(), (1, 2));
+        assert_eq!(accesses[3..], [(0x3010, 0x41, cycles::Access::Read), (0x3010, 0x41, cycles::Access::Write), (0x3010, 0x42, cycles::Access::Write)]);
+        assert_eq!(core.peek(0x3010), 0x42);
+
+        // LDA $30F0,X crossing a page reads the wrong page first.
+        counts.set((0, 0));
+        let mut core = cycle_core_on(counter(&counts), Variant::Nmos6502, 0xBD, [0xF0, 0x30], 0x20, 0);
+        let accesses = bus_accesses(&mut core, &table);
+        assert_eq!(counts.get(), (2, 0));
+        assert_eq!(accesses.len(), 5);
+        assert_eq!(core.acc, 0x41);
+
+        // RTS returning to 0x3100 reads the last byte of the JSR on the way.
+        counts.set((0, 0));
+        let mut core = cycle_core_on(counter(&counts), Variant::Nmos6502, 0x60, [0x00, 0x00], 0, 0);
+        core.sp = 0xFD;
+        core.load(0x01FE, &[0xFF, 0x30]);
+        let accesses = bus_accesses(&mut core, &table);
+        assert_eq!(counts.get(), (1, 0));
+        assert_eq!(accesses.len(), 6);
+        assert_eq!(accesses[5], (0x30FF, 0x41, cycles::Access::Read));
+        assert_eq!(core.pc, 0x3100);
+    }
+
+    #[test]
+    fn test_65c02_decimal_bus_cycle() {
+        // SED then ADC #$01 takes an extra cycle on the 65C02.
+        let mut core = cycle_core(Variant::Cmos65C02, 0x69, [0x01, 0x00], 0, 0b00101000);
+        let table: OpcodeTable = crate::table::gen_table(Variant::Cmos65C02);
+        let addresses: Vec<u16> = bus_accesses(&mut core, &table).iter().map(|access| access.0).collect();
+        assert_eq!(addresses, [0x0200, 0x0201, 0x0201]);
+        assert_eq!(core.cycles, 3);
+    }
+
+    #[test]
+    fn test_read_modify_write_bus_cycles() {
+        use cycles::Access::{Read, Write};
+
+        // INC $10 writes the old value back on NMOS parts...
+        let mut core = cycle_core(Variant::Nmos6502, 0xE6, [0x10, 0x00], 0, 0);
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        assert_eq!(bus_accesses(&mut core, &table), [
+            (0x0200, 0xE6, Read), (0x0201, 0x10, Read), (0x0010, 0x10, Read),
+            (0x0010, 0x10, Write), (0x0010, 0x11, Write),
+        ]);
+
+        // ...and reads it again on the 65C02.
+        let mut core = cycle_core(Variant::Cmos65C02, 0xE6, [0x10, 0x00], 0, 0);
+        let table: OpcodeTable = crate::table::gen_table(Variant::Cmos65C02);
+        assert_eq!(bus_accesses(&mut core, &table), [
+            (0x0200, 0xE6, Read), (0x0201, 0x10, Read), (0x0010, 0x10, Read),
+            (0x0010, 0x10, Read), (0x0010, 0x11, Write),
+        ]);
+    }
+
+    #[test]
+    fn test_dummy_read_bus_cycles() {
+        use cycles::Access::Read;
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+
+        // LDA $20F0,X crossing into the next page reads the wrong page first.
+        let mut core = cycle_core(Variant::Nmos6502, 0xBD, [0xF0, 0x20], 0x20, 0);
+        let accesses = bus_accesses(&mut core, &table);
+        assert_eq!(accesses.len(), 5);
+        assert_eq!((accesses[3].0, accesses[3].2), (0x2010, Read));
+        assert_eq!((accesses[4].0, accesses[4].2), (0x2110, Read));
+
+        // RTS reads the stack before pulling, then the return address.
+        let mut core = cycle_core(Variant::Nmos6502, 0x60, [0x00, 0x00], 0, 0);
+        core.sp = 0xFD;
+        core.load(0x01FE, &[0x02, 0x03]);
+        let addresses: Vec<u16> = bus_accesses(&mut core, &table).iter().map(|access| access.0).collect();
+        assert_eq!(addresses, [0x0200, 0x0201, 0x01FD, 0x01FE, 0x01FF, 0x0302]);
+        assert_eq!(core.pc, 0x0303);
+    }
+
+    #[test]
+    fn test_disassembler_addresses() {
+        // LDA $1234, LDA $2000,X, JMP ($FFFC), JSR $C000, then 65C02 JMP ($1234,X).
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let data = [0xAD, 0x34, 0x12, 0xBD, 0x00, 0x20, 0x6C, 0xFC, 0xFF, 0x20, 0x00, 0xC0];
+        let lines = crate::disassembler::disassemble(&data, &0x0200, &table).unwrap();
+        assert_eq!(lines, ["LDA $1234 \n", "LDA $2000,X \n", "JMP ($FFFC) \n", "JSR $C000 \n"]);
+
+        let table: OpcodeTable = crate::table::gen_table(Variant::Cmos65C02);
+        let lines = crate::disassembler::disassemble(&[0x7C, 0x34, 0x12], &0x0200, &table).unwrap();
+        assert_eq!(lines, ["JMP ($1234,X) \n"]);
+    }
+
+    #[test]
+    fn test_branch_targets() {
+        // BNE to itself, then BEQ forwards.
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let lines = crate::disassembler::disassemble(&[0xD0, 0xFE, 0xF0, 0x10], &0x0200, &table).unwrap();
+        assert_eq!(lines, ["BNE $0200 \n", "BEQ $0214 \n"]);
+
+        // The tracer lists the same targets.
+        let mut core = init(Variant::Nmos6502);
+        core.load(0x0200, &[0xD0, 0xFE, 0xF0, 0x10]);
+        core.pc = 0x0200;
+        let tracer = Tracer::new(TraceFormat::Nestest, Box::new(io::sink()));
+        assert!(tracer.line(&core, &table).contains("BNE $0200 "));
+        core.pc = 0x0202;
+        assert!(tracer.line(&core, &table).contains("BEQ $0214 "));
+
+        // BBR0 $12 back to its own address, and a BRL backwards.
+        let table: OpcodeTable = crate::table::gen_table(Variant::Rockwell65C02);
+        let lines = crate::disassembler::disassemble(&[0x0F, 0x12, 0xFD], &0x0200, &table).unwrap();
+        assert_eq!(lines, ["BBR0 $12,$0200 \n"]);
+
+        let table: OpcodeTable = crate::table::gen_table(Variant::W65816);
+        let lines = crate::disassembler::disassemble(&[0x82, 0xF0, 0xFF], &0x0200, &table).unwrap();
+        assert_eq!(lines, ["BRL $01F3 \n"]);
+    }
+
+    #[test]
+    fn test_disassembler_xce_register_widths() {
+        // REP #$30, LDA #$1234, XCE, then LDA #$12 is back to 8 bits.
+        let table: OpcodeTable = crate::table::gen_table(Variant::W65816);
+        let data = [0xC2, 0x30, 0xA9, 0x34, 0x12, 0xFB, 0xA9, 0x12, 0xA2, 0x01];
+        let lines = crate::disassembler::disassemble(&data, &0x0200, &table).unwrap();
+        assert_eq!(lines, ["REP #30 \n", "LDA #1234 \n", "XCE \n", "LDA #12 \n", "LDX #01 \n"]);
+    }
+
+    #[test]
+    fn test_jmp_indirect_page_wrap() {
+        // JMP ($10FF), with the bytes either side of the page boundary set up
+        // so each behaviour lands somewhere different.
+        let program: [u8; 3] = [0x6C, 0xFF, 0x10];
+        let setup = |core: &mut Core| {
+            core.write(0x10FF, 0x34);
+            core.write(0x1000, 0x12);
+            core.write(0x1100, 0x56);
+        };
+
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let mut core = run_variant(&program, 0, Variant::Nmos6502);
+        setup(&mut core);
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.pc, 0x1234);
+        assert_eq!(core.cycles, 5);
+
+        let table: OpcodeTable = crate::table::gen_table(Variant::Cmos65C02);
+        let mut core = run_variant(&program, 0, Variant::Cmos65C02);
+        setup(&mut core);
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.pc, 0x5634);
+        assert_eq!(core.cycles, 6);
+
+        // A pointer at the very top of memory wraps around instead of panicking.
+        let mut core = run_variant(&[0x6C, 0xFF, 0xFF], 0, Variant::Cmos65C02);
+        core.write(0xFFFF, 0x00);
+        core.write(0x0000, 0x03);
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.pc, 0x0300);
+    }
+
+    #[test]
+    fn test_zero_page_wraparound() {
+        use crate::addressing::{resolve, Resolved, Target};
+
+        // LDA $F0,X with X = 0x20 reads $10, not $0110.
+        let mut core = run_variant(&[0xA2, 0x20, 0xB5, 0xF0], 1, Variant::Nmos6502);
+        core.write(0x0010, 0x42);
+        core.write(0x0110, 0x99);
+        assert_eq!(resolve(&mut core, Mode::ZeroPageX).target, Target::Address(0x0010));
+        run_variant_steps(&mut core, 1);
+        assert_eq!((core.acc, core.pc), (0x42, 0x0204));
+
+        // STX $F0,Y and LDX $F0,Y wrap the same way.
+        let program: [u8; 10] = [0xA0, 0x20, 0xA2, 0x77, 0x96, 0xF0, 0xA2, 0x00, 0xB6, 0xF0];
+        let core = run_variant(&program, 5, Variant::Nmos6502);
+        assert_eq!((core.ix, core.peek(0x0010), core.peek(0x0110)), (0x77, 0x77, 0x00));
+
+        // STA ($FF,X) takes its pointer from $FF and $00.
+        let mut core = run_variant(&[0xA9, 0x5A, 0xA2, 0x00, 0x81, 0xFF], 0, Variant::Nmos6502);
+        core.write(0x00FF, 0x34);
+        core.write(0x0000, 0x12);
+        core.write(0x0100, 0x56);
+        run_variant_steps(&mut core, 3);
+        assert_eq!(core.read(0x1234), 0x5A);
+        assert_eq!(core.read(0x5634), 0x00);
+
+        // STA ($FF),Y wraps the pointer too, and indexing it can cross a page.
+        let mut core = run_variant(&[0xA9, 0xA5, 0xA0, 0x10, 0x91, 0xFF], 2, Variant::Nmos6502);
+        core.write(0x00FF, 0xF8);
+        core.write(0x0000, 0x12);
+        let resolved: Resolved = resolve(&mut core, Mode::IndirectIndexed);
+        assert_eq!(resolved, Resolved { target: Target::Address(0x1308), page_crossed: true });
+        run_variant_steps(&mut core, 1);
+        assert_eq!(core.read(0x1308), 0xA5);
+
+        // A read through (zp),Y that crosses a page costs a cycle, a store doesn't.
+        let mut core = run_variant(&[0xA0, 0x10, 0xB1, 0xFF, 0x91, 0xFF], 1, Variant::Nmos6502);
+        core.write(0x00FF, 0xF8);
+        core.write(0x0000, 0x12);
+        core.cycles = 0;
+        run_variant_steps(&mut core, 1);
+        assert_eq!(core.cycles, 6);
+        run_variant_steps(&mut core, 1);
+        assert_eq!(core.cycles, 12);
+
+        // INC $FF,X is a read-modify-write through the same wraparound.
+        let mut core = run_variant(&[0xA2, 0x02, 0xF6, 0xFF], 2, Variant::Nmos6502);
+        assert_eq!(core.read(0x0001), 0x01);
+        assert_eq!(core.read(0x0101), 0x00);
+
+        // Immediates resolve to the byte after the opcode, and accumulator
+        // and implied modes have no address at all.
+        assert_eq!(resolve(&mut core, Mode::Immediate).target, Target::Address(0x0205));
+        assert_eq!(resolve(&mut core, Mode::Accumulator).target, Target::Accumulator);
+        assert_eq!(resolve(&mut core, Mode::Implied).target, Target::Implied);
+    }
+
+    #[test]
+    fn test_65816_reset() {
+        let core = init(Variant::W65816);
+        assert!(core.emulation);
+        assert_eq!(core.sp_hi, 0x01);
+        assert_eq!(core.stat.bits(), 0b00000100);
+        assert_eq!(core.extended.len(), 0xFF0000);
+    }
+
+    #[test]
+    fn test_65816_native_mode() {
+        // CLC, XCE, REP #$30, LDA #$1234, STA $2000, LDX #$ABCD, TXY, XBA,
+        // SEP #$20, LDA #$FF
+        let program: [u8; 19] = [
+            0x18, 0xFB, 0xC2, 0x30, 0xA9, 0x34, 0x12, 0x8D, 0x00, 0x20,
+            0xA2, 0xCD, 0xAB, 0x9B, 0xEB, 0xE2, 0x20, 0xA9, 0xFF,
+        ];
+        let core = run_variant(&program, 10, Variant::W65816);
+        assert!(!core.emulation);
+        assert_eq!([core.peek(0x2000), core.peek(0x2001)], [0x34, 0x12]);
+        assert_eq!((core.ix_hi, core.ix), (0xAB, 0xCD));
+        assert_eq!((core.iy_hi, core.iy), (0xAB, 0xCD));
+        // XBA swapped the halves, then the 8-bit load left B alone.
+        assert_eq!((core.acc_hi, core.acc), (0x34, 0xFF));
+        assert_eq!(core.pc, 0x0213);
+        assert_eq!(core.cycles, 28);
+
+        // Back to emulation mode drops the index high bytes.
+        let mut core = core;
+        core.stat.set(Flag::Carry, true);
+        core.write(0x0213, 0xFB); // XCE
+        run_step(&mut core, &crate::table::gen_table(Variant::W65816)).unwrap();
+        assert!(core.emulation);
+        assert_eq!((core.ix_hi, core.iy_hi, core.sp_hi), (0, 0, 0x01));
+    }
+
+    #[test]
+    fn test_65816_long_addressing() {
+        // CLC, XCE, REP #$30, LDA #$ABCD, STA $018000, LDA #$0001,
+        // LDX #$8000, LDY #$3000, MVN $01,$00
+        let program: [u8; 25] = [
+            0x18, 0xFB, 0xC2, 0x30, 0xA9, 0xCD, 0xAB, 0x8F, 0x00, 0x80,
+            0x01, 0xA9, 0x01, 0x00, 0xA2, 0x00, 0x80, 0xA0, 0x00, 0x30,
+            0x54, 0x00, 0x01, 0xEA, 0xEA,
+        ];
+        let mut core = run_variant(&program, 9, Variant::W65816);
+        assert_eq!(core.read_long(0x018000), 0xCD);
+        assert_eq!(core.read_long(0x018001), 0xAB);
+        // MVN copies a byte per pass and stays put until the count runs out.
+        assert_eq!(core.pc, 0x0214);
+
+        let core = run_variant(&program, 10, Variant::W65816);
+        assert_eq!([core.peek(0x3000), core.peek(0x3001)], [0xCD, 0xAB]);
+        assert_eq!((core.acc_hi, core.acc), (0xFF, 0xFF));
+        assert_eq!((core.ix_hi, core.ix), (0x80, 0x02));
+        assert_eq!(core.pc, 0x0217);
+    }
+
+    #[test]
+    fn test_65816_jsl_and_stack_relative() {
+        // JSL $018000, NOP. In bank one: PHK, LDA $01,S, PLB, RTL
+        let table: OpcodeTable = crate::table::gen_table(Variant::W65816);
+        let mut core = init(Variant::W65816);
+        core.load(0x0200, &[0x22, 0x00, 0x80, 0x01, 0xEA]);
+        for (offset, value) in [0x4B, 0xA3, 0x01, 0xAB, 0x6B].into_iter().enumerate() {
+            core.write_long(0x018000 + offset as u32, value);
+        }
+        core.pc = 0x0200;
+
+        run_step(&mut core, &table).unwrap();
+        assert_eq!((core.pbr, core.pc), (0x01, 0x8000));
+
+        for _ in 0..4 {
+            run_step(&mut core, &table).unwrap();
+        }
+        assert_eq!(core.acc, 0x01);
+        assert_eq!(core.dbr, 0x01);
+        assert_eq!((core.pbr, core.pc), (0x00, 0x0204));
+    }
+
+    #[test]
+    fn test_65816_native_irq_and_decimal() {
+        // CLC, XCE, REP #$30, SED, CLC, LDA #$0999, ADC #$0001
+        // XCE leaves the old emulation flag in carry, hence the second CLC.
+        let program: [u8; 12] = [0x18, 0xFB, 0xC2, 0x30, 0xF8, 0x18, 0xA9, 0x99, 0x09, 0x69, 0x01, 0x00];
+        let mut core = run_variant(&program, 7, Variant::W65816);
+        assert_eq!((core.acc_hi, core.acc), (0x10, 0x00));
+
+        // Native mode interrupts push the program bank and use their own vectors.
+        core.load(0xFFEE, &[0x00, 0x90]);
+        core.pbr = 0x02;
+        core.stat.set(Flag::Interrupt, false);
+        core.set_irq(true);
+        run_step(&mut core, &crate::table::gen_table(Variant::W65816)).unwrap();
+        assert_eq!((core.pbr, core.pc), (0x00, 0x9000));
+        assert_eq!(core.peek(0x01FD), 0x02);
+        assert_eq!(core.stat.bits() & 0b00001000, 0);
+    }
+
+    // RAM with an output port at 0xD000 that records everything written to it.
+    struct PortBus {
+        ram: Ram,
+        output: std::rc::Rc<std::cell::RefCell<Vec<u8>>>,
+    }
+
+    impl Bus for PortBus {
+        fn read(&mut self, address: u16) -> u8 {
+            self.ram.read(address)
+        }
+
+        fn write(&mut self, address: u16, value: u8) {
+            if address == 0xD000 { self.output.borrow_mut().push(value) }
+            else { self.ram.write(address, value) }
+        }
+
+        fn peek(&self, address: u16) -> u8 {
+            self.ram.peek(address)
+        }
+    }
+
+    #[test]
+    fn test_custom_bus() {
+        let output = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let mut core = Core::with_bus(Box::new(PortBus { ram: Ram::new(), output: output.clone() }));
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+
+        // LDA #$48, STA $D000, LDX #$49, STX $D000, STA $10
+        core.load(0x0200, &[0xA9, 0x48, 0x8D, 0x00, 0xD0, 0xA2, 0x49, 0x8E, 0x00, 0xD0, 0x85, 0x10]);
+        core.pc = 0x0200;
+
+        for _ in 0..5 {
+            run_step(&mut core, &table).unwrap();
+        }
+
+        assert_eq!(*output.borrow(), [0x48, 0x49]);
+        assert_eq!(core.peek(0xD000), 0x00);
+        assert_eq!(core.peek(0x10), 0x48);
+    }
+
+    // Counts cycles and holds IRQ once the period is up, until the count is read.
+    struct Timer {
+        count: u8,
+        period: u8,
+    }
+
+    impl crate::devices::Device for Timer {
+        fn read(&mut self, _offset: u16) -> u8 {
+            let count: u8 = self.count;
+            self.count = 0;
+            self.period = 0;
+            count
+        }
+
+        fn write(&mut self, _offset: u16, value: u8) {
+            self.period = value;
+        }
+
+        fn tick(&mut self) {
+            self.count = self.count.wrapping_add(1);
+        }
+
+        fn irq(&self) -> bool {
+            self.period != 0 && self.count >= self.period
+        }
+
+        fn save(&self, state: &mut crate::savestate::StateWriter) {
+            state.u8(self.count);
+            state.u8(self.period);
+        }
+
+        fn restore(&mut self, state: &mut crate::savestate::StateReader) -> Result<(), EmulatorError> {
+            self.count = state.u8()?;
+            self.period = state.u8()?;
+            Ok(())
+        }
+    }
+
+    // Write only output port.
+    struct Output(std::rc::Rc<std::cell::RefCell<Vec<u8>>>);
+
+    impl crate::devices::Device for Output {
+        fn read(&mut self, _offset: u16) -> u8 {
+            0xFF
+        }
+
+        fn write(&mut self, _offset: u16, value: u8) {
+            self.0.borrow_mut().push(value);
+        }
+    }
+
+    #[test]
+    fn test_devices() {
+        let output = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let mut bus = crate::devices::DeviceBus::new();
+        bus.register(0xD000..=0xD00F, Box::new(Timer { count: 0, period: 10 })).unwrap();
+        bus.register(0xD010..=0xD010, Box::new(Output(output.clone()))).unwrap();
+        assert_eq!(
+            bus.register(0xD00F..=0xD011, Box::new(Output(output.clone()))),
+            Err(EmulatorError::DeviceOverlap { range: 0xD00F..=0xD011, mapped: 0xD000..=0xD00F }),
+        );
+        let empty = std::ops::RangeInclusive::new(0xD020, 0xD01F);
+        assert_eq!(bus.register(empty.clone(), Box::new(Output(output.clone()))), Err(EmulatorError::EmptyRange(empty)));
+
+        let mut core = Core::with_bus(Box::new(bus));
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+
+        // LDA #$41, STA $D010, NOP, NOP, with an IRQ handler at 0x0300 doing LDA $D000.
+        core.load(0x0200, &[0xA9, 0x41, 0x8D, 0x10, 0xD0, 0xEA, 0xEA]);
+        core.load(0x0300, &[0xAD, 0x00, 0xD0]);
+        core.load(0xFFFE, &[0x00, 0x03]);
+        core.pc = 0x0200;
+        core.sp = 0xFF;
+        core.stat = Status::default();
+
+        for _ in 0..4 {
+            run_step(&mut core, &table).unwrap();
+        }
+
+        // The timer's period was up after the second NOP.
+        assert_eq!(*output.borrow(), [0x41]);
+        assert!(core.irq);
+
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.pc, 0x0300);
+
+        // Reading the timer releases the line. It ticked through the interrupt sequence too.
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.acc, 17);
+        assert!(!core.irq);
+
+        // Ranges without a device are RAM, and devices answer dumps with their own peek.
+        assert_eq!(core.peek(0xD010), 0x00);
+        core.write(0xD011, 0x12);
+        assert_eq!(core.peek(0xD011), 0x12);
+    }
+
+    // A ROM image where every byte is its bank number.
+    fn banked_rom(banks: usize, size: usize) -> Vec<u8> {
+        (0..banks).flat_map(|bank| vec![bank as u8; size]).collect()
+    }
+
+    #[test]
+    fn test_bank_switching() {
+        use crate::mappers::{SwitchedWindow, UxRom};
+
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+
+        // An 8kb window at $A000 switched through $9000. 5 banks wraps to bank 1.
+        // LDA #$03, STA $9000, LDX $A123, LDA #$05, STA $9000, LDY $BFFF
+        let mut core = Core::with_bus(Box::new(SwitchedWindow::new(banked_rom(4, 0x2000), 0x2000, 0xA000, 0x9000).unwrap()));
+        core.load(0x0200, &[0xA9, 0x03, 0x8D, 0x00, 0x90, 0xAE, 0x23, 0xA1, 0xA9, 0x05, 0x8D, 0x00, 0x90, 0xAC, 0xFF, 0xBF]);
+        core.pc = 0x0200;
+
+        for _ in 0..6 {
+            run_step(&mut core, &table).unwrap();
+        }
+
+        assert_eq!(core.ix, 0x03);
+        assert_eq!(core.iy, 0x01);
+        assert_eq!(core.peek(0x9000), 0x05); // The register is RAM as well
+        assert_eq!(core.peek(0xC000), 0x00); // Outside the window
+
+        // UxROM with 8 banks, 128kb. The reset vector comes from the fixed last bank.
+        let mut rom: Vec<u8> = banked_rom(8, 0x4000);
+        rom[0x1FFFC..=0x1FFFD].copy_from_slice(&[0x00, 0xC0]);
+        // LDA #$06, STA $8000, LDA $8000
+        rom[0x1C000..0x1C008].copy_from_slice(&[0xA9, 0x06, 0x8D, 0x00, 0x80, 0xAD, 0x00, 0x80]);
+
+        let mut core = Core::with_bus(Box::new(UxRom::new(rom).unwrap()));
+        reset(&mut core);
+        assert_eq!(core.pc, 0xC000);
+        assert_eq!(core.peek(0x8000), 0x00);
+
+        for _ in 0..3 {
+            run_step(&mut core, &table).unwrap();
+        }
+
+        assert_eq!(core.acc, 0x06);
+        assert_eq!(core.peek(0xFFFD), 0xC0);
+
+        assert_eq!(UxRom::new(Vec::new()).err(), Some(EmulatorError::EmptyRom));
+        assert_eq!(
+            SwitchedWindow::new(banked_rom(4, 0x2000), 0x2000, 0xF000, 0x9000).err(),
+            Some(EmulatorError::WindowOutOfRange(0xF000)),
+        );
+        assert_eq!(
+            SwitchedWindow::new(vec![1, 2, 3], 0, 0x8000, 0x7000).err(),
+            Some(EmulatorError::ZeroBankSize),
+        );
+    }
+
+    #[test]
+    fn test_mmc1_and_6510_port() {
+        use crate::mappers::{Mmc1, Port6510};
+
+        let mut mmc1 = Mmc1::new(banked_rom(16, 0x4000)).unwrap();
+        assert_eq!((mmc1.peek(0x8000), mmc1.peek(0xC000)), (0x00, 0x0F));
+
+        // Five writes of bank 5 to the PRG register, low bit first.
+        for bit in 0..5 {
+            mmc1.write(0xE000, 0b101 >> bit);
+        }
+        assert_eq!((mmc1.peek(0x8000), mmc1.peek(0xC000)), (0x05, 0x0F));
+
+        // Control 0b01000, fixing the first bank at $8000. A reset partway through is thrown away.
+        mmc1.write(0x8000, 1);
+        mmc1.write(0x8000, 0x80);
+        for bit in 0..5 {
+            mmc1.write(0x8000, 0b01000 >> bit);
+        }
+        assert_eq!((mmc1.peek(0x8000), mmc1.peek(0xC000)), (0x00, 0x05));
+
+        // PRG RAM is still RAM.
+        mmc1.write(0x6000, 0x42);
+        assert_eq!(mmc1.peek(0x6000), 0x42);
+
+        assert_eq!(
+            Port6510::new(vec![0xBA; 0x2000], vec![0x4E; 0x4000], vec![0xC4; 0x1000]).err(),
+            Some(EmulatorError::RomSize { rom: "KERNAL", expected: 0x2000, found: 0x4000 }),
+        );
+
+        let mut c64 = Port6510::new(vec![0xBA; 0x2000], vec![0x4E; 0x2000], vec![0xC4; 0x1000]).unwrap();
+        // The port starts as inputs, which are pulled up to BASIC, KERNAL and I/O.
+        // Writes land in the RAM underneath.
+        c64.write(0xA000, 0x11);
+        c64.write(0xD000, 0x22);
+        assert_eq!((c64.read(0xA000), c64.read(0xD000), c64.read(0xE000)), (0xBA, 0x22, 0x4E));
+
+        // The usual C64 direction register, with CHAREN cleared for the character ROM.
+        c64.write(0x0000, 0x2F);
+        c64.write(0x0001, 0x33);
+        assert_eq!((c64.read(0xA000), c64.read(0xD000), c64.read(0xE000)), (0xBA, 0xC4, 0x4E));
+
+        // All RAM.
+        c64.write(0x0001, 0x30);
+        assert_eq!((c64.read(0xA000), c64.read(0xD000), c64.read(0xE000)), (0x11, 0x22, 0x00));
+        assert_eq!(c64.read(0x0001), 0xF0);
+    }
+
+    #[test]
+    fn test_rom_writes() {
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let mut core = init(Variant::Nmos6502);
+        core.load_rom(0xFFF0, &[0xAA; 0x20]);
+
+        // LDA #$55, STA $FFF8, INC $0005, STA $0200
+        core.load(0x0200, &[0xA9, 0x55, 0x8D, 0xF8, 0xFF, 0xEE, 0x05, 0x00, 0x8D, 0x00, 0x02]);
+        core.pc = 0x0200;
+
+        for _ in 0..3 {
+            run_step(&mut core, &table).unwrap();
+        }
+
+        // The ROM wrapped around to the bottom of memory, and ignores writes by default.
+        assert_eq!((core.peek(0xFFF8), core.peek(0x0005)), (0xAA, 0xAA));
+        assert_eq!(core.write_fault, None);
+
+        core.rom_writes = RomWrites::Trap;
+        core.pc = 0x0202;
+
+        // Each fault comes back from the instruction that made it, and RAM is still writable.
+        let fault = WriteFault { address: 0xFFF8, value: 0x55, pc: 0x0202 };
+        assert_eq!(run_step(&mut core, &table), Err(EmulatorError::BusFault(fault)));
+        assert_eq!(core.pc, 0x0205);
+
+        let fault = WriteFault { address: 0x0005, value: 0xAB, pc: 0x0205 };
+        assert_eq!(run_step(&mut core, &table), Err(EmulatorError::BusFault(fault)));
+        assert_eq!(core.peek(0xFFF8), 0xAA);
+
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.write_fault, None);
+        assert_eq!(core.peek(0x0200), 0x55);
+
+        // Loading isn't a CPU write, so ROM can be replaced.
+        core.load(0xFFF8, &[0x12]);
+        assert_eq!(core.peek(0xFFF8), 0x12);
+    }
+
+    #[test]
+    fn test_traps() {
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+
+        // LDA #$01, CMP #$01, BEQ ok, fail: JMP fail, ok: CMP #$02, BNE *
+        let program: [u8; 13] = [0xA9, 0x01, 0xC9, 0x01, 0xF0, 0x03, 0x4C, 0x06, 0x02, 0xC9, 0x02, 0xD0, 0xFE];
+
+        let result = run_test(&table, Variant::Nmos6502, &program, 0x0200, 0x0200, 0x020B, CYCLE_LIMIT);
+        assert_eq!(result, Ok((Trap::Passed(0x020B), 12)));
+
+        // Comparing against 2 first takes the JMP * instead.
+        let mut failing: [u8; 13] = program;
+        failing[3] = 0x02;
+        let result = run_test(&table, Variant::Nmos6502, &failing, 0x0200, 0x0200, 0x020B, CYCLE_LIMIT);
+        assert_eq!(result, Ok((Trap::Failed(0x0206), 9)));
+
+        // A JAM halts the core, which is a failure where it stopped.
+        let result = run_test(&table, Variant::Nmos6502, &[0xEA, 0x02], 0x0200, 0x0200, 0x0200, CYCLE_LIMIT);
+        assert_eq!(result, Ok((Trap::Failed(0x0201), 4)));
+
+        assert!(run_test(&table, Variant::Nmos6502, &program, 0xFFF8, 0xFFF8, 0x0000, CYCLE_LIMIT).is_err());
+
+        // INX, JMP back to it, never traps so it runs out of cycles.
+        let result = run_test(&table, Variant::Nmos6502, &[0xE8, 0x4C, 0x00, 0x02], 0x0200, 0x0200, 0x0200, 1000);
+        assert_eq!(result, Ok((Trap::Timeout(0x0200), 1000)));
+
+        // The limit counts from where the run started.
+        let mut core = init(Variant::Nmos6502);
+        core.load(0x0200, &[0xE8, 0x4C, 0x00, 0x02]);
+        core.pc = 0x0200;
+        core.cycles = 5000;
+        assert_eq!(run_until_trap(&mut core, &table, 0x0200, 12), Ok(Trap::Timeout(0x0201)));
+        assert_eq!(core.cycles, 5012);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut core = init(Variant::Nmos6502);
+        core.load(0x0200, &[0xEA]);
+        core.pc = 0x0200;
+
+        // Nothing in the tree for the opcode, reported where it was fetched.
+        let result = run_step(&mut core, &OpcodeTable::new());
+        assert_eq!(result, Err(EmulatorError::InvalidOpcode { opcode: 0xEA, address: 0x0200 }));
+
+        // NOP at the top of memory and PLA with an empty stack wrap around.
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        core.load(0xFFFF, &[0xEA]);
+        core.load(0x0000, &[0x68]);
+        core.pc = 0xFFFF;
+        core.sp = 0xFF;
+
+        run_step(&mut core, &table).unwrap();
+        assert_eq!(core.pc, 0x0000);
+
+        run_step(&mut core, &table).unwrap();
+        assert_eq!((core.pc, core.sp), (0x0001, 0x00));
+
+        assert_eq!(
+            EmulatorError::InvalidOpcode { opcode: 0x02, address: 0x1234 }.to_string(),
+            "Invalid opcode 0x02 at 0x1234",
+        );
+    }
+
+    #[test]
+    fn test_status() {
+        // PHP pushes B and bit 5 set, PLP ignores both.
+        let mut core = run_program(&[0x08, 0xA9, 0xFF, 0x48, 0x28], 1);
+        assert_eq!(core.peek(0x01FD), 0b00110100);
+        run_variant_steps(&mut core, 3);
+        assert_eq!(core.stat.bits(), 0b11001111);
+        assert_eq!(core.stat.to_string(), "NV--DIZC");
+
+        // BRK pushes B set, IRQ and NMI push it clear, and RTI drops it again.
+        let mut core = run_program(&[0x00], 0);
+        core.load(0xFFFE, &[0x00, 0x03]);
+        core.load(0x0300, &[0x40]);
+        core.stat = Status::from_bits(0b10000001);
+        run_variant_steps(&mut core, 1);
+        assert_eq!(core.peek(0x01FB), 0b10110001);
+        assert_eq!(core.stat.bits(), 0b10000101);
+        run_variant_steps(&mut core, 1);
+        assert_eq!(core.stat.bits(), 0b10000001);
+
+        core.set_irq(true);
+        run_variant_steps(&mut core, 1);
+        assert_eq!(core.peek(0x01FB), 0b10100001);
+
+        let mut status = Status::default();
+        status.set_zero_negative(0x80);
+        status.set(Flag::Carry, true);
+        assert_eq!((status.get(Flag::Negative), status.get(Flag::Zero), status.carry()), (true, false, 1));
+        assert_eq!(status.pushed(false), 0b10100001);
+        assert_eq!(Status::pulled(0xFF), Status::from_bits(0b11001111));
+    }
+
+    #[test]
+    fn test_stack() {
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+
+        // JSR with the stack pointer at 0x00 splits the return address
+        // across the top and bottom of page one, and RTS finds it again.
+        let mut core = run_program(&[0x20, 0x00, 0x03], 0);
+        core.load(0x0300, &[0x60]);
+        core.sp = 0x00;
+        run_step(&mut core, &table).unwrap();
+        assert_eq!((core.peek(0x0100), core.peek(0x01FF), core.sp), (0x02, 0x02, 0xFE));
+        assert_eq!(core.peek(0x0000), 0x00);
+        run_step(&mut core, &table).unwrap();
+        assert_eq!((core.pc, core.sp), (0x0203, 0x00));
+
+        // BRK and RTI come back to the byte after BRK's padding byte, with
+        // the status pulled from the right slot.
+        let mut core = run_program(&[0x00, 0xEA, 0xEA], 0);
+        core.load(0xFFFE, &[0x00, 0x03]);
+        core.load(0x0300, &[0x40]);
+        core.stat = Status::from_bits(0b11000011);
+        run_variant_steps(&mut core, 2);
+        assert_eq!((core.pc, core.sp, core.stat), (0x0202, 0xFD, Status::from_bits(0b11000011)));
+
+        // PHA from 0x00 wraps to 0xFF, and PLA wraps back. Nothing is
+        // reported while the checks are off.
+        let mut core = run_program(&[0x48, 0x68], 0);
+        core.sp = 0x00;
+        run_variant_steps(&mut core, 2);
+        assert_eq!((core.pc, core.sp), (0x0202, 0x00));
+
+        // With them on, the instruction still runs and the fault comes back
+        // with its PC.
+        core.stack_checks = true;
+        core.pc = 0x0200;
+        assert_eq!(run_step(&mut core, &table), Err(EmulatorError::StackOverflow { pc: 0x0200 }));
+        assert_eq!((core.sp, core.peek(0x0100)), (0xFF, core.acc));
+        assert_eq!(run_step(&mut core, &table), Err(EmulatorError::StackUnderflow { pc: 0x0201 }));
+        assert_eq!((core.pc, core.sp, core.stack_fault.clone()), (0x0202, 0x00, None));
+    }
+
+    #[test]
+    fn test_cpu_api() {
+        use crate::cpu::{Cpu, Step};
+
+        let mut cpu = Cpu::new(Variant::Nmos6502);
+
+        // LDX #$00, loop: INX, CPX #$05, BNE loop, JAM
+        cpu.load(0x0200, &[0xA2, 0x00, 0xE8, 0xE0, 0x05, 0xD0, 0xFB, 0x02]);
+        cpu.core.pc = 0x0200;
+
+        let step: Step = cpu.step().unwrap();
+        assert_eq!(step, Step { pc: 0x0200, event: Event::Instruction(0xA2), cycles: 2 });
+
+        // Stops at the end of the instruction that used up the cycles.
+        assert_eq!(cpu.run_for_cycles(5).unwrap(), 7);
+        assert_eq!((cpu.core.pc, cpu.core.ix), (0x0202, 0x01));
+
+        let step: Step = cpu.run_until(|core| core.ix == 0x03).unwrap();
+        assert_eq!(step.event, Event::Instruction(0xE8));
+        assert_eq!(cpu.core.pc, 0x0203);
+
+        // The JAM halts the core, which ends both kinds of run.
+        let step: Step = cpu.run_until(|_| false).unwrap();
+        assert_eq!((step.pc, step.event), (0x0207, Event::Halted));
+        assert_eq!(cpu.run_for_cycles(100).unwrap(), 0);
+
+        // RESET runs in place of an instruction, and brings the core back.
+        cpu.core.trigger_reset();
+        assert_eq!(cpu.step().unwrap().event, Event::Interrupt);
+        assert!(!cpu.core.halted);
+
+        assert!(cpu.load_file("missing.bin", 0x0200, false).is_err());
+    }
+
+    #[test]
+    fn test_save_state() {
+        use crate::cpu::Cpu;
+
+        let mut cpu = Cpu::new(Variant::Nmos6502);
+
+        // loop: INX, STX $10, JMP loop
+        cpu.load(0x0200, &[0xE8, 0x86, 0x10, 0x4C, 0x00, 0x02]);
+        cpu.load_rom(0xF000, &[0xEA]);
+        cpu.core.pc = 0x0200;
+        cpu.core.stack_checks = true;
+        cpu.run_for_cycles(100).unwrap();
+        cpu.core.set_nmi(true);
+
+        let state: Vec<u8> = cpu.core.save_state().unwrap();
+        let saved = (cpu.core.pc, cpu.core.ix, cpu.core.cycles, cpu.core.peek(0x10));
+
+        // Carry on, then go back. Everything lines up again, including the latched NMI.
+        cpu.run_for_cycles(100).unwrap();
+        cpu.core.rom.clear();
+        assert_ne!((cpu.core.pc, cpu.core.ix, cpu.core.cycles, cpu.core.peek(0x10)), saved);
+
+        cpu.core.restore_state(&state).unwrap();
+        assert_eq!((cpu.core.pc, cpu.core.ix, cpu.core.cycles, cpu.core.peek(0x10)), saved);
+        assert!(cpu.core.nmi && cpu.core.nmi_pending && cpu.core.stack_checks);
+        assert_eq!(cpu.core.rom, vec![0xF000..=0xF000]);
+        assert_eq!(cpu.step().unwrap().event, Event::Interrupt);
+
+        // Files go through the same state.
+        cpu.save("test.state").unwrap();
+        let mut copy = Cpu::new(Variant::Nmos6502);
+        copy.restore("test.state").unwrap();
+        assert_eq!((copy.core.pc, copy.core.sp, copy.core.stat), (cpu.core.pc, cpu.core.sp, cpu.core.stat));
+        fs::remove_file("test.state").unwrap();
+
+        // Bad states are refused without touching the core.
+        let error = |message: &str| Err(EmulatorError::SaveState(message.to_string()));
+        let mut other: Vec<u8> = state.clone();
+        other[8] = 2;
+        assert_eq!(cpu.core.restore_state(&other), error("Unsupported version 2, expected 1"));
+        assert_eq!(cpu.core.restore_state(&state[..100]), error("Save state is cut short"));
+        assert_eq!(cpu.core.restore_state(b"not a state"), error("Not a save state"));
+        assert_eq!(Cpu::new(Variant::Cmos65C02).core.restore_state(&state), error("Saved from a different CPU than the Cmos65C02"));
+        assert_eq!(copy.core.pc, cpu.core.pc);
+
+        // Mappers keep their registers and devices their own state.
+        let mut bus = crate::devices::DeviceBus::new();
+        bus.register(0xD000..=0xD00F, Box::new(Timer { count: 0, period: 0 })).unwrap();
+        let mut core = Core::with_bus(Box::new(bus));
+        core.write(0xD000, 0x20);
+        for _ in 0..5 { core.bus.tick() }
+        core.write(0x0300, 0x42);
+        let state: Vec<u8> = core.save_state().unwrap();
+
+        assert_eq!(core.read(0xD000), 5);
+        core.write(0x0300, 0x00);
+        core.restore_state(&state).unwrap();
+        assert_eq!((core.read(0xD000), core.peek(0x0300)), (5, 0x42));
+
+        let mut core = Core::with_bus(Box::new(crate::mappers::Mmc1::new(banked_rom(16, 0x4000)).unwrap()));
+        for bit in 0..5 {
+            core.write(0xE000, 0b101 >> bit);
+        }
+        let state: Vec<u8> = core.save_state().unwrap();
+        core.write(0xE000, 0x80);
+        core.restore_state(&state).unwrap();
+        assert_eq!(core.peek(0x8000), 0x05);
+
+        // A state for a different bus doesn't fit.
+        assert!(Core::new().restore_state(&state).is_err());
+    }
+
+    #[test]
+    fn test_history() {
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let mut core = init(Variant::Nmos6502);
+        let mut history = History::new(usize::MAX);
+
+        // loop: INX, STX $10, PHA, JMP loop. IRQ handler at 0x0300: INY, RTI
+        core.load(0x0200, &[0xE8, 0x86, 0x10, 0x48, 0x4C, 0x00, 0x02]);
+        core.load(0x0300, &[0xC8, 0x40]);
+        core.load(0xFFFE, &[0x00, 0x03]);
+        core.pc = 0x0200;
+        core.stat.set(Flag::Interrupt, false);
+
+        assert!(!history.step_back(&mut core).unwrap());
+
+        // Every state along the way, with the IRQ held for a while partway through.
+        let mut states: Vec<Vec<u8>> = Vec::new();
+        for step in 0..30 {
+            core.set_irq((10..12).contains(&step));
+            states.push(core.save_state().unwrap());
+            history.record(&mut core).unwrap();
+            run_step(&mut core, &table).unwrap();
+        }
+        assert_eq!(core.iy, 1);
+        assert_eq!(history.len(), 30);
+
+        // Each step back lands on exactly the state before it, memory and stack included.
+        for step in (25..30).rev() {
+            assert!(history.step_back(&mut core).unwrap());
+            assert_eq!(core.save_state().unwrap(), states[step]);
+        }
+
+        // Rewinding by cycles goes back at least that far, through the interrupt.
+        let before: u64 = core.cycles;
+        let cycles: u64 = history.rewind_cycles(&mut core, 40).unwrap();
+        assert!(cycles >= 40);
+        assert_eq!(core.cycles, before - cycles);
+        assert_eq!(core.save_state().unwrap(), states[history.len()]);
+        assert_eq!(core.iy, 0);
+
+        // Carrying on from there records a new future.
+        history.record(&mut core).unwrap();
+        run_step(&mut core, &table).unwrap();
+        let len: usize = history.len();
+        assert!(history.step_back(&mut core).unwrap());
+        assert_eq!(history.len(), len - 1);
+
+        // Going back further than the history just goes to the start.
+        history.rewind_cycles(&mut core, u64::MAX).unwrap();
+        assert_eq!(core.save_state().unwrap(), states[0]);
+        assert!(history.is_empty());
+
+        // Over budget, the oldest stretches are dropped and the newest is always
+        // kept. Every state left can still be gone back to exactly.
+        let mut history = History::new(0);
+        let mut states: Vec<u64> = Vec::new();
+        for _ in 0..1500 {
+            states.push(fingerprint(&core));
+            history.record(&mut core).unwrap();
+            run_step(&mut core, &table).unwrap();
+        }
+        assert!((1..1500).contains(&history.len()));
+
+        let dropped: usize = states.len() - history.len();
+        while history.step_back(&mut core).unwrap() {
+            assert_eq!(fingerprint(&core), states[dropped + history.len()]);
+        }
+        assert!(history.is_empty());
+    }
+
+    // The whole machine, to tell states apart without keeping them all.
+    fn fingerprint(core: &Core) -> u64 {
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        core.save_state().unwrap().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_history_checkpoints() {
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let mut core = init(Variant::Nmos6502);
+        let mut history = History::default();
+
+        // Load a program and set PC the way the shell does, then step it.
+        let data: [u8; 4] = [0xA9, 0x42, 0x85, 0x10]; // LDA #$42, STA $10
+        fs::write("history.bin", data).unwrap();
+        load_data(&mut core, "history.bin".to_string(), 0x0200, false).unwrap();
+        fs::remove_file("history.bin").unwrap();
+        history.checkpoint(&mut core).unwrap();
+        set_pc(&mut core, 0x0200);
+        history.checkpoint(&mut core).unwrap();
+
+        // The checkpoint and the record before the first step are the same state.
+        for _ in 0..2 {
+            history.record(&mut core).unwrap();
+            run_step(&mut core, &table).unwrap();
+        }
+        assert_eq!(history.len(), 3);
+        assert_eq!(core.peek(0x10), 0x42);
+
+        // Back over the STA, then the LDA, to just after PC was set.
+        assert_eq!(go_back(&mut core, &mut history, None).unwrap(), Some(3));
+        assert_eq!(core.peek(0x10), 0x00);
+        assert_eq!(go_back(&mut core, &mut history, None).unwrap(), Some(2));
+        assert_eq!((core.pc, core.acc), (0x0200, 0x00));
+
+        // Then to before PC was set, with the program still loaded, and no further.
+        assert_eq!(go_back(&mut core, &mut history, None).unwrap(), Some(0));
+        assert_ne!(core.pc, 0x0200);
+        assert_eq!(core.peek(0x0200), 0xA9);
+        assert_eq!(go_back(&mut core, &mut history, None).unwrap(), None);
+    }
+
+    #[test]
+    fn test_history_leaves_devices_alone() {
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let output = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let mut bus = crate::devices::DeviceBus::new();
+        bus.register(0xD000..=0xD00F, Box::new(Timer { count: 0, period: 0 })).unwrap();
+        bus.register(0xD010..=0xD010, Box::new(Output(output.clone()))).unwrap();
+        let mut core = power_on(Core::with_bus(Box::new(bus)), Variant::Nmos6502);
+        let mut history = History::default();
+
+        // LDX #$00, loop: INX, STX $D010, JMP loop
+        core.load(0x0200, &[0xA2, 0x00, 0xE8, 0x8E, 0x10, 0xD0, 0x4C, 0x02, 0x02]);
+        core.pc = 0x0200;
+        let mut states: Vec<Vec<u8>> = Vec::new();
+        for _ in 0..10 {
+            states.push(core.save_state().unwrap());
+            history.record(&mut core).unwrap();
+            run_step(&mut core, &table).unwrap();
+        }
+        assert_eq!(*output.borrow(), [1, 2, 3]);
+
+        // Going back doesn't write to the device again, and the timer's count comes back.
+        history.rewind_cycles(&mut core, 12).unwrap();
+        assert_eq!(*output.borrow(), [1, 2, 3]);
+        assert_eq!(core.ix, 2);
+        assert_eq!(core.save_state().unwrap(), states[history.len()]);
+    }
+
+    #[test]
+    fn test_history_across_checkpoints() {
+        use crate::mappers::SwitchedWindow;
+
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let mut core = Core::with_bus(Box::new(SwitchedWindow::new(banked_rom(4, 0x2000), 0x2000, 0xA000, 0x9000).unwrap()));
+        let mut history = History::default();
+
+        // loop: INX, STX $9000, LDA $A000, STA $10, JMP loop, switching banks as it goes.
+        core.load(0x0200, &[0xE8, 0x8E, 0x00, 0x90, 0xAD, 0x00, 0xA0, 0x85, 0x10, 0x4C, 0x00, 0x02]);
+        core.pc = 0x0200;
+
+        // Stepping, then a change from outside, then stepping on from there.
+        let mut states: Vec<Vec<u8>> = Vec::new();
+        let mut cycles: Vec<u64> = Vec::new();
+        for step in 0..40 {
+            if step == 20 {
+                core.load(0x0300, &[0x99]);
+                history.checkpoint(&mut core).unwrap();
+            }
+
+            states.push(core.save_state().unwrap());
+            cycles.push(core.cycles);
+            history.record(&mut core).unwrap();
+            run_step(&mut core, &table).unwrap();
+        }
+        assert_eq!(history.len(), 40);
+
+        // Back past the checkpoint to a step before it, then a step at a time to the start.
+        let back: u64 = core.cycles - cycles[7];
+        history.rewind_cycles(&mut core, back).unwrap();
+        assert_eq!(history.len(), 7);
+        assert_eq!(core.save_state().unwrap(), states[7]);
+        assert_eq!(core.peek(0x0300), 0x00);
+
+        while history.step_back(&mut core).unwrap() {
+            assert_eq!(core.save_state().unwrap(), states[history.len()]);
+        }
+        assert!(history.is_empty());
+    }
+
+    #[test]
+    fn test_trace() {
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let mut core = init(Variant::Nmos6502);
+        let mut tracer = Tracer::new(TraceFormat::Nestest, Box::new(io::sink()));
+
+        // JMP $C5F5, then LDX #$00, STX $00, LDA ($80),Y, *NOP $A9, JMP ($02FF)
+        core.load(0xC000, &[0x4C, 0xF5, 0xC5]);
+        core.load(0xC5F5, &[0xA2, 0x00, 0x86, 0x00, 0xB1, 0x80, 0x04, 0xA9, 0x6C, 0xFF, 0x02]);
+        core.load(0x0080, &[0x00, 0x02]);
+        core.load(0x0200, &[0x5A]);
+        core.load(0x02FF, &[0x34, 0x12]);
+        core.pc = 0xC000;
+
+        // The first line of nestest.log.
+        let mut lines: Vec<String> = Vec::new();
+        for _ in 0..6 {
+            lines.push(tracer.line(&core, &table));
+            tracer.step(&mut core, &table).unwrap();
+        }
+
+        assert_eq!(lines[0], "C000  4C F5 C5  JMP $C5F5                       A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 21 CYC:7");
+        assert_eq!(lines[1], "C5F5  A2 00     LDX #$00                        A:00 X:00 Y:00 P:24 SP:FD PPU:  0, 30 CYC:10");
+        assert_eq!(&lines[2][16..48], "STX $00 = 00                    ");
+        assert_eq!(&lines[3][16..48], "LDA ($80),Y = 0200 @ 0200 = 5A  ");
+        assert_eq!(&lines[4][6..48], "04 A9    *NOP $A9 = 00                    ");
+
+        // The NMOS page bug takes the high byte from $0200.
+        assert_eq!(&lines[5][16..48], "JMP ($02FF) = 5A34              ");
+        assert_eq!(core.pc, 0x5A34);
+
+        let json = Tracer::new(TraceFormat::JsonLines, Box::new(io::sink()));
+        core.pc = 0xC5F5;
+        assert_eq!(
+            json.line(&core, &table),
+            "{\"pc\":50677,\"bytes\":[162, 0],\"disassembly\":\"LDX #$00\",\"a\":90,\"x\":0,\"y\":0,\"p\":36,\"sp\":253,\"cycles\":28}",
+        );
+
+        // Through a Cpu into a file. The interrupt sequence doesn't get a line.
+        let mut cpu = crate::cpu::Cpu::new(Variant::Nmos6502);
+        cpu.load(0x0200, &[0xEA, 0xEA]);
+        cpu.core.pc = 0x0200;
+        cpu.set_tracer(Some(Tracer::file(TraceFormat::Nestest, "test.log").unwrap())).unwrap();
+        cpu.step().unwrap();
+        cpu.core.trigger_nmi();
+        cpu.step().unwrap();
+        cpu.set_tracer(None).unwrap();
+
+        let log: String = fs::read_to_string("test.log").unwrap();
+        assert_eq!(log.lines().map(|line| &line[..19]).collect::<Vec<&str>>(), ["0200  EA        NOP"]);
+        fs::remove_file("test.log").unwrap();
+    }
+
+    // Output that can't be written to.
+    struct Broken;
+
+    impl Write for Broken {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_trace_write_error() {
+        let table: OpcodeTable = crate::table::gen_table(Variant::Nmos6502);
+        let mut core = init(Variant::Nmos6502);
+        let mut tracer = Tracer::new(TraceFormat::Nestest, Box::new(Broken));
+
+        // The step still happens and comes back, the write error is kept for later.
+        core.load(0x0200, &[0xEA, 0xEA]);
+        core.pc = 0x0200;
+        assert_eq!(tracer.step(&mut core, &table), Ok(Event::Instruction(0xEA)));
+        assert_eq!(tracer.step(&mut core, &table), Ok(Event::Instruction(0xEA)));
+        assert_eq!(core.pc, 0x0202);
+
+        assert_eq!(tracer.flush(), Err(EmulatorError::Io("disk full".to_string())));
+        assert_eq!(tracer.take_error(), None);
+        assert_eq!(tracer.flush(), Ok(()));
     }
 
     #[test]
@@ -477,8 +2975,8 @@ mod tests {
         let mut core = Core::new();
         let data = vec![0x01, 0x02, 0x03, 0x04];
         fs::write("test.bin", &data).unwrap();
-        load_data(&mut core, "test.bin".to_string(), 0x1000);
-        assert_eq!(&core.memory[0x1000..0x1004], &data[..]);
+        load_data(&mut core, "test.bin".to_string(), 0x1000, false).unwrap();
+        assert_eq!((0x1000..0x1004).map(|address| core.peek(address)).collect::<Vec<u8>>(), data);
         fs::remove_file("test.bin").unwrap();
     }
 }
\ No newline at end of file
@@ -0,0 +1,779 @@
+use crate::{
+    instruction::{flags::*, Instruction, Mnemonic::*, Mode::*, Penalty::*},
+    opcodes::*,
+    system::{Core, Variant},
+    w65816,
+};
+
+/*
+    The opcode table. Every variant gets a table of 256 entries, indexed
+    directly by the opcode, each with the function that runs the opcode and
+    its description from instruction.rs. Decoding an instruction is then a
+    single lookup, with nothing allocated, which matters when test suites run
+    hundreds of millions of them.
+
+    This started out as a prefix tree, going through the opcode bit by bit,
+    based on this post:
+    https://dev.to/timclicks/two-trie-implementations-in-rust-ones-super-fast-2f3m
+    A flat array does the same job with a lot less work for every instruction.
+*/
+
+// What runs an opcode.
+pub type Function = fn(&mut Core) -> &mut Core;
+
+// One opcode in the table.
+#[derive(Clone, Copy, Debug)]
+pub struct Entry {
+    pub info: Instruction,
+    pub function: Function,
+}
+
+#[derive(Clone, Debug)]
+pub struct OpcodeTable {
+    entries: [Option<Entry>; 256],
+}
+
+impl Default for OpcodeTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OpcodeTable {
+    // An empty table, where every opcode is invalid.
+    pub fn new() -> Self {
+        Self { entries: [None; 256] }
+    }
+
+    // Add or replace an opcode.
+    pub fn insert(&mut self, opcode: u8, info: Instruction, function: Function) {
+        self.entries[opcode as usize] = Some(Entry { info, function });
+    }
+
+    pub fn contains(&self, opcode: u8) -> bool {
+        self.entries[opcode as usize].is_some()
+    }
+
+    pub fn get(&self, opcode: u8) -> Option<&Entry> {
+        self.entries[opcode as usize].as_ref()
+    }
+
+    // Grab the description of an opcode.
+    pub fn get_instruction(&self, opcode: u8) -> Option<Instruction> {
+        self.get(opcode).map(|entry| entry.info)
+    }
+
+    pub fn get_function(&self, opcode: u8) -> Option<Function> {
+        self.get(opcode).map(|entry| entry.function)
+    }
+}
+
+// All insert code was generated using the python helper script. Each entry is
+// the mnemonic, addressing mode, length, base cycles, cycle penalty and the
+// flags it changes, see instruction.rs.
+// The official NMOS set is shared by every variant, which then adds its own extras.
+pub fn gen_table(variant: Variant) -> OpcodeTable {
+    // The 65816 doesn't share the NMOS table, it has a full set of its own.
+    if variant == Variant::W65816 { return gen_table_65816() }
+
+    let mut table: OpcodeTable = OpcodeTable::new();
+
+    table.insert(0x69, Instruction::new(Adc, Immediate, 2, 2, Fixed, C | Z | V | N), adc);
+    table.insert(0x65, Instruction::new(Adc, ZeroPage, 2, 3, Fixed, C | Z | V | N), adc);
+    table.insert(0x75, Instruction::new(Adc, ZeroPageX, 2, 4, Fixed, C | Z | V | N), adc);
+    table.insert(0x6d, Instruction::new(Adc, Absolute, 3, 4, Fixed, C | Z | V | N), adc);
+    table.insert(0x7d, Instruction::new(Adc, AbsoluteX, 3, 4, PageCross, C | Z | V | N), adc);
+    table.insert(0x79, Instruction::new(Adc, AbsoluteY, 3, 4, PageCross, C | Z | V | N), adc);
+    table.insert(0x61, Instruction::new(Adc, IndexedIndirect, 2, 6, Fixed, C | Z | V | N), adc);
+    table.insert(0x71, Instruction::new(Adc, IndirectIndexed, 2, 5, PageCross, C | Z | V | N), adc);
+    table.insert(0x29, Instruction::new(And, Immediate, 2, 2, Fixed, Z | N), and);
+    table.insert(0x25, Instruction::new(And, ZeroPage, 2, 3, Fixed, Z | N), and);
+    table.insert(0x35, Instruction::new(And, ZeroPageX, 2, 4, Fixed, Z | N), and);
+    table.insert(0x2d, Instruction::new(And, Absolute, 3, 4, Fixed, Z | N), and);
+    table.insert(0x3d, Instruction::new(And, AbsoluteX, 3, 4, PageCross, Z | N), and);
+    table.insert(0x39, Instruction::new(And, AbsoluteY, 3, 4, PageCross, Z | N), and);
+    table.insert(0x21, Instruction::new(And, IndexedIndirect, 2, 6, Fixed, Z | N), and);
+    table.insert(0x31, Instruction::new(And, IndirectIndexed, 2, 5, PageCross, Z | N), and);
+    table.insert(0x0a, Instruction::new(Asl, Accumulator, 1, 2, Fixed, C | Z | N), asl);
+    table.insert(0x06, Instruction::new(Asl, ZeroPage, 2, 5, Fixed, C | Z | N), asl);
+    table.insert(0x16, Instruction::new(Asl, ZeroPageX, 2, 6, Fixed, C | Z | N), asl);
+    table.insert(0x0e, Instruction::new(Asl, Absolute, 3, 6, Fixed, C | Z | N), asl);
+    table.insert(0x1e, Instruction::new(Asl, AbsoluteX, 3, 7, Fixed, C | Z | N), asl);
+    table.insert(0x90, Instruction::new(Bcc, Relative, 2, 2, Branch, NONE), bcc);
+    table.insert(0xB0, Instruction::new(Bcs, Relative, 2, 2, Branch, NONE), bcs);
+    table.insert(0xF0, Instruction::new(Beq, Relative, 2, 2, Branch, NONE), beq);
+    table.insert(0x30, Instruction::new(Bmi, Relative, 2, 2, Branch, NONE), bmi);
+    table.insert(0xD0, Instruction::new(Bne, Relative, 2, 2, Branch, NONE), bne);
+    table.insert(0x10, Instruction::new(Bpl, Relative, 2, 2, Branch, NONE), bpl);
+    table.insert(0x50, Instruction::new(Bvc, Relative, 2, 2, Branch, NONE), bvc);
+    table.insert(0x70, Instruction::new(Bvs, Relative, 2, 2, Branch, NONE), bvs);
+    table.insert(0x24, Instruction::new(Bit, ZeroPage, 2, 3, Fixed, Z | V | N), bit);
+    table.insert(0x2c, Instruction::new(Bit, Absolute, 3, 4, Fixed, Z | V | N), bit);
+    table.insert(0x00, Instruction::new(Brk, Implied, 1, 7, Fixed, NONE), brk);
+    table.insert(0x18, Instruction::new(Clc, Implied, 1, 2, Fixed, C), clc);
+    table.insert(0xd8, Instruction::new(Cld, Implied, 1, 2, Fixed, D), cld);
+    table.insert(0x58, Instruction::new(Cli, Implied, 1, 2, Fixed, I), cli);
+    table.insert(0xb8, Instruction::new(Clv, Implied, 1, 2, Fixed, V), clv);
+    table.insert(0xea, Instruction::new(Nop, Implied, 1, 2, Fixed, NONE), nop);
+    table.insert(0x48, Instruction::new(Pha, Implied, 1, 3, Fixed, NONE), pha);
+    table.insert(0x68, Instruction::new(Pla, Implied, 1, 4, Fixed, Z | N), pla);
+    table.insert(0x08, Instruction::new(Php, Implied, 1, 3, Fixed, NONE), php);
+    table.insert(0x28, Instruction::new(Plp, Implied, 1, 4, Fixed, ALL), plp);
+    table.insert(0x40, Instruction::new(Rti, Implied, 1, 6, Fixed, NONE), rti);
+    table.insert(0x60, Instruction::new(Rts, Implied, 1, 6, Fixed, NONE), rts);
+    table.insert(0x38, Instruction::new(Sec, Implied, 1, 2, Fixed, C), sec);
+    table.insert(0xf8, Instruction::new(Sed, Implied, 1, 2, Fixed, D), sed);
+    table.insert(0x78, Instruction::new(Sei, Implied, 1, 2, Fixed, I), sei);
+    table.insert(0xaa, Instruction::new(Tax, Implied, 1, 2, Fixed, Z | N), tax);
+    table.insert(0x8a, Instruction::new(Txa, Implied, 1, 2, Fixed, Z | N), txa);
+    table.insert(0xa8, Instruction::new(Tay, Implied, 1, 2, Fixed, Z | N), tay);
+    table.insert(0x98, Instruction::new(Tya, Implied, 1, 2, Fixed, Z | N), tya);
+    table.insert(0xba, Instruction::new(Tsx, Implied, 1, 2, Fixed, Z | N), tsx);
+    table.insert(0x9a, Instruction::new(Txs, Implied, 1, 2, Fixed, NONE), txs);
+    table.insert(0xc9, Instruction::new(Cmp, Immediate, 2, 2, Fixed, C | Z | N), cmp);
+    table.insert(0xc5, Instruction::new(Cmp, ZeroPage, 2, 3, Fixed, C | Z | N), cmp);
+    table.insert(0xd5, Instruction::new(Cmp, ZeroPageX, 2, 4, Fixed, C | Z | N), cmp);
+    table.insert(0xcd, Instruction::new(Cmp, Absolute, 3, 4, Fixed, C | Z | N), cmp);
+    table.insert(0xdd, Instruction::new(Cmp, AbsoluteX, 3, 4, PageCross, C | Z | N), cmp);
+    table.insert(0xd9, Instruction::new(Cmp, AbsoluteY, 3, 4, PageCross, C | Z | N), cmp);
+    table.insert(0xc1, Instruction::new(Cmp, IndexedIndirect, 2, 6, Fixed, C | Z | N), cmp);
+    table.insert(0xd1, Instruction::new(Cmp, IndirectIndexed, 2, 5, PageCross, C | Z | N), cmp);
+    table.insert(0xe0, Instruction::new(Cpx, Immediate, 2, 2, Fixed, C | Z | N), cpx);
+    table.insert(0xe4, Instruction::new(Cpx, ZeroPage, 2, 3, Fixed, C | Z | N), cpx);
+    table.insert(0xec, Instruction::new(Cpx, Absolute, 3, 4, Fixed, C | Z | N), cpx);
+    table.insert(0xc0, Instruction::new(Cpy, Immediate, 2, 2, Fixed, C | Z | N), cpy);
+    table.insert(0xc4, Instruction::new(Cpy, ZeroPage, 2, 3, Fixed, C | Z | N), cpy);
+    table.insert(0xcc, Instruction::new(Cpy, Absolute, 3, 4, Fixed, C | Z | N), cpy);
+    table.insert(0xc6, Instruction::new(Dec, ZeroPage, 2, 5, Fixed, Z | N), dec);
+    table.insert(0xd6, Instruction::new(Dec, ZeroPageX, 2, 6, Fixed, Z | N), dec);
+    table.insert(0xce, Instruction::new(Dec, Absolute, 3, 6, Fixed, Z | N), dec);
+    table.insert(0xde, Instruction::new(Dec, AbsoluteX, 3, 7, Fixed, Z | N), dec);
+    table.insert(0xca, Instruction::new(Dex, Implied, 1, 2, Fixed, Z | N), dex);
+    table.insert(0x88, Instruction::new(Dey, Implied, 1, 2, Fixed, Z | N), dey);
+    table.insert(0xe8, Instruction::new(Inx, Implied, 1, 2, Fixed, Z | N), inx);
+    table.insert(0xc8, Instruction::new(Iny, Implied, 1, 2, Fixed, Z | N), iny);
+    table.insert(0x49, Instruction::new(Eor, Immediate, 2, 2, Fixed, Z | N), eor);
+    table.insert(0x45, Instruction::new(Eor, ZeroPage, 2, 3, Fixed, Z | N), eor);
+    table.insert(0x55, Instruction::new(Eor, ZeroPageX, 2, 4, Fixed, Z | N), eor);
+    table.insert(0x4d, Instruction::new(Eor, Absolute, 3, 4, Fixed, Z | N), eor);
+    table.insert(0x5d, Instruction::new(Eor, AbsoluteX, 3, 4, PageCross, Z | N), eor);
+    table.insert(0x59, Instruction::new(Eor, AbsoluteY, 3, 4, PageCross, Z | N), eor);
+    table.insert(0x41, Instruction::new(Eor, IndexedIndirect, 2, 6, Fixed, Z | N), eor);
+    table.insert(0x51, Instruction::new(Eor, IndirectIndexed, 2, 5, PageCross, Z | N), eor);
+    table.insert(0xe6, Instruction::new(Inc, ZeroPage, 2, 5, Fixed, Z | N), inc);
+    table.insert(0xf6, Instruction::new(Inc, ZeroPageX, 2, 6, Fixed, Z | N), inc);
+    table.insert(0xee, Instruction::new(Inc, Absolute, 3, 6, Fixed, Z | N), inc);
+    table.insert(0xfe, Instruction::new(Inc, AbsoluteX, 3, 7, Fixed, Z | N), inc);
+    table.insert(0x4c, Instruction::new(Jmp, Absolute, 3, 3, Fixed, NONE), jmp);
+    table.insert(0x6c, Instruction::new(Jmp, Indirect, 3, 5, Fixed, NONE), jmp);
+    table.insert(0x20, Instruction::new(Jsr, Absolute, 3, 6, Fixed, NONE), jsr);
+    table.insert(0xa9, Instruction::new(Lda, Immediate, 2, 2, Fixed, Z | N), lda);
+    table.insert(0xa5, Instruction::new(Lda, ZeroPage, 2, 3, Fixed, Z | N), lda);
+    table.insert(0xb5, Instruction::new(Lda, ZeroPageX, 2, 4, Fixed, Z | N), lda);
+    table.insert(0xad, Instruction::new(Lda, Absolute, 3, 4, Fixed, Z | N), lda);
+    table.insert(0xbd, Instruction::new(Lda, AbsoluteX, 3, 4, PageCross, Z | N), lda);
+    table.insert(0xb9, Instruction::new(Lda, AbsoluteY, 3, 4, PageCross, Z | N), lda);
+    table.insert(0xa1, Instruction::new(Lda, IndexedIndirect, 2, 6, Fixed, Z | N), lda);
+    table.insert(0xb1, Instruction::new(Lda, IndirectIndexed, 2, 5, PageCross, Z | N), lda);
+    table.insert(0xa2, Instruction::new(Ldx, Immediate, 2, 2, Fixed, Z | N), ldx);
+    table.insert(0xa6, Instruction::new(Ldx, ZeroPage, 2, 3, Fixed, Z | N), ldx);
+    table.insert(0xb6, Instruction::new(Ldx, ZeroPageY, 2, 4, Fixed, Z | N), ldx);
+    table.insert(0xae, Instruction::new(Ldx, Absolute, 3, 4, Fixed, Z | N), ldx);
+    table.insert(0xbe, Instruction::new(Ldx, AbsoluteY, 3, 4, PageCross, Z | N), ldx);
+    table.insert(0xa0, Instruction::new(Ldy, Immediate, 2, 2, Fixed, Z | N), ldy);
+    table.insert(0xa4, Instruction::new(Ldy, ZeroPage, 2, 3, Fixed, Z | N), ldy);
+    table.insert(0xb4, Instruction::new(Ldy, ZeroPageX, 2, 4, Fixed, Z | N), ldy);
+    table.insert(0xac, Instruction::new(Ldy, Absolute, 3, 4, Fixed, Z | N), ldy);
+    table.insert(0xbc, Instruction::new(Ldy, AbsoluteX, 3, 4, PageCross, Z | N), ldy);
+    table.insert(0x4a, Instruction::new(Lsr, Accumulator, 1, 2, Fixed, C | Z | N), lsr);
+    table.insert(0x46, Instruction::new(Lsr, ZeroPage, 2, 5, Fixed, C | Z | N), lsr);
+    table.insert(0x56, Instruction::new(Lsr, ZeroPageX, 2, 6, Fixed, C | Z | N), lsr);
+    table.insert(0x4e, Instruction::new(Lsr, Absolute, 3, 6, Fixed, C | Z | N), lsr);
+    table.insert(0x5e, Instruction::new(Lsr, AbsoluteX, 3, 7, Fixed, C | Z | N), lsr);
+    table.insert(0x09, Instruction::new(Ora, Immediate, 2, 2, Fixed, Z | N), ora);
+    table.insert(0x05, Instruction::new(Ora, ZeroPage, 2, 3, Fixed, Z | N), ora);
+    table.insert(0x15, Instruction::new(Ora, ZeroPageX, 2, 4, Fixed, Z | N), ora);
+    table.insert(0x0d, Instruction::new(Ora, Absolute, 3, 4, Fixed, Z | N), ora);
+    table.insert(0x1d, Instruction::new(Ora, AbsoluteX, 3, 4, PageCross, Z | N), ora);
+    table.insert(0x19, Instruction::new(Ora, AbsoluteY, 3, 4, PageCross, Z | N), ora);
+    table.insert(0x01, Instruction::new(Ora, IndexedIndirect, 2, 6, Fixed, Z | N), ora);
+    table.insert(0x11, Instruction::new(Ora, IndirectIndexed, 2, 5, PageCross, Z | N), ora);
+    table.insert(0x2a, Instruction::new(Rol, Accumulator, 1, 2, Fixed, C | Z | N), rol);
+    table.insert(0x26, Instruction::new(Rol, ZeroPage, 2, 5, Fixed, C | Z | N), rol);
+    table.insert(0x36, Instruction::new(Rol, ZeroPageX, 2, 6, Fixed, C | Z | N), rol);
+    table.insert(0x2e, Instruction::new(Rol, Absolute, 3, 6, Fixed, C | Z | N), rol);
+    table.insert(0x3e, Instruction::new(Rol, AbsoluteX, 3, 7, Fixed, C | Z | N), rol);
+    table.insert(0x6a, Instruction::new(Ror, Accumulator, 1, 2, Fixed, C | Z | N), ror);
+    table.insert(0x66, Instruction::new(Ror, ZeroPage, 2, 5, Fixed, C | Z | N), ror);
+    table.insert(0x76, Instruction::new(Ror, ZeroPageX, 2, 6, Fixed, C | Z | N), ror);
+    table.insert(0x6e, Instruction::new(Ror, Absolute, 3, 6, Fixed, C | Z | N), ror);
+    table.insert(0x7e, Instruction::new(Ror, AbsoluteX, 3, 7, Fixed, C | Z | N), ror);
+    table.insert(0xe9, Instruction::new(Sbc, Immediate, 2, 2, Fixed, C | Z | V | N), sbc);
+    table.insert(0xe5, Instruction::new(Sbc, ZeroPage, 2, 3, Fixed, C | Z | V | N), sbc);
+    table.insert(0xf5, Instruction::new(Sbc, ZeroPageX, 2, 4, Fixed, C | Z | V | N), sbc);
+    table.insert(0xed, Instruction::new(Sbc, Absolute, 3, 4, Fixed, C | Z | V | N), sbc);
+    table.insert(0xfd, Instruction::new(Sbc, AbsoluteX, 3, 4, PageCross, C | Z | V | N), sbc);
+    table.insert(0xf9, Instruction::new(Sbc, AbsoluteY, 3, 4, PageCross, C | Z | V | N), sbc);
+    table.insert(0xe1, Instruction::new(Sbc, IndexedIndirect, 2, 6, Fixed, C | Z | V | N), sbc);
+    table.insert(0xf1, Instruction::new(Sbc, IndirectIndexed, 2, 5, PageCross, C | Z | V | N), sbc);
+    table.insert(0x85, Instruction::new(Sta, ZeroPage, 2, 3, Fixed, NONE), sta);
+    table.insert(0x95, Instruction::new(Sta, ZeroPageX, 2, 4, Fixed, NONE), sta);
+    table.insert(0x8d, Instruction::new(Sta, Absolute, 3, 4, Fixed, NONE), sta);
+    table.insert(0x9d, Instruction::new(Sta, AbsoluteX, 3, 5, Fixed, NONE), sta);
+    table.insert(0x99, Instruction::new(Sta, AbsoluteY, 3, 5, Fixed, NONE), sta);
+    table.insert(0x81, Instruction::new(Sta, IndexedIndirect, 2, 6, Fixed, NONE), sta);
+    table.insert(0x91, Instruction::new(Sta, IndirectIndexed, 2, 6, Fixed, NONE), sta);
+    table.insert(0x86, Instruction::new(Stx, ZeroPage, 2, 3, Fixed, NONE), stx);
+    table.insert(0x96, Instruction::new(Stx, ZeroPageY, 2, 4, Fixed, NONE), stx);
+    table.insert(0x8e, Instruction::new(Stx, Absolute, 3, 4, Fixed, NONE), stx);
+    table.insert(0x84, Instruction::new(Sty, ZeroPage, 2, 3, Fixed, NONE), sty);
+    table.insert(0x94, Instruction::new(Sty, ZeroPageX, 2, 4, Fixed, NONE), sty);
+    table.insert(0x8c, Instruction::new(Sty, Absolute, 3, 4, Fixed, NONE), sty);
+
+
+    if variant.is_cmos() {
+        insert_65c02(&mut table, variant);
+    } else {
+        insert_undocumented(&mut table);
+    }
+                                                
+    table
+}
+
+// Undocumented NMOS opcodes. Together with the official set this fills all 256 entries.
+fn insert_undocumented(table: &mut OpcodeTable) {
+    table.insert(0x03, Instruction::new(Slo, IndexedIndirect, 2, 8, Fixed, C | Z | N), slo);
+    table.insert(0x07, Instruction::new(Slo, ZeroPage, 2, 5, Fixed, C | Z | N), slo);
+    table.insert(0x0f, Instruction::new(Slo, Absolute, 3, 6, Fixed, C | Z | N), slo);
+    table.insert(0x13, Instruction::new(Slo, IndirectIndexed, 2, 8, Fixed, C | Z | N), slo);
+    table.insert(0x17, Instruction::new(Slo, ZeroPageX, 2, 6, Fixed, C | Z | N), slo);
+    table.insert(0x1b, Instruction::new(Slo, AbsoluteY, 3, 7, Fixed, C | Z | N), slo);
+    table.insert(0x1f, Instruction::new(Slo, AbsoluteX, 3, 7, Fixed, C | Z | N), slo);
+    table.insert(0x23, Instruction::new(Rla, IndexedIndirect, 2, 8, Fixed, C | Z | N), rla);
+    table.insert(0x27, Instruction::new(Rla, ZeroPage, 2, 5, Fixed, C | Z | N), rla);
+    table.insert(0x2f, Instruction::new(Rla, Absolute, 3, 6, Fixed, C | Z | N), rla);
+    table.insert(0x33, Instruction::new(Rla, IndirectIndexed, 2, 8, Fixed, C | Z | N), rla);
+    table.insert(0x37, Instruction::new(Rla, ZeroPageX, 2, 6, Fixed, C | Z | N), rla);
+    table.insert(0x3b, Instruction::new(Rla, AbsoluteY, 3, 7, Fixed, C | Z | N), rla);
+    table.insert(0x3f, Instruction::new(Rla, AbsoluteX, 3, 7, Fixed, C | Z | N), rla);
+    table.insert(0x43, Instruction::new(Sre, IndexedIndirect, 2, 8, Fixed, C | Z | N), sre);
+    table.insert(0x47, Instruction::new(Sre, ZeroPage, 2, 5, Fixed, C | Z | N), sre);
+    table.insert(0x4f, Instruction::new(Sre, Absolute, 3, 6, Fixed, C | Z | N), sre);
+    table.insert(0x53, Instruction::new(Sre, IndirectIndexed, 2, 8, Fixed, C | Z | N), sre);
+    table.insert(0x57, Instruction::new(Sre, ZeroPageX, 2, 6, Fixed, C | Z | N), sre);
+    table.insert(0x5b, Instruction::new(Sre, AbsoluteY, 3, 7, Fixed, C | Z | N), sre);
+    table.insert(0x5f, Instruction::new(Sre, AbsoluteX, 3, 7, Fixed, C | Z | N), sre);
+    table.insert(0x63, Instruction::new(Rra, IndexedIndirect, 2, 8, Fixed, C | Z | V | N), rra);
+    table.insert(0x67, Instruction::new(Rra, ZeroPage, 2, 5, Fixed, C | Z | V | N), rra);
+    table.insert(0x6f, Instruction::new(Rra, Absolute, 3, 6, Fixed, C | Z | V | N), rra);
+    table.insert(0x73, Instruction::new(Rra, IndirectIndexed, 2, 8, Fixed, C | Z | V | N), rra);
+    table.insert(0x77, Instruction::new(Rra, ZeroPageX, 2, 6, Fixed, C | Z | V | N), rra);
+    table.insert(0x7b, Instruction::new(Rra, AbsoluteY, 3, 7, Fixed, C | Z | V | N), rra);
+    table.insert(0x7f, Instruction::new(Rra, AbsoluteX, 3, 7, Fixed, C | Z | V | N), rra);
+    table.insert(0xc3, Instruction::new(Dcp, IndexedIndirect, 2, 8, Fixed, C | Z | N), dcp);
+    table.insert(0xc7, Instruction::new(Dcp, ZeroPage, 2, 5, Fixed, C | Z | N), dcp);
+    table.insert(0xcf, Instruction::new(Dcp, Absolute, 3, 6, Fixed, C | Z | N), dcp);
+    table.insert(0xd3, Instruction::new(Dcp, IndirectIndexed, 2, 8, Fixed, C | Z | N), dcp);
+    table.insert(0xd7, Instruction::new(Dcp, ZeroPageX, 2, 6, Fixed, C | Z | N), dcp);
+    table.insert(0xdb, Instruction::new(Dcp, AbsoluteY, 3, 7, Fixed, C | Z | N), dcp);
+    table.insert(0xdf, Instruction::new(Dcp, AbsoluteX, 3, 7, Fixed, C | Z | N), dcp);
+    table.insert(0xe3, Instruction::new(Isc, IndexedIndirect, 2, 8, Fixed, C | Z | V | N), isc);
+    table.insert(0xe7, Instruction::new(Isc, ZeroPage, 2, 5, Fixed, C | Z | V | N), isc);
+    table.insert(0xef, Instruction::new(Isc, Absolute, 3, 6, Fixed, C | Z | V | N), isc);
+    table.insert(0xf3, Instruction::new(Isc, IndirectIndexed, 2, 8, Fixed, C | Z | V | N), isc);
+    table.insert(0xf7, Instruction::new(Isc, ZeroPageX, 2, 6, Fixed, C | Z | V | N), isc);
+    table.insert(0xfb, Instruction::new(Isc, AbsoluteY, 3, 7, Fixed, C | Z | V | N), isc);
+    table.insert(0xff, Instruction::new(Isc, AbsoluteX, 3, 7, Fixed, C | Z | V | N), isc);
+    table.insert(0x87, Instruction::new(Sax, ZeroPage, 2, 3, Fixed, NONE), sax);
+    table.insert(0x97, Instruction::new(Sax, ZeroPageY, 2, 4, Fixed, NONE), sax);
+    table.insert(0x8f, Instruction::new(Sax, Absolute, 3, 4, Fixed, NONE), sax);
+    table.insert(0x83, Instruction::new(Sax, IndexedIndirect, 2, 6, Fixed, NONE), sax);
+    table.insert(0xa7, Instruction::new(Lax, ZeroPage, 2, 3, Fixed, Z | N), lax);
+    table.insert(0xb7, Instruction::new(Lax, ZeroPageY, 2, 4, Fixed, Z | N), lax);
+    table.insert(0xaf, Instruction::new(Lax, Absolute, 3, 4, Fixed, Z | N), lax);
+    table.insert(0xbf, Instruction::new(Lax, AbsoluteY, 3, 4, PageCross, Z | N), lax);
+    table.insert(0xa3, Instruction::new(Lax, IndexedIndirect, 2, 6, Fixed, Z | N), lax);
+    table.insert(0xb3, Instruction::new(Lax, IndirectIndexed, 2, 5, PageCross, Z | N), lax);
+    table.insert(0x0b, Instruction::new(Anc, Immediate, 2, 2, Fixed, C | Z | N), anc);
+    table.insert(0x2b, Instruction::new(Anc, Immediate, 2, 2, Fixed, C | Z | N), anc);
+    table.insert(0x4b, Instruction::new(Alr, Immediate, 2, 2, Fixed, C | Z | N), alr);
+    table.insert(0x6b, Instruction::new(Arr, Immediate, 2, 2, Fixed, C | Z | V | N), arr);
+    table.insert(0xcb, Instruction::new(Sbx, Immediate, 2, 2, Fixed, C | Z | N), sbx);
+    table.insert(0xeb, Instruction::new(Usbc, Immediate, 2, 2, Fixed, C | Z | V | N), sbc);
+    table.insert(0x8b, Instruction::new(Ane, Immediate, 2, 2, Fixed, Z | N), ane);
+    table.insert(0xab, Instruction::new(Lxa, Immediate, 2, 2, Fixed, Z | N), lxa);
+    table.insert(0xbb, Instruction::new(Las, AbsoluteY, 3, 4, PageCross, Z | N), las);
+    table.insert(0x93, Instruction::new(Sha, IndirectIndexed, 2, 6, Fixed, NONE), sha);
+    table.insert(0x9f, Instruction::new(Sha, AbsoluteY, 3, 5, Fixed, NONE), sha);
+    table.insert(0x9e, Instruction::new(Shx, AbsoluteY, 3, 5, Fixed, NONE), shx);
+    table.insert(0x9c, Instruction::new(Shy, AbsoluteX, 3, 5, Fixed, NONE), shy);
+    table.insert(0x9b, Instruction::new(Tas, AbsoluteY, 3, 5, Fixed, NONE), tas);
+    table.insert(0x1a, Instruction::new(Nop, Implied, 1, 2, Fixed, NONE), nop);
+    table.insert(0x3a, Instruction::new(Nop, Implied, 1, 2, Fixed, NONE), nop);
+    table.insert(0x5a, Instruction::new(Nop, Implied, 1, 2, Fixed, NONE), nop);
+    table.insert(0x7a, Instruction::new(Nop, Implied, 1, 2, Fixed, NONE), nop);
+    table.insert(0xda, Instruction::new(Nop, Implied, 1, 2, Fixed, NONE), nop);
+    table.insert(0xfa, Instruction::new(Nop, Implied, 1, 2, Fixed, NONE), nop);
+    table.insert(0x80, Instruction::new(Nop, Immediate, 2, 2, Fixed, NONE), nop);
+    table.insert(0x82, Instruction::new(Nop, Immediate, 2, 2, Fixed, NONE), nop);
+    table.insert(0x89, Instruction::new(Nop, Immediate, 2, 2, Fixed, NONE), nop);
+    table.insert(0xc2, Instruction::new(Nop, Immediate, 2, 2, Fixed, NONE), nop);
+    table.insert(0xe2, Instruction::new(Nop, Immediate, 2, 2, Fixed, NONE), nop);
+    table.insert(0x04, Instruction::new(Nop, ZeroPage, 2, 3, Fixed, NONE), nop);
+    table.insert(0x44, Instruction::new(Nop, ZeroPage, 2, 3, Fixed, NONE), nop);
+    table.insert(0x64, Instruction::new(Nop, ZeroPage, 2, 3, Fixed, NONE), nop);
+    table.insert(0x14, Instruction::new(Nop, ZeroPageX, 2, 4, Fixed, NONE), nop);
+    table.insert(0x34, Instruction::new(Nop, ZeroPageX, 2, 4, Fixed, NONE), nop);
+    table.insert(0x54, Instruction::new(Nop, ZeroPageX, 2, 4, Fixed, NONE), nop);
+    table.insert(0x74, Instruction::new(Nop, ZeroPageX, 2, 4, Fixed, NONE), nop);
+    table.insert(0xd4, Instruction::new(Nop, ZeroPageX, 2, 4, Fixed, NONE), nop);
+    table.insert(0xf4, Instruction::new(Nop, ZeroPageX, 2, 4, Fixed, NONE), nop);
+    table.insert(0x0c, Instruction::new(Nop, Absolute, 3, 4, Fixed, NONE), nop);
+    table.insert(0x1c, Instruction::new(Nop, AbsoluteX, 3, 4, PageCross, NONE), nop);
+    table.insert(0x3c, Instruction::new(Nop, AbsoluteX, 3, 4, PageCross, NONE), nop);
+    table.insert(0x5c, Instruction::new(Nop, AbsoluteX, 3, 4, PageCross, NONE), nop);
+    table.insert(0x7c, Instruction::new(Nop, AbsoluteX, 3, 4, PageCross, NONE), nop);
+    table.insert(0xdc, Instruction::new(Nop, AbsoluteX, 3, 4, PageCross, NONE), nop);
+    table.insert(0xfc, Instruction::new(Nop, AbsoluteX, 3, 4, PageCross, NONE), nop);
+    table.insert(0x02, Instruction::new(Jam, Implied, 1, 2, Fixed, NONE), jam);
+    table.insert(0x12, Instruction::new(Jam, Implied, 1, 2, Fixed, NONE), jam);
+    table.insert(0x22, Instruction::new(Jam, Implied, 1, 2, Fixed, NONE), jam);
+    table.insert(0x32, Instruction::new(Jam, Implied, 1, 2, Fixed, NONE), jam);
+    table.insert(0x42, Instruction::new(Jam, Implied, 1, 2, Fixed, NONE), jam);
+    table.insert(0x52, Instruction::new(Jam, Implied, 1, 2, Fixed, NONE), jam);
+    table.insert(0x62, Instruction::new(Jam, Implied, 1, 2, Fixed, NONE), jam);
+    table.insert(0x72, Instruction::new(Jam, Implied, 1, 2, Fixed, NONE), jam);
+    table.insert(0x92, Instruction::new(Jam, Implied, 1, 2, Fixed, NONE), jam);
+    table.insert(0xb2, Instruction::new(Jam, Implied, 1, 2, Fixed, NONE), jam);
+    table.insert(0xd2, Instruction::new(Jam, Implied, 1, 2, Fixed, NONE), jam);
+    table.insert(0xf2, Instruction::new(Jam, Implied, 1, 2, Fixed, NONE), jam);
+}
+
+// 65C02 additions. The CMOS parts fixed a few cycle counts and turned every
+// opcode they don't use into a NOP, so there are no undocumented opcodes.
+fn insert_65c02(table: &mut OpcodeTable, variant: Variant) {
+    table.insert(0x80, Instruction::new(Bra, Relative, 2, 2, Branch, NONE), bra);
+    table.insert(0xda, Instruction::new(Phx, Implied, 1, 3, Fixed, NONE), phx);
+    table.insert(0x5a, Instruction::new(Phy, Implied, 1, 3, Fixed, NONE), phy);
+    table.insert(0xfa, Instruction::new(Plx, Implied, 1, 4, Fixed, Z | N), plx);
+    table.insert(0x7a, Instruction::new(Ply, Implied, 1, 4, Fixed, Z | N), ply);
+    table.insert(0x64, Instruction::new(Stz, ZeroPage, 2, 3, Fixed, NONE), stz);
+    table.insert(0x74, Instruction::new(Stz, ZeroPageX, 2, 4, Fixed, NONE), stz);
+    table.insert(0x9c, Instruction::new(Stz, Absolute, 3, 4, Fixed, NONE), stz);
+    table.insert(0x9e, Instruction::new(Stz, AbsoluteX, 3, 5, Fixed, NONE), stz);
+    table.insert(0x04, Instruction::new(Tsb, ZeroPage, 2, 5, Fixed, Z), tsb);
+    table.insert(0x0c, Instruction::new(Tsb, Absolute, 3, 6, Fixed, Z), tsb);
+    table.insert(0x14, Instruction::new(Trb, ZeroPage, 2, 5, Fixed, Z), trb);
+    table.insert(0x1c, Instruction::new(Trb, Absolute, 3, 6, Fixed, Z), trb);
+    table.insert(0x12, Instruction::new(Ora, ZeroPageIndirect, 2, 5, Fixed, Z | N), ora);
+    table.insert(0x32, Instruction::new(And, ZeroPageIndirect, 2, 5, Fixed, Z | N), and);
+    table.insert(0x52, Instruction::new(Eor, ZeroPageIndirect, 2, 5, Fixed, Z | N), eor);
+    table.insert(0x72, Instruction::new(Adc, ZeroPageIndirect, 2, 5, Fixed, C | Z | V | N), adc);
+    table.insert(0x92, Instruction::new(Sta, ZeroPageIndirect, 2, 5, Fixed, NONE), sta);
+    table.insert(0xb2, Instruction::new(Lda, ZeroPageIndirect, 2, 5, Fixed, Z | N), lda);
+    table.insert(0xd2, Instruction::new(Cmp, ZeroPageIndirect, 2, 5, Fixed, C | Z | N), cmp);
+    table.insert(0xf2, Instruction::new(Sbc, ZeroPageIndirect, 2, 5, Fixed, C | Z | V | N), sbc);
+    table.insert(0x1a, Instruction::new(Inc, Accumulator, 1, 2, Fixed, Z | N), inc);
+    table.insert(0x3a, Instruction::new(Dec, Accumulator, 1, 2, Fixed, Z | N), dec);
+    table.insert(0x89, Instruction::new(Bit, Immediate, 2, 2, Fixed, Z), bit);
+    table.insert(0x34, Instruction::new(Bit, ZeroPageX, 2, 4, Fixed, Z | V | N), bit);
+    table.insert(0x3c, Instruction::new(Bit, AbsoluteX, 3, 4, PageCross, Z | V | N), bit);
+    table.insert(0x7c, Instruction::new(Jmp, AbsoluteIndexedIndirect, 3, 6, Fixed, NONE), jmp);
+
+    // Changed cycle counts. JMP (ind) takes an extra cycle to fix the page
+    // wrap bug, and the shifts on ABSX only take the extra cycle on a page cross.
+    table.insert(0x6c, Instruction::new(Jmp, Indirect, 3, 6, Fixed, NONE), jmp);
+    table.insert(0x1e, Instruction::new(Asl, AbsoluteX, 3, 6, PageCross, C | Z | N), asl);
+    table.insert(0x5e, Instruction::new(Lsr, AbsoluteX, 3, 6, PageCross, C | Z | N), lsr);
+    table.insert(0x3e, Instruction::new(Rol, AbsoluteX, 3, 6, PageCross, C | Z | N), rol);
+    table.insert(0x7e, Instruction::new(Ror, AbsoluteX, 3, 6, PageCross, C | Z | N), ror);
+
+    // Unused opcodes are NOPs.
+    table.insert(0x02, Instruction::new(Nop, Immediate, 2, 2, Fixed, NONE), nop);
+    table.insert(0x03, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x07, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x0b, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x0f, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x13, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x17, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x1b, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x1f, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x22, Instruction::new(Nop, Immediate, 2, 2, Fixed, NONE), nop);
+    table.insert(0x23, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x27, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x2b, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x2f, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x33, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x37, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x3b, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x3f, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x42, Instruction::new(Nop, Immediate, 2, 2, Fixed, NONE), nop);
+    table.insert(0x43, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x44, Instruction::new(Nop, ZeroPage, 2, 3, Fixed, NONE), nop);
+    table.insert(0x47, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x4b, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x4f, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x53, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x54, Instruction::new(Nop, ZeroPageX, 2, 4, Fixed, NONE), nop);
+    table.insert(0x57, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x5b, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x5c, Instruction::new(Nop, Absolute, 3, 8, Fixed, NONE), nop);
+    table.insert(0x5f, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x62, Instruction::new(Nop, Immediate, 2, 2, Fixed, NONE), nop);
+    table.insert(0x63, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x67, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x6b, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x6f, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x73, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x77, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x7b, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x7f, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x82, Instruction::new(Nop, Immediate, 2, 2, Fixed, NONE), nop);
+    table.insert(0x83, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x87, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x8b, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x8f, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x93, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x97, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x9b, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0x9f, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xa3, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xa7, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xab, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xaf, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xb3, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xb7, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xbb, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xbf, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xc2, Instruction::new(Nop, Immediate, 2, 2, Fixed, NONE), nop);
+    table.insert(0xc3, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xc7, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xcb, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xcf, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xd3, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xd4, Instruction::new(Nop, ZeroPageX, 2, 4, Fixed, NONE), nop);
+    table.insert(0xd7, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xdb, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xdc, Instruction::new(Nop, Absolute, 3, 4, Fixed, NONE), nop);
+    table.insert(0xdf, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xe2, Instruction::new(Nop, Immediate, 2, 2, Fixed, NONE), nop);
+    table.insert(0xe3, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xe7, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xeb, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xef, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xf3, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xf4, Instruction::new(Nop, ZeroPageX, 2, 4, Fixed, NONE), nop);
+    table.insert(0xf7, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xfb, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+    table.insert(0xfc, Instruction::new(Nop, Absolute, 3, 4, Fixed, NONE), nop);
+    table.insert(0xff, Instruction::new(Nop, Implied, 1, 1, Fixed, NONE), nop);
+
+    // Rockwell bit instructions. These replace some of the single byte NOPs.
+    if variant.has_bit_instructions() {
+        table.insert(0x07, Instruction::new(Rmb(0), ZeroPage, 2, 5, Fixed, NONE), rmb);
+        table.insert(0x87, Instruction::new(Smb(0), ZeroPage, 2, 5, Fixed, NONE), smb);
+        table.insert(0x0f, Instruction::new(Bbr(0), ZeroPageRelative, 3, 5, Branch, NONE), bbr);
+        table.insert(0x8f, Instruction::new(Bbs(0), ZeroPageRelative, 3, 5, Branch, NONE), bbs);
+        table.insert(0x17, Instruction::new(Rmb(1), ZeroPage, 2, 5, Fixed, NONE), rmb);
+        table.insert(0x97, Instruction::new(Smb(1), ZeroPage, 2, 5, Fixed, NONE), smb);
+        table.insert(0x1f, Instruction::new(Bbr(1), ZeroPageRelative, 3, 5, Branch, NONE), bbr);
+        table.insert(0x9f, Instruction::new(Bbs(1), ZeroPageRelative, 3, 5, Branch, NONE), bbs);
+        table.insert(0x27, Instruction::new(Rmb(2), ZeroPage, 2, 5, Fixed, NONE), rmb);
+        table.insert(0xa7, Instruction::new(Smb(2), ZeroPage, 2, 5, Fixed, NONE), smb);
+        table.insert(0x2f, Instruction::new(Bbr(2), ZeroPageRelative, 3, 5, Branch, NONE), bbr);
+        table.insert(0xaf, Instruction::new(Bbs(2), ZeroPageRelative, 3, 5, Branch, NONE), bbs);
+        table.insert(0x37, Instruction::new(Rmb(3), ZeroPage, 2, 5, Fixed, NONE), rmb);
+        table.insert(0xb7, Instruction::new(Smb(3), ZeroPage, 2, 5, Fixed, NONE), smb);
+        table.insert(0x3f, Instruction::new(Bbr(3), ZeroPageRelative, 3, 5, Branch, NONE), bbr);
+        table.insert(0xbf, Instruction::new(Bbs(3), ZeroPageRelative, 3, 5, Branch, NONE), bbs);
+        table.insert(0x47, Instruction::new(Rmb(4), ZeroPage, 2, 5, Fixed, NONE), rmb);
+        table.insert(0xc7, Instruction::new(Smb(4), ZeroPage, 2, 5, Fixed, NONE), smb);
+        table.insert(0x4f, Instruction::new(Bbr(4), ZeroPageRelative, 3, 5, Branch, NONE), bbr);
+        table.insert(0xcf, Instruction::new(Bbs(4), ZeroPageRelative, 3, 5, Branch, NONE), bbs);
+        table.insert(0x57, Instruction::new(Rmb(5), ZeroPage, 2, 5, Fixed, NONE), rmb);
+        table.insert(0xd7, Instruction::new(Smb(5), ZeroPage, 2, 5, Fixed, NONE), smb);
+        table.insert(0x5f, Instruction::new(Bbr(5), ZeroPageRelative, 3, 5, Branch, NONE), bbr);
+        table.insert(0xdf, Instruction::new(Bbs(5), ZeroPageRelative, 3, 5, Branch, NONE), bbs);
+        table.insert(0x67, Instruction::new(Rmb(6), ZeroPage, 2, 5, Fixed, NONE), rmb);
+        table.insert(0xe7, Instruction::new(Smb(6), ZeroPage, 2, 5, Fixed, NONE), smb);
+        table.insert(0x6f, Instruction::new(Bbr(6), ZeroPageRelative, 3, 5, Branch, NONE), bbr);
+        table.insert(0xef, Instruction::new(Bbs(6), ZeroPageRelative, 3, 5, Branch, NONE), bbs);
+        table.insert(0x77, Instruction::new(Rmb(7), ZeroPage, 2, 5, Fixed, NONE), rmb);
+        table.insert(0xf7, Instruction::new(Smb(7), ZeroPage, 2, 5, Fixed, NONE), smb);
+        table.insert(0x7f, Instruction::new(Bbr(7), ZeroPageRelative, 3, 5, Branch, NONE), bbr);
+        table.insert(0xff, Instruction::new(Bbs(7), ZeroPageRelative, 3, 5, Branch, NONE), bbs);
+    }
+
+    // WDC low power instructions.
+    if variant == Variant::Wdc65C02 {
+        table.insert(0xcb, Instruction::new(Wai, Implied, 1, 3, Fixed, NONE), wai);
+        table.insert(0xdb, Instruction::new(Stp, Implied, 1, 3, Fixed, NONE), stp);
+    }
+}
+
+// WDC 65C816 table. Every opcode is used, mostly by functions in w65816.rs.
+// IMMM and IMMX immediates are listed at their 8-bit size and gain a byte
+// when the M or X flag is clear. Cycle counts are for 8-bit registers and a
+// page aligned direct page; the functions add the extra cycles themselves.
+fn gen_table_65816() -> OpcodeTable {
+    let mut table: OpcodeTable = OpcodeTable::new();
+
+    table.insert(0x00, Instruction::new(Brk, Immediate, 2, 7, Fixed, I | D), w65816::brk);
+    table.insert(0x01, Instruction::new(Ora, IndexedIndirect, 2, 6, Fixed, Z | N), w65816::ora);
+    table.insert(0x02, Instruction::new(Cop, Immediate, 2, 7, Fixed, I | D), w65816::cop);
+    table.insert(0x03, Instruction::new(Ora, StackRelative, 2, 4, Fixed, Z | N), w65816::ora);
+    table.insert(0x04, Instruction::new(Tsb, ZeroPage, 2, 5, Fixed, Z), w65816::tsb);
+    table.insert(0x05, Instruction::new(Ora, ZeroPage, 2, 3, Fixed, Z | N), w65816::ora);
+    table.insert(0x06, Instruction::new(Asl, ZeroPage, 2, 5, Fixed, C | Z | N), w65816::asl);
+    table.insert(0x07, Instruction::new(Ora, IndirectLong, 2, 6, Fixed, Z | N), w65816::ora);
+    table.insert(0x08, Instruction::new(Php, Implied, 1, 3, Fixed, NONE), w65816::php);
+    table.insert(0x09, Instruction::new(Ora, ImmediateM, 2, 2, Fixed, Z | N), w65816::ora);
+    table.insert(0x0a, Instruction::new(Asl, Accumulator, 1, 2, Fixed, C | Z | N), w65816::asl);
+    table.insert(0x0b, Instruction::new(Phd, Implied, 1, 4, Fixed, NONE), w65816::phd);
+    table.insert(0x0c, Instruction::new(Tsb, Absolute, 3, 6, Fixed, Z), w65816::tsb);
+    table.insert(0x0d, Instruction::new(Ora, Absolute, 3, 4, Fixed, Z | N), w65816::ora);
+    table.insert(0x0e, Instruction::new(Asl, Absolute, 3, 6, Fixed, C | Z | N), w65816::asl);
+    table.insert(0x0f, Instruction::new(Ora, AbsoluteLong, 4, 5, Fixed, Z | N), w65816::ora);
+    table.insert(0x10, Instruction::new(Bpl, Relative, 2, 2, Branch, NONE), w65816::bpl);
+    table.insert(0x11, Instruction::new(Ora, IndirectIndexed, 2, 5, PageCross, Z | N), w65816::ora);
+    table.insert(0x12, Instruction::new(Ora, ZeroPageIndirect, 2, 5, Fixed, Z | N), w65816::ora);
+    table.insert(0x13, Instruction::new(Ora, StackRelativeIndirectY, 2, 7, Fixed, Z | N), w65816::ora);
+    table.insert(0x14, Instruction::new(Trb, ZeroPage, 2, 5, Fixed, Z), w65816::trb);
+    table.insert(0x15, Instruction::new(Ora, ZeroPageX, 2, 4, Fixed, Z | N), w65816::ora);
+    table.insert(0x16, Instruction::new(Asl, ZeroPageX, 2, 6, Fixed, C | Z | N), w65816::asl);
+    table.insert(0x17, Instruction::new(Ora, IndirectLongY, 2, 6, Fixed, Z | N), w65816::ora);
+    table.insert(0x18, Instruction::new(Clc, Implied, 1, 2, Fixed, C), clc);
+    table.insert(0x19, Instruction::new(Ora, AbsoluteY, 3, 4, PageCross, Z | N), w65816::ora);
+    table.insert(0x1a, Instruction::new(Inc, Accumulator, 1, 2, Fixed, Z | N), w65816::inc);
+    table.insert(0x1b, Instruction::new(Tcs, Implied, 1, 2, Fixed, NONE), w65816::tcs);
+    table.insert(0x1c, Instruction::new(Trb, Absolute, 3, 6, Fixed, Z), w65816::trb);
+    table.insert(0x1d, Instruction::new(Ora, AbsoluteX, 3, 4, PageCross, Z | N), w65816::ora);
+    table.insert(0x1e, Instruction::new(Asl, AbsoluteX, 3, 7, Fixed, C | Z | N), w65816::asl);
+    table.insert(0x1f, Instruction::new(Ora, AbsoluteLongX, 4, 5, Fixed, Z | N), w65816::ora);
+    table.insert(0x20, Instruction::new(Jsr, Absolute, 3, 6, Fixed, NONE), w65816::jsr);
+    table.insert(0x21, Instruction::new(And, IndexedIndirect, 2, 6, Fixed, Z | N), w65816::and);
+    table.insert(0x22, Instruction::new(Jsl, AbsoluteLong, 4, 8, Fixed, NONE), w65816::jsl);
+    table.insert(0x23, Instruction::new(And, StackRelative, 2, 4, Fixed, Z | N), w65816::and);
+    table.insert(0x24, Instruction::new(Bit, ZeroPage, 2, 3, Fixed, Z | V | N), w65816::bit);
+    table.insert(0x25, Instruction::new(And, ZeroPage, 2, 3, Fixed, Z | N), w65816::and);
+    table.insert(0x26, Instruction::new(Rol, ZeroPage, 2, 5, Fixed, C | Z | N), w65816::rol);
+    table.insert(0x27, Instruction::new(And, IndirectLong, 2, 6, Fixed, Z | N), w65816::and);
+    table.insert(0x28, Instruction::new(Plp, Implied, 1, 4, Fixed, ALL), w65816::plp);
+    table.insert(0x29, Instruction::new(And, ImmediateM, 2, 2, Fixed, Z | N), w65816::and);
+    table.insert(0x2a, Instruction::new(Rol, Accumulator, 1, 2, Fixed, C | Z | N), w65816::rol);
+    table.insert(0x2b, Instruction::new(Pld, Implied, 1, 5, Fixed, Z | N), w65816::pld);
+    table.insert(0x2c, Instruction::new(Bit, Absolute, 3, 4, Fixed, Z | V | N), w65816::bit);
+    table.insert(0x2d, Instruction::new(And, Absolute, 3, 4, Fixed, Z | N), w65816::and);
+    table.insert(0x2e, Instruction::new(Rol, Absolute, 3, 6, Fixed, C | Z | N), w65816::rol);
+    table.insert(0x2f, Instruction::new(And, AbsoluteLong, 4, 5, Fixed, Z | N), w65816::and);
+    table.insert(0x30, Instruction::new(Bmi, Relative, 2, 2, Branch, NONE), w65816::bmi);
+    table.insert(0x31, Instruction::new(And, IndirectIndexed, 2, 5, PageCross, Z | N), w65816::and);
+    table.insert(0x32, Instruction::new(And, ZeroPageIndirect, 2, 5, Fixed, Z | N), w65816::and);
+    table.insert(0x33, Instruction::new(And, StackRelativeIndirectY, 2, 7, Fixed, Z | N), w65816::and);
+    table.insert(0x34, Instruction::new(Bit, ZeroPageX, 2, 4, Fixed, Z | V | N), w65816::bit);
+    table.insert(0x35, Instruction::new(And, ZeroPageX, 2, 4, Fixed, Z | N), w65816::and);
+    table.insert(0x36, Instruction::new(Rol, ZeroPageX, 2, 6, Fixed, C | Z | N), w65816::rol);
+    table.insert(0x37, Instruction::new(And, IndirectLongY, 2, 6, Fixed, Z | N), w65816::and);
+    table.insert(0x38, Instruction::new(Sec, Implied, 1, 2, Fixed, C), sec);
+    table.insert(0x39, Instruction::new(And, AbsoluteY, 3, 4, PageCross, Z | N), w65816::and);
+    table.insert(0x3a, Instruction::new(Dec, Accumulator, 1, 2, Fixed, Z | N), w65816::dec);
+    table.insert(0x3b, Instruction::new(Tsc, Implied, 1, 2, Fixed, Z | N), w65816::tsc);
+    table.insert(0x3c, Instruction::new(Bit, AbsoluteX, 3, 4, PageCross, Z | V | N), w65816::bit);
+    table.insert(0x3d, Instruction::new(And, AbsoluteX, 3, 4, PageCross, Z | N), w65816::and);
+    table.insert(0x3e, Instruction::new(Rol, AbsoluteX, 3, 7, Fixed, C | Z | N), w65816::rol);
+    table.insert(0x3f, Instruction::new(And, AbsoluteLongX, 4, 5, Fixed, Z | N), w65816::and);
+    table.insert(0x40, Instruction::new(Rti, Implied, 1, 6, Fixed, ALL), w65816::rti);
+    table.insert(0x41, Instruction::new(Eor, IndexedIndirect, 2, 6, Fixed, Z | N), w65816::eor);
+    table.insert(0x42, Instruction::new(Wdm, Immediate, 2, 2, Fixed, NONE), w65816::wdm);
+    table.insert(0x43, Instruction::new(Eor, StackRelative, 2, 4, Fixed, Z | N), w65816::eor);
+    table.insert(0x44, Instruction::new(Mvp, BlockMove, 3, 7, Fixed, NONE), w65816::mvp);
+    table.insert(0x45, Instruction::new(Eor, ZeroPage, 2, 3, Fixed, Z | N), w65816::eor);
+    table.insert(0x46, Instruction::new(Lsr, ZeroPage, 2, 5, Fixed, C | Z | N), w65816::lsr);
+    table.insert(0x47, Instruction::new(Eor, IndirectLong, 2, 6, Fixed, Z | N), w65816::eor);
+    table.insert(0x48, Instruction::new(Pha, Implied, 1, 3, Fixed, NONE), w65816::pha);
+    table.insert(0x49, Instruction::new(Eor, ImmediateM, 2, 2, Fixed, Z | N), w65816::eor);
+    table.insert(0x4a, Instruction::new(Lsr, Accumulator, 1, 2, Fixed, C | Z | N), w65816::lsr);
+    table.insert(0x4b, Instruction::new(Phk, Implied, 1, 3, Fixed, NONE), w65816::phk);
+    table.insert(0x4c, Instruction::new(Jmp, Absolute, 3, 3, Fixed, NONE), w65816::jmp);
+    table.insert(0x4d, Instruction::new(Eor, Absolute, 3, 4, Fixed, Z | N), w65816::eor);
+    table.insert(0x4e, Instruction::new(Lsr, Absolute, 3, 6, Fixed, C | Z | N), w65816::lsr);
+    table.insert(0x4f, Instruction::new(Eor, AbsoluteLong, 4, 5, Fixed, Z | N), w65816::eor);
+    table.insert(0x50, Instruction::new(Bvc, Relative, 2, 2, Branch, NONE), w65816::bvc);
+    table.insert(0x51, Instruction::new(Eor, IndirectIndexed, 2, 5, PageCross, Z | N), w65816::eor);
+    table.insert(0x52, Instruction::new(Eor, ZeroPageIndirect, 2, 5, Fixed, Z | N), w65816::eor);
+    table.insert(0x53, Instruction::new(Eor, StackRelativeIndirectY, 2, 7, Fixed, Z | N), w65816::eor);
+    table.insert(0x54, Instruction::new(Mvn, BlockMove, 3, 7, Fixed, NONE), w65816::mvn);
+    table.insert(0x55, Instruction::new(Eor, ZeroPageX, 2, 4, Fixed, Z | N), w65816::eor);
+    table.insert(0x56, Instruction::new(Lsr, ZeroPageX, 2, 6, Fixed, C | Z | N), w65816::lsr);
+    table.insert(0x57, Instruction::new(Eor, IndirectLongY, 2, 6, Fixed, Z | N), w65816::eor);
+    table.insert(0x58, Instruction::new(Cli, Implied, 1, 2, Fixed, I), cli);
+    table.insert(0x59, Instruction::new(Eor, AbsoluteY, 3, 4, PageCross, Z | N), w65816::eor);
+    table.insert(0x5a, Instruction::new(Phy, Implied, 1, 3, Fixed, NONE), w65816::phy);
+    table.insert(0x5b, Instruction::new(Tcd, Implied, 1, 2, Fixed, Z | N), w65816::tcd);
+    table.insert(0x5c, Instruction::new(Jml, AbsoluteLong, 4, 4, Fixed, NONE), w65816::jml);
+    table.insert(0x5d, Instruction::new(Eor, AbsoluteX, 3, 4, PageCross, Z | N), w65816::eor);
+    table.insert(0x5e, Instruction::new(Lsr, AbsoluteX, 3, 7, Fixed, C | Z | N), w65816::lsr);
+    table.insert(0x5f, Instruction::new(Eor, AbsoluteLongX, 4, 5, Fixed, Z | N), w65816::eor);
+    table.insert(0x60, Instruction::new(Rts, Implied, 1, 6, Fixed, NONE), w65816::rts);
+    table.insert(0x61, Instruction::new(Adc, IndexedIndirect, 2, 6, Fixed, C | Z | V | N), w65816::adc);
+    table.insert(0x62, Instruction::new(Per, RelativeLong, 3, 6, Fixed, NONE), w65816::per);
+    table.insert(0x63, Instruction::new(Adc, StackRelative, 2, 4, Fixed, C | Z | V | N), w65816::adc);
+    table.insert(0x64, Instruction::new(Stz, ZeroPage, 2, 3, Fixed, NONE), w65816::stz);
+    table.insert(0x65, Instruction::new(Adc, ZeroPage, 2, 3, Fixed, C | Z | V | N), w65816::adc);
+    table.insert(0x66, Instruction::new(Ror, ZeroPage, 2, 5, Fixed, C | Z | N), w65816::ror);
+    table.insert(0x67, Instruction::new(Adc, IndirectLong, 2, 6, Fixed, C | Z | V | N), w65816::adc);
+    table.insert(0x68, Instruction::new(Pla, Implied, 1, 4, Fixed, Z | N), w65816::pla);
+    table.insert(0x69, Instruction::new(Adc, ImmediateM, 2, 2, Fixed, C | Z | V | N), w65816::adc);
+    table.insert(0x6a, Instruction::new(Ror, Accumulator, 1, 2, Fixed, C | Z | N), w65816::ror);
+    table.insert(0x6b, Instruction::new(Rtl, Implied, 1, 6, Fixed, NONE), w65816::rtl);
+    table.insert(0x6c, Instruction::new(Jmp, Indirect, 3, 5, Fixed, NONE), w65816::jmp);
+    table.insert(0x6d, Instruction::new(Adc, Absolute, 3, 4, Fixed, C | Z | V | N), w65816::adc);
+    table.insert(0x6e, Instruction::new(Ror, Absolute, 3, 6, Fixed, C | Z | N), w65816::ror);
+    table.insert(0x6f, Instruction::new(Adc, AbsoluteLong, 4, 5, Fixed, C | Z | V | N), w65816::adc);
+    table.insert(0x70, Instruction::new(Bvs, Relative, 2, 2, Branch, NONE), w65816::bvs);
+    table.insert(0x71, Instruction::new(Adc, IndirectIndexed, 2, 5, PageCross, C | Z | V | N), w65816::adc);
+    table.insert(0x72, Instruction::new(Adc, ZeroPageIndirect, 2, 5, Fixed, C | Z | V | N), w65816::adc);
+    table.insert(0x73, Instruction::new(Adc, StackRelativeIndirectY, 2, 7, Fixed, C | Z | V | N), w65816::adc);
+    table.insert(0x74, Instruction::new(Stz, ZeroPageX, 2, 4, Fixed, NONE), w65816::stz);
+    table.insert(0x75, Instruction::new(Adc, ZeroPageX, 2, 4, Fixed, C | Z | V | N), w65816::adc);
+    table.insert(0x76, Instruction::new(Ror, ZeroPageX, 2, 6, Fixed, C | Z | N), w65816::ror);
+    table.insert(0x77, Instruction::new(Adc, IndirectLongY, 2, 6, Fixed, C | Z | V | N), w65816::adc);
+    table.insert(0x78, Instruction::new(Sei, Implied, 1, 2, Fixed, I), sei);
+    table.insert(0x79, Instruction::new(Adc, AbsoluteY, 3, 4, PageCross, C | Z | V | N), w65816::adc);
+    table.insert(0x7a, Instruction::new(Ply, Implied, 1, 4, Fixed, Z | N), w65816::ply);
+    table.insert(0x7b, Instruction::new(Tdc, Implied, 1, 2, Fixed, Z | N), w65816::tdc);
+    table.insert(0x7c, Instruction::new(Jmp, AbsoluteIndexedIndirect, 3, 6, Fixed, NONE), w65816::jmp);
+    table.insert(0x7d, Instruction::new(Adc, AbsoluteX, 3, 4, PageCross, C | Z | V | N), w65816::adc);
+    table.insert(0x7e, Instruction::new(Ror, AbsoluteX, 3, 7, Fixed, C | Z | N), w65816::ror);
+    table.insert(0x7f, Instruction::new(Adc, AbsoluteLongX, 4, 5, Fixed, C | Z | V | N), w65816::adc);
+    table.insert(0x80, Instruction::new(Bra, Relative, 2, 2, Branch, NONE), w65816::bra);
+    table.insert(0x81, Instruction::new(Sta, IndexedIndirect, 2, 6, Fixed, NONE), w65816::sta);
+    table.insert(0x82, Instruction::new(Brl, RelativeLong, 3, 4, Fixed, NONE), w65816::brl);
+    table.insert(0x83, Instruction::new(Sta, StackRelative, 2, 4, Fixed, NONE), w65816::sta);
+    table.insert(0x84, Instruction::new(Sty, ZeroPage, 2, 3, Fixed, NONE), w65816::sty);
+    table.insert(0x85, Instruction::new(Sta, ZeroPage, 2, 3, Fixed, NONE), w65816::sta);
+    table.insert(0x86, Instruction::new(Stx, ZeroPage, 2, 3, Fixed, NONE), w65816::stx);
+    table.insert(0x87, Instruction::new(Sta, IndirectLong, 2, 6, Fixed, NONE), w65816::sta);
+    table.insert(0x88, Instruction::new(Dey, Implied, 1, 2, Fixed, Z | N), w65816::dey);
+    table.insert(0x89, Instruction::new(Bit, ImmediateM, 2, 2, Fixed, Z | V | N), w65816::bit);
+    table.insert(0x8a, Instruction::new(Txa, Implied, 1, 2, Fixed, Z | N), w65816::txa);
+    table.insert(0x8b, Instruction::new(Phb, Implied, 1, 3, Fixed, NONE), w65816::phb);
+    table.insert(0x8c, Instruction::new(Sty, Absolute, 3, 4, Fixed, NONE), w65816::sty);
+    table.insert(0x8d, Instruction::new(Sta, Absolute, 3, 4, Fixed, NONE), w65816::sta);
+    table.insert(0x8e, Instruction::new(Stx, Absolute, 3, 4, Fixed, NONE), w65816::stx);
+    table.insert(0x8f, Instruction::new(Sta, AbsoluteLong, 4, 5, Fixed, NONE), w65816::sta);
+    table.insert(0x90, Instruction::new(Bcc, Relative, 2, 2, Branch, NONE), w65816::bcc);
+    table.insert(0x91, Instruction::new(Sta, IndirectIndexed, 2, 6, Fixed, NONE), w65816::sta);
+    table.insert(0x92, Instruction::new(Sta, ZeroPageIndirect, 2, 5, Fixed, NONE), w65816::sta);
+    table.insert(0x93, Instruction::new(Sta, StackRelativeIndirectY, 2, 7, Fixed, NONE), w65816::sta);
+    table.insert(0x94, Instruction::new(Sty, ZeroPageX, 2, 4, Fixed, NONE), w65816::sty);
+    table.insert(0x95, Instruction::new(Sta, ZeroPageX, 2, 4, Fixed, NONE), w65816::sta);
+    table.insert(0x96, Instruction::new(Stx, ZeroPageY, 2, 4, Fixed, NONE), w65816::stx);
+    table.insert(0x97, Instruction::new(Sta, IndirectLongY, 2, 6, Fixed, NONE), w65816::sta);
+    table.insert(0x98, Instruction::new(Tya, Implied, 1, 2, Fixed, Z | N), w65816::tya);
+    table.insert(0x99, Instruction::new(Sta, AbsoluteY, 3, 5, Fixed, NONE), w65816::sta);
+    table.insert(0x9a, Instruction::new(Txs, Implied, 1, 2, Fixed, NONE), w65816::txs);
+    table.insert(0x9b, Instruction::new(Txy, Implied, 1, 2, Fixed, Z | N), w65816::txy);
+    table.insert(0x9c, Instruction::new(Stz, Absolute, 3, 4, Fixed, NONE), w65816::stz);
+    table.insert(0x9d, Instruction::new(Sta, AbsoluteX, 3, 5, Fixed, NONE), w65816::sta);
+    table.insert(0x9e, Instruction::new(Stz, AbsoluteX, 3, 5, Fixed, NONE), w65816::stz);
+    table.insert(0x9f, Instruction::new(Sta, AbsoluteLongX, 4, 5, Fixed, NONE), w65816::sta);
+    table.insert(0xa0, Instruction::new(Ldy, ImmediateX, 2, 2, Fixed, Z | N), w65816::ldy);
+    table.insert(0xa1, Instruction::new(Lda, IndexedIndirect, 2, 6, Fixed, Z | N), w65816::lda);
+    table.insert(0xa2, Instruction::new(Ldx, ImmediateX, 2, 2, Fixed, Z | N), w65816::ldx);
+    table.insert(0xa3, Instruction::new(Lda, StackRelative, 2, 4, Fixed, Z | N), w65816::lda);
+    table.insert(0xa4, Instruction::new(Ldy, ZeroPage, 2, 3, Fixed, Z | N), w65816::ldy);
+    table.insert(0xa5, Instruction::new(Lda, ZeroPage, 2, 3, Fixed, Z | N), w65816::lda);
+    table.insert(0xa6, Instruction::new(Ldx, ZeroPage, 2, 3, Fixed, Z | N), w65816::ldx);
+    table.insert(0xa7, Instruction::new(Lda, IndirectLong, 2, 6, Fixed, Z | N), w65816::lda);
+    table.insert(0xa8, Instruction::new(Tay, Implied, 1, 2, Fixed, Z | N), w65816::tay);
+    table.insert(0xa9, Instruction::new(Lda, ImmediateM, 2, 2, Fixed, Z | N), w65816::lda);
+    table.insert(0xaa, Instruction::new(Tax, Implied, 1, 2, Fixed, Z | N), w65816::tax);
+    table.insert(0xab, Instruction::new(Plb, Implied, 1, 4, Fixed, Z | N), w65816::plb);
+    table.insert(0xac, Instruction::new(Ldy, Absolute, 3, 4, Fixed, Z | N), w65816::ldy);
+    table.insert(0xad, Instruction::new(Lda, Absolute, 3, 4, Fixed, Z | N), w65816::lda);
+    table.insert(0xae, Instruction::new(Ldx, Absolute, 3, 4, Fixed, Z | N), w65816::ldx);
+    table.insert(0xaf, Instruction::new(Lda, AbsoluteLong, 4, 5, Fixed, Z | N), w65816::lda);
+    table.insert(0xb0, Instruction::new(Bcs, Relative, 2, 2, Branch, NONE), w65816::bcs);
+    table.insert(0xb1, Instruction::new(Lda, IndirectIndexed, 2, 5, PageCross, Z | N), w65816::lda);
+    table.insert(0xb2, Instruction::new(Lda, ZeroPageIndirect, 2, 5, Fixed, Z | N), w65816::lda);
+    table.insert(0xb3, Instruction::new(Lda, StackRelativeIndirectY, 2, 7, Fixed, Z | N), w65816::lda);
+    table.insert(0xb4, Instruction::new(Ldy, ZeroPageX, 2, 4, Fixed, Z | N), w65816::ldy);
+    table.insert(0xb5, Instruction::new(Lda, ZeroPageX, 2, 4, Fixed, Z | N), w65816::lda);
+    table.insert(0xb6, Instruction::new(Ldx, ZeroPageY, 2, 4, Fixed, Z | N), w65816::ldx);
+    table.insert(0xb7, Instruction::new(Lda, IndirectLongY, 2, 6, Fixed, Z | N), w65816::lda);
+    table.insert(0xb8, Instruction::new(Clv, Implied, 1, 2, Fixed, V), clv);
+    table.insert(0xb9, Instruction::new(Lda, AbsoluteY, 3, 4, PageCross, Z | N), w65816::lda);
+    table.insert(0xba, Instruction::new(Tsx, Implied, 1, 2, Fixed, Z | N), w65816::tsx);
+    table.insert(0xbb, Instruction::new(Tyx, Implied, 1, 2, Fixed, Z | N), w65816::tyx);
+    table.insert(0xbc, Instruction::new(Ldy, AbsoluteX, 3, 4, PageCross, Z | N), w65816::ldy);
+    table.insert(0xbd, Instruction::new(Lda, AbsoluteX, 3, 4, PageCross, Z | N), w65816::lda);
+    table.insert(0xbe, Instruction::new(Ldx, AbsoluteY, 3, 4, PageCross, Z | N), w65816::ldx);
+    table.insert(0xbf, Instruction::new(Lda, AbsoluteLongX, 4, 5, Fixed, Z | N), w65816::lda);
+    table.insert(0xc0, Instruction::new(Cpy, ImmediateX, 2, 2, Fixed, C | Z | N), w65816::cpy);
+    table.insert(0xc1, Instruction::new(Cmp, IndexedIndirect, 2, 6, Fixed, C | Z | N), w65816::cmp);
+    table.insert(0xc2, Instruction::new(Rep, Immediate, 2, 3, Fixed, ALL), w65816::rep);
+    table.insert(0xc3, Instruction::new(Cmp, StackRelative, 2, 4, Fixed, C | Z | N), w65816::cmp);
+    table.insert(0xc4, Instruction::new(Cpy, ZeroPage, 2, 3, Fixed, C | Z | N), w65816::cpy);
+    table.insert(0xc5, Instruction::new(Cmp, ZeroPage, 2, 3, Fixed, C | Z | N), w65816::cmp);
+    table.insert(0xc6, Instruction::new(Dec, ZeroPage, 2, 5, Fixed, Z | N), w65816::dec);
+    table.insert(0xc7, Instruction::new(Cmp, IndirectLong, 2, 6, Fixed, C | Z | N), w65816::cmp);
+    table.insert(0xc8, Instruction::new(Iny, Implied, 1, 2, Fixed, Z | N), w65816::iny);
+    table.insert(0xc9, Instruction::new(Cmp, ImmediateM, 2, 2, Fixed, C | Z | N), w65816::cmp);
+    table.insert(0xca, Instruction::new(Dex, Implied, 1, 2, Fixed, Z | N), w65816::dex);
+    table.insert(0xcb, Instruction::new(Wai, Implied, 1, 3, Fixed, NONE), wai);
+    table.insert(0xcc, Instruction::new(Cpy, Absolute, 3, 4, Fixed, C | Z | N), w65816::cpy);
+    table.insert(0xcd, Instruction::new(Cmp, Absolute, 3, 4, Fixed, C | Z | N), w65816::cmp);
+    table.insert(0xce, Instruction::new(Dec, Absolute, 3, 6, Fixed, Z | N), w65816::dec);
+    table.insert(0xcf, Instruction::new(Cmp, AbsoluteLong, 4, 5, Fixed, C | Z | N), w65816::cmp);
+    table.insert(0xd0, Instruction::new(Bne, Relative, 2, 2, Branch, NONE), w65816::bne);
+    table.insert(0xd1, Instruction::new(Cmp, IndirectIndexed, 2, 5, PageCross, C | Z | N), w65816::cmp);
+    table.insert(0xd2, Instruction::new(Cmp, ZeroPageIndirect, 2, 5, Fixed, C | Z | N), w65816::cmp);
+    table.insert(0xd3, Instruction::new(Cmp, StackRelativeIndirectY, 2, 7, Fixed, C | Z | N), w65816::cmp);
+    table.insert(0xd4, Instruction::new(Pei, ZeroPageIndirect, 2, 6, Fixed, NONE), w65816::pei);
+    table.insert(0xd5, Instruction::new(Cmp, ZeroPageX, 2, 4, Fixed, C | Z | N), w65816::cmp);
+    table.insert(0xd6, Instruction::new(Dec, ZeroPageX, 2, 6, Fixed, Z | N), w65816::dec);
+    table.insert(0xd7, Instruction::new(Cmp, IndirectLongY, 2, 6, Fixed, C | Z | N), w65816::cmp);
+    table.insert(0xd8, Instruction::new(Cld, Implied, 1, 2, Fixed, D), cld);
+    table.insert(0xd9, Instruction::new(Cmp, AbsoluteY, 3, 4, PageCross, C | Z | N), w65816::cmp);
+    table.insert(0xda, Instruction::new(Phx, Implied, 1, 3, Fixed, NONE), w65816::phx);
+    table.insert(0xdb, Instruction::new(Stp, Implied, 1, 3, Fixed, NONE), stp);
+    table.insert(0xdc, Instruction::new(Jml, AbsoluteIndirectLong, 3, 6, Fixed, NONE), w65816::jml);
+    table.insert(0xdd, Instruction::new(Cmp, AbsoluteX, 3, 4, PageCross, C | Z | N), w65816::cmp);
+    table.insert(0xde, Instruction::new(Dec, AbsoluteX, 3, 7, Fixed, Z | N), w65816::dec);
+    table.insert(0xdf, Instruction::new(Cmp, AbsoluteLongX, 4, 5, Fixed, C | Z | N), w65816::cmp);
+    table.insert(0xe0, Instruction::new(Cpx, ImmediateX, 2, 2, Fixed, C | Z | N), w65816::cpx);
+    table.insert(0xe1, Instruction::new(Sbc, IndexedIndirect, 2, 6, Fixed, C | Z | V | N), w65816::sbc);
+    table.insert(0xe2, Instruction::new(Sep, Immediate, 2, 3, Fixed, ALL), w65816::sep);
+    table.insert(0xe3, Instruction::new(Sbc, StackRelative, 2, 4, Fixed, C | Z | V | N), w65816::sbc);
+    table.insert(0xe4, Instruction::new(Cpx, ZeroPage, 2, 3, Fixed, C | Z | N), w65816::cpx);
+    table.insert(0xe5, Instruction::new(Sbc, ZeroPage, 2, 3, Fixed, C | Z | V | N), w65816::sbc);
+    table.insert(0xe6, Instruction::new(Inc, ZeroPage, 2, 5, Fixed, Z | N), w65816::inc);
+    table.insert(0xe7, Instruction::new(Sbc, IndirectLong, 2, 6, Fixed, C | Z | V | N), w65816::sbc);
+    table.insert(0xe8, Instruction::new(Inx, Implied, 1, 2, Fixed, Z | N), w65816::inx);
+    table.insert(0xe9, Instruction::new(Sbc, ImmediateM, 2, 2, Fixed, C | Z | V | N), w65816::sbc);
+    table.insert(0xea, Instruction::new(Nop, Implied, 1, 2, Fixed, NONE), nop);
+    table.insert(0xeb, Instruction::new(Xba, Implied, 1, 3, Fixed, Z | N), w65816::xba);
+    table.insert(0xec, Instruction::new(Cpx, Absolute, 3, 4, Fixed, C | Z | N), w65816::cpx);
+    table.insert(0xed, Instruction::new(Sbc, Absolute, 3, 4, Fixed, C | Z | V | N), w65816::sbc);
+    table.insert(0xee, Instruction::new(Inc, Absolute, 3, 6, Fixed, Z | N), w65816::inc);
+    table.insert(0xef, Instruction::new(Sbc, AbsoluteLong, 4, 5, Fixed, C | Z | V | N), w65816::sbc);
+    table.insert(0xf0, Instruction::new(Beq, Relative, 2, 2, Branch, NONE), w65816::beq);
+    table.insert(0xf1, Instruction::new(Sbc, IndirectIndexed, 2, 5, PageCross, C | Z | V | N), w65816::sbc);
+    table.insert(0xf2, Instruction::new(Sbc, ZeroPageIndirect, 2, 5, Fixed, C | Z | V | N), w65816::sbc);
+    table.insert(0xf3, Instruction::new(Sbc, StackRelativeIndirectY, 2, 7, Fixed, C | Z | V | N), w65816::sbc);
+    table.insert(0xf4, Instruction::new(Pea, Absolute, 3, 5, Fixed, NONE), w65816::pea);
+    table.insert(0xf5, Instruction::new(Sbc, ZeroPageX, 2, 4, Fixed, C | Z | V | N), w65816::sbc);
+    table.insert(0xf6, Instruction::new(Inc, ZeroPageX, 2, 6, Fixed, Z | N), w65816::inc);
+    table.insert(0xf7, Instruction::new(Sbc, IndirectLongY, 2, 6, Fixed, C | Z | V | N), w65816::sbc);
+    table.insert(0xf8, Instruction::new(Sed, Implied, 1, 2, Fixed, D), sed);
+    table.insert(0xf9, Instruction::new(Sbc, AbsoluteY, 3, 4, PageCross, C | Z | V | N), w65816::sbc);
+    table.insert(0xfa, Instruction::new(Plx, Implied, 1, 4, Fixed, Z | N), w65816::plx);
+    table.insert(0xfb, Instruction::new(Xce, Implied, 1, 2, Fixed, C), w65816::xce);
+    table.insert(0xfc, Instruction::new(Jsr, AbsoluteIndexedIndirect, 3, 8, Fixed, NONE), w65816::jsr);
+    table.insert(0xfd, Instruction::new(Sbc, AbsoluteX, 3, 4, PageCross, C | Z | V | N), w65816::sbc);
+    table.insert(0xfe, Instruction::new(Inc, AbsoluteX, 3, 7, Fixed, Z | N), w65816::inc);
+    table.insert(0xff, Instruction::new(Sbc, AbsoluteLongX, 4, 5, Fixed, C | Z | V | N), w65816::sbc);
+
+    table
+}
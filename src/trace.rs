@@ -0,0 +1,264 @@
+use crate::{
+    error::EmulatorError,
+    instruction::{Instruction, Mnemonic, Mode},
+    status::Flag,
+    system::{run_step, Core, Event, Variant},
+    table::OpcodeTable,
+};
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+/*
+    Execution traces.
+
+    A trace is one line for every instruction run, with the state the core
+    was in just before it ran: PC, the instruction's bytes, its disassembly,
+    A, X, Y, P and SP, and the cycle count. Lined up against a log from a
+    known good emulator, the first line that differs is usually the bug.
+
+    `Nestest` is the layout of nestest.log, so a run of nestest.nes from
+    0xC000 can be diffed against it directly. That includes its notes on the
+    operands, the effective address and what's there before the instruction
+    runs, its `*` in front of undocumented opcodes, and the PPU column. There
+    is no PPU here, the dot and scanline are worked out from the cycle count
+    at three dots a cycle. P has bit 5 set, as it reads when pushed.
+
+    `JsonLines` is one JSON object per line with the same fields as numbers,
+    for anything that would rather not parse columns. Only the low bytes of
+    the 65816's registers go in either one, and its operands are left
+    without notes, as they'd need the direct page and the data bank.
+
+    Interrupt sequences aren't instructions, so they don't get a line. An
+    instruction that fails still does, as it's usually the one being looked
+    for.
+
+    A line that can't be written doesn't stop the step it's for, which has
+    already happened by then. The first write error is held on to and comes
+    back from `flush()`, or from `take_error()` for checking as it goes.
+*/
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TraceFormat {
+    #[default]
+    Nestest, // Columns as in nestest.log
+    JsonLines, // A JSON object per line
+}
+
+pub struct Tracer {
+    format: TraceFormat,
+    out: Box<dyn Write>,
+    stdout: bool, // Lines go to stdout, where the shell would draw over them
+    error: Option<EmulatorError>, // First line that couldn't be written, until it's taken
+}
+
+impl Tracer {
+    pub fn new(format: TraceFormat, out: Box<dyn Write>) -> Self {
+        Self { format, out, stdout: false, error: None }
+    }
+
+    pub fn stdout(format: TraceFormat) -> Self {
+        Self { stdout: true, ..Self::new(format, Box::new(io::stdout())) }
+    }
+
+    // Trace into a file, replacing whatever was in it.
+    pub fn file(format: TraceFormat, path: &str) -> Result<Self, EmulatorError> {
+        Ok(Self::new(format, Box::new(BufWriter::new(File::create(path)?))))
+    }
+
+    pub fn is_stdout(&self) -> bool {
+        self.stdout
+    }
+
+    // Runs a step, writing a line for it if it was an instruction.
+    pub(crate) fn step(&mut self, core: &mut Core, table: &OpcodeTable) -> Result<Event, EmulatorError> {
+        let line: String = self.line(core, table);
+        let result: Result<Event, EmulatorError> = run_step(core, table);
+
+        if matches!(result, Ok(Event::Instruction(_)) | Err(_)) {
+            if let Err(error) = writeln!(self.out, "{}", line) {
+                self.error.get_or_insert(error.into());
+            }
+        }
+
+        result
+    }
+
+    // The first line that couldn't be written, if there was one.
+    pub fn take_error(&mut self) -> Option<EmulatorError> {
+        self.error.take()
+    }
+
+    pub fn flush(&mut self) -> Result<(), EmulatorError> {
+        if let Some(error) = self.take_error() { return Err(error) }
+
+        Ok(self.out.flush()?)
+    }
+
+    // The line for the instruction at PC, looking at memory without reading it.
+    pub fn line(&self, core: &Core, table: &OpcodeTable) -> String {
+        let pc: u16 = core.pc;
+        let opcode: u8 = peek_program(core, pc);
+        let info: Option<Instruction> = table.get_instruction(opcode);
+
+        let bytes: Vec<u8> = (0..info.map_or(1, |info| length(core, info)))
+            .map(|offset| peek_program(core, pc.wrapping_add(offset)))
+            .collect();
+
+        let disassembly: String = match info {
+            Some(info) if self.format == TraceFormat::Nestest => {
+                format!("{}{}", nestest_mnemonic(info.mnemonic), operand(core, info, &bytes))
+            }
+            Some(info) => format!("{}{}", info.mnemonic, operand(core, info, &bytes)),
+            None => "???".to_string(),
+        };
+
+        // B only exists on the stack, except in 65816 native mode where it's X.
+        let p: u8 = if core.emulation { core.stat.pushed(false) } else { core.stat.bits() };
+
+        match self.format {
+            TraceFormat::Nestest => {
+                let hex: Vec<String> = bytes.iter().map(|byte| format!("{:02X}", byte)).collect();
+                let undocumented: bool = info.is_some_and(|info| is_undocumented(opcode, info.mnemonic));
+                let dots: u64 = core.cycles * 3;
+
+                format!(
+                    "{:04X}  {:<8} {}{:<31} A:{:02X} X:{:02X} Y:{:02X} P:{:02X} SP:{:02X} PPU:{:>3},{:>3} CYC:{}",
+                    pc, hex.join(" "), if undocumented { '*' } else { ' ' }, disassembly,
+                    core.acc, core.ix, core.iy, p, core.sp, (dots / 341) % 262, dots % 341, core.cycles,
+                )
+            }
+            TraceFormat::JsonLines => format!(
+                "{{\"pc\":{},\"bytes\":{:?},\"disassembly\":{:?},\"a\":{},\"x\":{},\"y\":{},\"p\":{},\"sp\":{},\"cycles\":{}}}",
+                pc, bytes, disassembly, core.acc, core.ix, core.iy, p, core.sp, core.cycles,
+            ),
+        }
+    }
+}
+
+// A byte from the program bank.
+fn peek_program(core: &Core, address: u16) -> u8 {
+    match core.pbr {
+        0 => core.peek(address),
+        bank => core.extended.get(((bank as usize) << 16 | address as usize) - 0x10000).copied().unwrap_or(0),
+    }
+}
+
+// IMMM and IMMX immediates are a byte longer with 16-bit registers.
+fn length(core: &Core, info: Instruction) -> u16 {
+    let wide: bool = !core.emulation && match info.mode {
+        Mode::ImmediateM => !core.stat.get(Flag::Memory),
+        Mode::ImmediateX => !core.stat.get(Flag::Index),
+        _ => false,
+    };
+
+    info.length as u16 + wide as u16
+}
+
+fn is_undocumented(opcode: u8, mnemonic: Mnemonic) -> bool {
+    use Mnemonic::*;
+
+    matches!(
+        mnemonic,
+        Alr | Anc | Ane | Arr | Dcp | Isc | Jam | Las | Lax | Lxa | Rla | Rra |
+        Sax | Sbx | Sha | Shx | Shy | Slo | Sre | Tas | Usbc
+    ) || (mnemonic == Nop && opcode != 0xEA)
+}
+
+// The names nestest.log uses where they aren't the ones here.
+fn nestest_mnemonic(mnemonic: Mnemonic) -> String {
+    match mnemonic {
+        Mnemonic::Isc => "ISB".to_string(),
+        Mnemonic::Usbc => "SBC".to_string(),
+        _ => mnemonic.to_string(),
+    }
+}
+
+fn word(low: u8, high: u8) -> u16 {
+    ((high as u16) << 8) | low as u16
+}
+
+// Little endian word at an address, wrapping within its page like the zero
+// page pointers and the NMOS JMP (ind) do.
+fn peek_word_in_page(core: &Core, address: u16) -> u16 {
+    word(core.peek(address), core.peek((address & 0xFF00) | (address as u8).wrapping_add(1) as u16))
+}
+
+// The operand as written, with what it points at before the instruction runs.
+fn operand(core: &Core, info: Instruction, bytes: &[u8]) -> String {
+    let byte: u8 = bytes.get(1).copied().unwrap_or(0);
+    let absolute: u16 = word(byte, bytes.get(2).copied().unwrap_or(0));
+    let target: u16 = info.branch_target(core.pc, bytes).unwrap_or(0);
+    let notes: bool = core.variant != Variant::W65816;
+
+    match info.mode {
+        Mode::Implied => String::new(),
+        Mode::Accumulator => " A".to_string(),
+        Mode::Immediate => format!(" #${:02X}", byte),
+        Mode::ImmediateM | Mode::ImmediateX if bytes.len() == 3 => format!(" #${:04X}", absolute),
+        Mode::ImmediateM | Mode::ImmediateX => format!(" #${:02X}", byte),
+        Mode::ZeroPage if notes => format!(" ${:02X} = {:02X}", byte, core.peek(byte as u16)),
+        Mode::ZeroPage => format!(" ${:02X}", byte),
+        Mode::ZeroPageX | Mode::ZeroPageY if notes => {
+            let (name, index) = if info.mode == Mode::ZeroPageX { ('X', core.ix) } else { ('Y', core.iy) };
+            let address: u8 = byte.wrapping_add(index);
+
+            format!(" ${:02X},{} @ {:02X} = {:02X}", byte, name, address, core.peek(address as u16))
+        }
+        Mode::ZeroPageX => format!(" ${:02X},X", byte),
+        Mode::ZeroPageY => format!(" ${:02X},Y", byte),
+        Mode::Absolute if !notes || matches!(info.mnemonic, Mnemonic::Jmp | Mnemonic::Jsr) => format!(" ${:04X}", absolute),
+        Mode::Absolute => format!(" ${:04X} = {:02X}", absolute, core.peek(absolute)),
+        Mode::AbsoluteX | Mode::AbsoluteY if notes => {
+            let (name, index) = if info.mode == Mode::AbsoluteX { ('X', core.ix) } else { ('Y', core.iy) };
+            let address: u16 = absolute.wrapping_add(index as u16);
+
+            format!(" ${:04X},{} @ {:04X} = {:02X}", absolute, name, address, core.peek(address))
+        }
+        Mode::AbsoluteX => format!(" ${:04X},X", absolute),
+        Mode::AbsoluteY => format!(" ${:04X},Y", absolute),
+        Mode::Indirect if notes => {
+            let target: u16 = if core.variant.is_cmos() {
+                word(core.peek(absolute), core.peek(absolute.wrapping_add(1)))
+            } else {
+                peek_word_in_page(core, absolute)
+            };
+
+            format!(" (${:04X}) = {:04X}", absolute, target)
+        }
+        Mode::Indirect => format!(" (${:04X})", absolute),
+        Mode::AbsoluteIndexedIndirect => format!(" (${:04X},X)", absolute),
+        Mode::IndexedIndirect if notes => {
+            let zp: u8 = byte.wrapping_add(core.ix);
+            let address: u16 = peek_word_in_page(core, zp as u16);
+
+            format!(" (${:02X},X) @ {:02X} = {:04X} = {:02X}", byte, zp, address, core.peek(address))
+        }
+        Mode::IndexedIndirect => format!(" (${:02X},X)", byte),
+        Mode::IndirectIndexed if notes => {
+            let pointer: u16 = peek_word_in_page(core, byte as u16);
+            let address: u16 = pointer.wrapping_add(core.iy as u16);
+
+            format!(" (${:02X}),Y = {:04X} @ {:04X} = {:02X}", byte, pointer, address, core.peek(address))
+        }
+        Mode::IndirectIndexed => format!(" (${:02X}),Y", byte),
+        Mode::ZeroPageIndirect if notes => {
+            let address: u16 = peek_word_in_page(core, byte as u16);
+
+            format!(" (${:02X}) = {:04X} = {:02X}", byte, address, core.peek(address))
+        }
+        Mode::ZeroPageIndirect => format!(" (${:02X})", byte),
+        Mode::ZeroPageRelative => format!(" ${:02X},${:04X}", byte, target),
+        Mode::Relative | Mode::RelativeLong => format!(" ${:04X}", target),
+        Mode::IndirectLong => format!(" [${:02X}]", byte),
+        Mode::IndirectLongY => format!(" [${:02X}],Y", byte),
+        Mode::StackRelative => format!(" ${:02X},S", byte),
+        Mode::StackRelativeIndirectY => format!(" (${:02X},S),Y", byte),
+        Mode::AbsoluteLong | Mode::AbsoluteLongX => format!(
+            " ${:02X}{:04X}{}",
+            bytes.get(3).copied().unwrap_or(0), absolute, if info.mode == Mode::AbsoluteLongX { ",X" } else { "" },
+        ),
+        Mode::AbsoluteIndirectLong => format!(" [${:04X}]", absolute),
+        Mode::BlockMove => format!(" ${:02X},${:02X}", bytes.get(2).copied().unwrap_or(0), byte),
+    }
+}
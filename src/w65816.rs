@@ -0,0 +1,1468 @@
+use crate::{status::{Flag, Status}, system::Core};
+
+/*
+    WDC 65C816 support.
+
+    The 65816 runs on the same Core as the 6502 variants. Its extra state sits
+    alongside the 8-bit registers: `acc_hi` is the B half of the 16-bit
+    accumulator, `ix_hi`, `iy_hi` and `sp_hi` extend the index registers and
+    the stack pointer, and `dp`, `dbr` and `pbr` are the direct page, data bank
+    and program bank registers.
+
+    In emulation mode (E=1) every width is pinned to 8 bits and the stack stays
+    in page one, so 6502 code runs as it would on a 65C02. In native mode status
+    bits 5 and 4 become M and X. When set, the accumulator and memory (M) or the
+    index registers (X) are 8 bits wide, otherwise they are 16.
+
+    The opcodes here work out their addressing mode from the opcode like the
+    ones in opcodes.rs, but every mode resolves to a 24-bit address first.
+    Immediate operands are just the address of the byte after the opcode, which
+    lets reads be shared between all the modes.
+*/
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mode {
+    ImmediateM, // #const, width follows M
+    ImmediateX, // #const, width follows X
+    Direct, // dp
+    DirectX, // dp,X
+    DirectY, // dp,Y
+    DirectIndirect, // (dp)
+    DirectXIndirect, // (dp,X)
+    DirectIndirectY, // (dp),Y
+    DirectIndirectLong, // [dp]
+    DirectIndirectLongY, // [dp],Y
+    Absolute, // abs
+    AbsoluteX, // abs,X
+    AbsoluteY, // abs,Y
+    Long, // long
+    LongX, // long,X
+    StackRelative, // sr,S
+    StackRelativeIndirectY, // (sr,S),Y
+}
+
+// True when the accumulator and memory accesses are 8 bits wide.
+fn m8(core: &Core) -> bool {
+    core.emulation || core.stat.get(Flag::Memory)
+}
+
+// True when the index registers are 8 bits wide.
+fn x8(core: &Core) -> bool {
+    core.emulation || core.stat.get(Flag::Index)
+}
+
+fn mask(wide: bool) -> u16 {
+    if wide { 0xFFFF } else { 0x00FF }
+}
+
+fn sign(wide: bool) -> u16 {
+    if wide { 0x8000 } else { 0x0080 }
+}
+
+// Sets the zero and negative flags for a result of the given width.
+fn zero_negative(core: &mut Core, value: u16, wide: bool) {
+    core.stat.set(Flag::Zero, value & mask(wide) == 0);
+    core.stat.set(Flag::Negative, value & sign(wide) != 0);
+}
+
+// Loads the status register. M and X only exist in native mode, and the
+// index high bytes are dropped when X is set.
+fn set_status(core: &mut Core, value: u8) {
+    if core.emulation {
+        core.stat = Status::pulled(value);
+    } else {
+        core.stat = Status::from_bits(value);
+    }
+
+    if x8(core) {
+        core.ix_hi = 0;
+        core.iy_hi = 0;
+    }
+}
+
+// The accumulator at the current M width.
+fn get_a(core: &Core) -> u16 {
+    if m8(core) { core.acc as u16 } else { get_c(core) }
+}
+
+// Writes the accumulator at the current M width. B is kept when M is set.
+fn set_a(core: &mut Core, value: u16) {
+    core.acc = value as u8;
+
+    if !m8(core) { core.acc_hi = (value >> 8) as u8 }
+}
+
+// The full 16-bit accumulator, regardless of M.
+fn get_c(core: &Core) -> u16 {
+    ((core.acc_hi as u16) << 8) | core.acc as u16
+}
+
+fn set_c(core: &mut Core, value: u16) {
+    core.acc = value as u8;
+    core.acc_hi = (value >> 8) as u8;
+}
+
+fn get_x(core: &Core) -> u16 {
+    ((core.ix_hi as u16) << 8) | core.ix as u16
+}
+
+// The high byte of an index register is always zero while X is set.
+fn set_x(core: &mut Core, value: u16) {
+    core.ix = value as u8;
+    core.ix_hi = if x8(core) { 0 } else { (value >> 8) as u8 };
+}
+
+fn get_y(core: &Core) -> u16 {
+    ((core.iy_hi as u16) << 8) | core.iy as u16
+}
+
+fn set_y(core: &mut Core, value: u16) {
+    core.iy = value as u8;
+    core.iy_hi = if x8(core) { 0 } else { (value >> 8) as u8 };
+}
+
+fn get_s(core: &Core) -> u16 {
+    ((core.sp_hi as u16) << 8) | core.sp as u16
+}
+
+// The stack is held in page one in emulation mode.
+fn set_s(core: &mut Core, value: u16) {
+    core.sp = value as u8;
+    core.sp_hi = if core.emulation { 0x01 } else { (value >> 8) as u8 };
+}
+
+// Address of a byte following the opcode. PC wraps within the program bank.
+fn program_address(core: &Core, offset: u16) -> u32 {
+    ((core.pbr as u32) << 16) | core.pc.wrapping_add(offset) as u32
+}
+
+fn operand8(core: &mut Core, offset: u16) -> u8 {
+    core.read_long(program_address(core, offset))
+}
+
+fn operand16(core: &mut Core) -> u16 {
+    ((operand8(core, 2) as u16) << 8) | operand8(core, 1) as u16
+}
+
+fn operand24(core: &mut Core) -> u32 {
+    ((operand8(core, 3) as u32) << 16) | operand16(core) as u32
+}
+
+fn read16(core: &mut Core, address: u32) -> u16 {
+    let low: u8 = core.read_long(address);
+    let high: u8 = core.read_long((address + 1) & 0xFFFFFF);
+
+    ((high as u16) << 8) | low as u16
+}
+
+// Pointers in the direct page and the stack are always in bank zero.
+fn read16_bank0(core: &mut Core, address: u16) -> u16 {
+    let low: u8 = core.read_long(address as u32);
+    let high: u8 = core.read_long(address.wrapping_add(1) as u32);
+
+    ((high as u16) << 8) | low as u16
+}
+
+fn read24_bank0(core: &mut Core, address: u16) -> u32 {
+    let bank: u8 = core.read_long(address.wrapping_add(2) as u32);
+
+    ((bank as u32) << 16) | read16_bank0(core, address) as u32
+}
+
+// Reads data at the given width. The second byte of a 16-bit access costs a cycle.
+fn read_data(core: &mut Core, address: u32, wide: bool) -> u16 {
+    if wide {
+        core.cycles += 1;
+        read16(core, address)
+    } else {
+        core.read_long(address) as u16
+    }
+}
+
+fn write_data(core: &mut Core, address: u32, value: u16, wide: bool) {
+    core.write_long(address, value as u8);
+
+    if wide {
+        core.cycles += 1;
+        core.write_long((address + 1) & 0xFFFFFF, (value >> 8) as u8);
+    }
+}
+
+// Emulation mode has the 8-bit stack in page one, see stack.rs.
+fn push8(core: &mut Core, value: u8) {
+    if core.emulation { return core.push(value) }
+
+    let s: u16 = get_s(core);
+
+    core.write_long(s as u32, value);
+    set_s(core, s.wrapping_sub(1));
+}
+
+fn push16(core: &mut Core, value: u16) {
+    push8(core, (value >> 8) as u8);
+    push8(core, value as u8);
+}
+
+fn pull8(core: &mut Core) -> u8 {
+    if core.emulation { return core.pull() }
+
+    let s: u16 = get_s(core).wrapping_add(1);
+
+    set_s(core, s);
+    core.read_long(get_s(core) as u32)
+}
+
+fn pull16(core: &mut Core) -> u16 {
+    let low: u8 = pull8(core);
+    let high: u8 = pull8(core);
+
+    ((high as u16) << 8) | low as u16
+}
+
+// Pushes a register at the given width, with the extra cycle for 16 bits.
+fn push_data(core: &mut Core, value: u16, wide: bool) {
+    if wide {
+        core.cycles += 1;
+        push16(core, value);
+    } else {
+        push8(core, value as u8);
+    }
+}
+
+fn pull_data(core: &mut Core, wide: bool) -> u16 {
+    if wide {
+        core.cycles += 1;
+        pull16(core)
+    } else {
+        pull8(core) as u16
+    }
+}
+
+// Direct page address for the operand plus an index, always in bank zero.
+// A direct page that isn't page aligned costs an extra cycle.
+fn direct(core: &mut Core, index: u16) -> u32 {
+    let operand: u8 = operand8(core, 1);
+
+    if core.dp & 0x00FF != 0 { core.cycles += 1 }
+
+    if core.emulation && core.dp & 0x00FF == 0 {
+        // Emulation mode keeps the 6502 zero page wrap.
+        ((core.dp & 0xFF00) | operand.wrapping_add(index as u8) as u16) as u32
+    } else {
+        core.dp.wrapping_add(operand as u16).wrapping_add(index) as u32
+    }
+}
+
+// Indexing a 24-bit base address. Indexing can carry into the next bank.
+// Reads take an extra cycle when a page is crossed or the index is 16 bits.
+fn indexed(core: &mut Core, base: u32, index: u16) -> u32 {
+    let address: u32 = (base + index as u32) & 0xFFFFFF;
+
+    core.page_crossed = (base & 0xFFFF00) != (address & 0xFFFF00) || !x8(core);
+
+    address
+}
+
+// Effective 24-bit address for an addressing mode.
+fn address(core: &mut Core, mode: Mode) -> u32 {
+    let bank: u32 = (core.dbr as u32) << 16;
+    let x: u16 = get_x(core);
+    let y: u16 = get_y(core);
+
+    match mode {
+        Mode::ImmediateM | Mode::ImmediateX => program_address(core, 1),
+        Mode::Direct => direct(core, 0),
+        Mode::DirectX => direct(core, x),
+        Mode::DirectY => direct(core, y),
+        Mode::DirectIndirect => {
+            let pointer: u32 = direct(core, 0);
+
+            bank | read16_bank0(core, pointer as u16) as u32
+        }
+        Mode::DirectXIndirect => {
+            let pointer: u32 = direct(core, x);
+
+            bank | read16_bank0(core, pointer as u16) as u32
+        }
+        Mode::DirectIndirectY => {
+            let pointer: u32 = direct(core, 0);
+            let base: u32 = bank | read16_bank0(core, pointer as u16) as u32;
+
+            indexed(core, base, y)
+        }
+        Mode::DirectIndirectLong => {
+            let pointer: u32 = direct(core, 0);
+
+            read24_bank0(core, pointer as u16)
+        }
+        Mode::DirectIndirectLongY => {
+            let pointer: u32 = direct(core, 0);
+
+            (read24_bank0(core, pointer as u16) + y as u32) & 0xFFFFFF
+        }
+        Mode::Absolute => bank | operand16(core) as u32,
+        Mode::AbsoluteX => {
+            let base: u32 = bank | operand16(core) as u32;
+
+            indexed(core, base, x)
+        }
+        Mode::AbsoluteY => {
+            let base: u32 = bank | operand16(core) as u32;
+
+            indexed(core, base, y)
+        }
+        Mode::Long => operand24(core),
+        Mode::LongX => (operand24(core) + x as u32) & 0xFFFFFF,
+        Mode::StackRelative => get_s(core).wrapping_add(operand8(core, 1) as u16) as u32,
+        Mode::StackRelativeIndirectY => {
+            let pointer: u16 = get_s(core).wrapping_add(operand8(core, 1) as u16);
+            let base: u32 = bank | read16_bank0(core, pointer) as u32;
+
+            (base + y as u32) & 0xFFFFFF
+        }
+    }
+}
+
+// Instruction length for an addressing mode. Immediates grow with M or X.
+fn length(core: &Core, mode: Mode) -> u16 {
+    match mode {
+        Mode::ImmediateM => if m8(core) { 2 } else { 3 },
+        Mode::ImmediateX => if x8(core) { 2 } else { 3 },
+        Mode::Absolute | Mode::AbsoluteX | Mode::AbsoluteY => 3,
+        Mode::Long | Mode::LongX => 4,
+        _ => 2,
+    }
+}
+
+fn skip(core: &mut Core, bytes: u16) -> &mut Core {
+    core.pc = core.pc.wrapping_add(bytes);
+
+    core
+}
+
+fn next(core: &mut Core, mode: Mode) -> &mut Core {
+    let bytes: u16 = length(core, mode);
+
+    skip(core, bytes)
+}
+
+// ORA, AND, EOR, ADC, STA, LDA, CMP and SBC share one layout of addressing
+// modes, picked out by the low five bits of the opcode.
+fn group_one_mode(core: &Core) -> Mode {
+    match core.ir & 0x1F {
+        0x01 => Mode::DirectXIndirect,
+        0x03 => Mode::StackRelative,
+        0x05 => Mode::Direct,
+        0x07 => Mode::DirectIndirectLong,
+        0x09 => Mode::ImmediateM,
+        0x0D => Mode::Absolute,
+        0x0F => Mode::Long,
+        0x11 => Mode::DirectIndirectY,
+        0x12 => Mode::DirectIndirect,
+        0x13 => Mode::StackRelativeIndirectY,
+        0x15 => Mode::DirectX,
+        0x17 => Mode::DirectIndirectLongY,
+        0x19 => Mode::AbsoluteY,
+        0x1D => Mode::AbsoluteX,
+        0x1F => Mode::LongX,
+        _ => unreachable!("{:?}", core.info)
+    }
+}
+
+// Reads the operand of a group one instruction at the M width.
+fn load_m(core: &mut Core) -> (u16, Mode) {
+    let mode: Mode = group_one_mode(core);
+    let address: u32 = address(core, mode);
+    let wide: bool = !m8(core);
+
+    (read_data(core, address, wide), mode)
+}
+
+pub fn ora(core: &mut Core) -> &mut Core {
+    let (value, mode) = load_m(core);
+    let result: u16 = get_a(core) | value;
+
+    set_a(core, result);
+    zero_negative(core, result, !m8(core));
+
+    next(core, mode)
+}
+
+pub fn and(core: &mut Core) -> &mut Core {
+    let (value, mode) = load_m(core);
+    let result: u16 = get_a(core) & value;
+
+    set_a(core, result);
+    zero_negative(core, result, !m8(core));
+
+    next(core, mode)
+}
+
+pub fn eor(core: &mut Core) -> &mut Core {
+    let (value, mode) = load_m(core);
+    let result: u16 = get_a(core) ^ value;
+
+    set_a(core, result);
+    zero_negative(core, result, !m8(core));
+
+    next(core, mode)
+}
+
+pub fn lda(core: &mut Core) -> &mut Core {
+    let (value, mode) = load_m(core);
+
+    set_a(core, value);
+    zero_negative(core, value, !m8(core));
+
+    next(core, mode)
+}
+
+pub fn sta(core: &mut Core) -> &mut Core {
+    let mode: Mode = group_one_mode(core);
+    let address: u32 = address(core, mode);
+    let wide: bool = !m8(core);
+    let value: u16 = get_a(core);
+
+    write_data(core, address, value, wide);
+
+    next(core, mode)
+}
+
+pub fn adc(core: &mut Core) -> &mut Core {
+    let (value, mode) = load_m(core);
+
+    add_with_carry(core, value);
+
+    next(core, mode)
+}
+
+pub fn sbc(core: &mut Core) -> &mut Core {
+    let (value, mode) = load_m(core);
+
+    subtract_with_borrow(core, value);
+
+    next(core, mode)
+}
+
+pub fn cmp(core: &mut Core) -> &mut Core {
+    let (value, mode) = load_m(core);
+    let a: u16 = get_a(core);
+
+    compare(core, a, value, !m8(core));
+
+    next(core, mode)
+}
+
+// Binary or decimal addition at the M width. Decimal mode works a digit at
+// a time, so 16-bit BCD carries through all four digits.
+fn add_with_carry(core: &mut Core, value: u16) {
+    let wide: bool = !m8(core);
+    let limit: u32 = mask(wide) as u32;
+    let a: u32 = get_a(core) as u32;
+    let value: u32 = value as u32 & limit;
+    let mut carry: u32 = core.stat.carry() as u32;
+
+    let result: u32 = if core.stat.get(Flag::Decimal) {
+        let digits: u32 = if wide { 4 } else { 2 };
+        let mut result: u32 = 0;
+
+        for digit in 0..digits {
+            let shift: u32 = digit * 4;
+            let mut sum: u32 = ((a >> shift) & 0xF) + ((value >> shift) & 0xF) + carry;
+
+            if sum > 9 { sum += 6 }
+            carry = (sum > 0xF) as u32;
+
+            result |= (sum & 0xF) << shift;
+        }
+
+        result | (carry << (digits * 4))
+    } else {
+        a + value + carry
+    };
+
+    core.stat.set(Flag::Overflow, (!(a ^ value) & (a ^ result) & sign(wide) as u32) != 0);
+    core.stat.set(Flag::Carry, result > limit);
+
+    set_a(core, result as u16);
+    zero_negative(core, result as u16, wide);
+}
+
+// Binary or decimal subtraction at the M width. The carry is the inverted borrow.
+fn subtract_with_borrow(core: &mut Core, value: u16) {
+    let wide: bool = !m8(core);
+    let limit: u32 = mask(wide) as u32;
+    let a: u32 = get_a(core) as u32;
+    let value: u32 = value as u32 & limit;
+    let carry: u32 = core.stat.carry() as u32;
+
+    let binary: u32 = a + (!value & limit) + carry;
+
+    let result: u32 = if core.stat.get(Flag::Decimal) {
+        let digits: u32 = if wide { 4 } else { 2 };
+        let mut borrow: i32 = 1 - carry as i32;
+        let mut result: u32 = 0;
+
+        for digit in 0..digits {
+            let shift: u32 = digit * 4;
+            let mut difference: i32 = ((a >> shift) & 0xF) as i32 - ((value >> shift) & 0xF) as i32 - borrow;
+
+            borrow = (difference < 0) as i32;
+            if difference < 0 { difference += 10 }
+
+            result |= (difference as u32 & 0xF) << shift;
+        }
+
+        result
+    } else {
+        binary & limit
+    };
+
+    core.stat.set(Flag::Overflow, ((a ^ value) & (a ^ binary) & sign(wide) as u32) != 0);
+    core.stat.set(Flag::Carry, binary > limit);
+
+    set_a(core, result as u16);
+    zero_negative(core, result as u16, wide);
+}
+
+fn compare(core: &mut Core, register: u16, value: u16, wide: bool) {
+    let register: u16 = register & mask(wide);
+    let value: u16 = value & mask(wide);
+
+    core.stat.set(Flag::Carry, register >= value);
+    zero_negative(core, register.wrapping_sub(value), wide);
+}
+
+// Addressing modes for the shifts, INC, DEC, TSB and TRB. None is the accumulator.
+fn modify_mode(core: &Core) -> Option<Mode> {
+    match core.ir & 0x1F {
+        0x0A | 0x1A => None,
+        0x04 | 0x06 | 0x14 => Some(Mode::Direct),
+        0x0C | 0x0E | 0x1C => Some(Mode::Absolute),
+        0x16 => Some(Mode::DirectX),
+        0x1E => Some(Mode::AbsoluteX),
+        _ => unreachable!("{:?}", core.info)
+    }
+}
+
+// Read-modify-write on the accumulator or memory at the M width.
+fn modify(core: &mut Core, operation: fn(&mut Core, u16, bool) -> u16) -> &mut Core {
+    let wide: bool = !m8(core);
+
+    match modify_mode(core) {
+        None => {
+            let value: u16 = get_a(core);
+            let result: u16 = operation(core, value, wide);
+
+            set_a(core, result);
+
+            skip(core, 1)
+        }
+        Some(mode) => {
+            let address: u32 = address(core, mode);
+            let value: u16 = read_data(core, address, wide);
+            let result: u16 = operation(core, value, wide);
+
+            write_data(core, address, result, wide);
+
+            next(core, mode)
+        }
+    }
+}
+
+pub fn asl(core: &mut Core) -> &mut Core {
+    modify(core, |core, value, wide| {
+        let result: u16 = (value << 1) & mask(wide);
+
+        core.stat.set(Flag::Carry, value & sign(wide) != 0);
+        zero_negative(core, result, wide);
+
+        result
+    })
+}
+
+pub fn lsr(core: &mut Core) -> &mut Core {
+    modify(core, |core, value, wide| {
+        let result: u16 = (value & mask(wide)) >> 1;
+
+        core.stat.set(Flag::Carry, value & 1 != 0);
+        zero_negative(core, result, wide);
+
+        result
+    })
+}
+
+pub fn rol(core: &mut Core) -> &mut Core {
+    modify(core, |core, value, wide| {
+        let carry: u16 = core.stat.carry() as u16;
+        let result: u16 = ((value << 1) | carry) & mask(wide);
+
+        core.stat.set(Flag::Carry, value & sign(wide) != 0);
+        zero_negative(core, result, wide);
+
+        result
+    })
+}
+
+pub fn ror(core: &mut Core) -> &mut Core {
+    modify(core, |core, value, wide| {
+        let carry: u16 = if core.stat.get(Flag::Carry) { sign(wide) } else { 0 };
+        let result: u16 = ((value & mask(wide)) >> 1) | carry;
+
+        core.stat.set(Flag::Carry, value & 1 != 0);
+        zero_negative(core, result, wide);
+
+        result
+    })
+}
+
+pub fn inc(core: &mut Core) -> &mut Core {
+    modify(core, |core, value, wide| {
+        let result: u16 = value.wrapping_add(1) & mask(wide);
+
+        zero_negative(core, result, wide);
+
+        result
+    })
+}
+
+pub fn dec(core: &mut Core) -> &mut Core {
+    modify(core, |core, value, wide| {
+        let result: u16 = value.wrapping_sub(1) & mask(wide);
+
+        zero_negative(core, result, wide);
+
+        result
+    })
+}
+
+pub fn tsb(core: &mut Core) -> &mut Core {
+    modify(core, |core, value, wide| {
+        let a: u16 = get_a(core);
+
+        core.stat.set(Flag::Zero, a & value & mask(wide) == 0);
+
+        value | a
+    })
+}
+
+pub fn trb(core: &mut Core) -> &mut Core {
+    modify(core, |core, value, wide| {
+        let a: u16 = get_a(core);
+
+        core.stat.set(Flag::Zero, a & value & mask(wide) == 0);
+
+        value & !a
+    })
+}
+
+pub fn bit(core: &mut Core) -> &mut Core {
+    let mode: Mode = match core.ir {
+        0x89 => Mode::ImmediateM,
+        0x24 => Mode::Direct,
+        0x2C => Mode::Absolute,
+        0x34 => Mode::DirectX,
+        0x3C => Mode::AbsoluteX,
+        _ => unreachable!("{:?}", core.info)
+    };
+
+    let address: u32 = address(core, mode);
+    let wide: bool = !m8(core);
+    let value: u16 = read_data(core, address, wide);
+    let a: u16 = get_a(core);
+
+    core.stat.set(Flag::Zero, a & value == 0);
+
+    // BIT #imm only touches the zero flag.
+    if mode != Mode::ImmediateM {
+        core.stat.set(Flag::Negative, value & sign(wide) != 0);
+        core.stat.set(Flag::Overflow, value & (sign(wide) >> 1) != 0);
+    }
+
+    next(core, mode)
+}
+
+pub fn stz(core: &mut Core) -> &mut Core {
+    let mode: Mode = match core.ir {
+        0x64 => Mode::Direct,
+        0x74 => Mode::DirectX,
+        0x9C => Mode::Absolute,
+        0x9E => Mode::AbsoluteX,
+        _ => unreachable!("{:?}", core.info)
+    };
+
+    let address: u32 = address(core, mode);
+    let wide: bool = !m8(core);
+
+    write_data(core, address, 0, wide);
+
+    next(core, mode)
+}
+
+pub fn ldx(core: &mut Core) -> &mut Core {
+    let mode: Mode = match core.ir {
+        0xA2 => Mode::ImmediateX,
+        0xA6 => Mode::Direct,
+        0xAE => Mode::Absolute,
+        0xB6 => Mode::DirectY,
+        0xBE => Mode::AbsoluteY,
+        _ => unreachable!("{:?}", core.info)
+    };
+
+    let address: u32 = address(core, mode);
+    let wide: bool = !x8(core);
+    let value: u16 = read_data(core, address, wide);
+
+    set_x(core, value);
+    zero_negative(core, value, wide);
+
+    next(core, mode)
+}
+
+pub fn ldy(core: &mut Core) -> &mut Core {
+    let mode: Mode = match core.ir {
+        0xA0 => Mode::ImmediateX,
+        0xA4 => Mode::Direct,
+        0xAC => Mode::Absolute,
+        0xB4 => Mode::DirectX,
+        0xBC => Mode::AbsoluteX,
+        _ => unreachable!("{:?}", core.info)
+    };
+
+    let address: u32 = address(core, mode);
+    let wide: bool = !x8(core);
+    let value: u16 = read_data(core, address, wide);
+
+    set_y(core, value);
+    zero_negative(core, value, wide);
+
+    next(core, mode)
+}
+
+pub fn stx(core: &mut Core) -> &mut Core {
+    let mode: Mode = match core.ir {
+        0x86 => Mode::Direct,
+        0x8E => Mode::Absolute,
+        0x96 => Mode::DirectY,
+        _ => unreachable!("{:?}", core.info)
+    };
+
+    let address: u32 = address(core, mode);
+    let wide: bool = !x8(core);
+    let value: u16 = get_x(core);
+
+    write_data(core, address, value, wide);
+
+    next(core, mode)
+}
+
+pub fn sty(core: &mut Core) -> &mut Core {
+    let mode: Mode = match core.ir {
+        0x84 => Mode::Direct,
+        0x8C => Mode::Absolute,
+        0x94 => Mode::DirectX,
+        _ => unreachable!("{:?}", core.info)
+    };
+
+    let address: u32 = address(core, mode);
+    let wide: bool = !x8(core);
+    let value: u16 = get_y(core);
+
+    write_data(core, address, value, wide);
+
+    next(core, mode)
+}
+
+pub fn cpx(core: &mut Core) -> &mut Core {
+    let mode: Mode = match core.ir {
+        0xE0 => Mode::ImmediateX,
+        0xE4 => Mode::Direct,
+        0xEC => Mode::Absolute,
+        _ => unreachable!("{:?}", core.info)
+    };
+
+    let address: u32 = address(core, mode);
+    let wide: bool = !x8(core);
+    let value: u16 = read_data(core, address, wide);
+    let x: u16 = get_x(core);
+
+    compare(core, x, value, wide);
+
+    next(core, mode)
+}
+
+pub fn cpy(core: &mut Core) -> &mut Core {
+    let mode: Mode = match core.ir {
+        0xC0 => Mode::ImmediateX,
+        0xC4 => Mode::Direct,
+        0xCC => Mode::Absolute,
+        _ => unreachable!("{:?}", core.info)
+    };
+
+    let address: u32 = address(core, mode);
+    let wide: bool = !x8(core);
+    let value: u16 = read_data(core, address, wide);
+    let y: u16 = get_y(core);
+
+    compare(core, y, value, wide);
+
+    next(core, mode)
+}
+
+pub fn inx(core: &mut Core) -> &mut Core {
+    let value: u16 = get_x(core).wrapping_add(1);
+
+    set_x(core, value);
+    zero_negative(core, value, !x8(core));
+
+    skip(core, 1)
+}
+
+pub fn iny(core: &mut Core) -> &mut Core {
+    let value: u16 = get_y(core).wrapping_add(1);
+
+    set_y(core, value);
+    zero_negative(core, value, !x8(core));
+
+    skip(core, 1)
+}
+
+pub fn dex(core: &mut Core) -> &mut Core {
+    let value: u16 = get_x(core).wrapping_sub(1);
+
+    set_x(core, value);
+    zero_negative(core, value, !x8(core));
+
+    skip(core, 1)
+}
+
+pub fn dey(core: &mut Core) -> &mut Core {
+    let value: u16 = get_y(core).wrapping_sub(1);
+
+    set_y(core, value);
+    zero_negative(core, value, !x8(core));
+
+    skip(core, 1)
+}
+
+// Shared by the conditional branches and BRA. Only emulation mode takes the
+// extra cycle for branching into another page.
+fn branch(core: &mut Core, condition: bool) -> &mut Core {
+    let offset: i8 = operand8(core, 1) as i8;
+    let next: u16 = core.pc.wrapping_add(2);
+
+    if condition {
+        let target: u16 = next.wrapping_add(offset as u16);
+
+        core.cycles += 1;
+
+        if core.emulation && (target & 0xFF00) != (next & 0xFF00) { core.cycles += 1 }
+
+        core.pc = target;
+    } else {
+        core.pc = next;
+    }
+
+    core
+}
+
+pub fn bpl(core: &mut Core) -> &mut Core {
+    let condition: bool = !core.stat.get(Flag::Negative);
+
+    branch(core, condition)
+}
+
+pub fn bmi(core: &mut Core) -> &mut Core {
+    let condition: bool = core.stat.get(Flag::Negative);
+
+    branch(core, condition)
+}
+
+pub fn bvc(core: &mut Core) -> &mut Core {
+    let condition: bool = !core.stat.get(Flag::Overflow);
+
+    branch(core, condition)
+}
+
+pub fn bvs(core: &mut Core) -> &mut Core {
+    let condition: bool = core.stat.get(Flag::Overflow);
+
+    branch(core, condition)
+}
+
+pub fn bcc(core: &mut Core) -> &mut Core {
+    let condition: bool = !core.stat.get(Flag::Carry);
+
+    branch(core, condition)
+}
+
+pub fn bcs(core: &mut Core) -> &mut Core {
+    let condition: bool = core.stat.get(Flag::Carry);
+
+    branch(core, condition)
+}
+
+pub fn bne(core: &mut Core) -> &mut Core {
+    let condition: bool = !core.stat.get(Flag::Zero);
+
+    branch(core, condition)
+}
+
+pub fn beq(core: &mut Core) -> &mut Core {
+    let condition: bool = core.stat.get(Flag::Zero);
+
+    branch(core, condition)
+}
+
+pub fn bra(core: &mut Core) -> &mut Core {
+    branch(core, true)
+}
+
+// Branch long, a 16-bit offset that can reach anywhere in the program bank.
+pub fn brl(core: &mut Core) -> &mut Core {
+    let offset: u16 = operand16(core);
+
+    core.pc = core.pc.wrapping_add(3).wrapping_add(offset);
+
+    core
+}
+
+pub fn jmp(core: &mut Core) -> &mut Core {
+    core.pc = match core.ir {
+        0x4C => operand16(core), // JMP abs
+        0x6C => { // JMP (abs)
+            let pointer: u16 = operand16(core);
+            read16_bank0(core, pointer)
+        }
+        0x7C => { // JMP (abs,X), the pointer is in the program bank
+            let pointer: u16 = operand16(core).wrapping_add(get_x(core));
+            let bank: u32 = (core.pbr as u32) << 16;
+
+            ((core.read_long(bank | pointer.wrapping_add(1) as u32) as u16) << 8)
+                | core.read_long(bank | pointer as u32) as u16
+        }
+        _ => unreachable!("{:?}", core.info)
+    };
+
+    core
+}
+
+// Long jumps also load the program bank.
+pub fn jml(core: &mut Core) -> &mut Core {
+    let target: u32 = match core.ir {
+        0x5C => operand24(core), // JML long
+        0xDC => { // JML [abs]
+            let pointer: u16 = operand16(core);
+            read24_bank0(core, pointer)
+        }
+        _ => unreachable!("{:?}", core.info)
+    };
+
+    core.pbr = (target >> 16) as u8;
+    core.pc = target as u16;
+
+    core
+}
+
+pub fn jsr(core: &mut Core) -> &mut Core {
+    let target: u16 = match core.ir {
+        0x20 => operand16(core), // JSR abs
+        0xFC => { // JSR (abs,X), the pointer is in the program bank
+            let pointer: u16 = operand16(core).wrapping_add(get_x(core));
+            let bank: u32 = (core.pbr as u32) << 16;
+
+            ((core.read_long(bank | pointer.wrapping_add(1) as u32) as u16) << 8)
+                | core.read_long(bank | pointer as u32) as u16
+        }
+        _ => unreachable!("{:?}", core.info)
+    };
+
+    // The return address pushed is the last byte of the instruction.
+    let return_address: u16 = core.pc.wrapping_add(2);
+
+    push16(core, return_address);
+
+    core.pc = target;
+
+    core
+}
+
+// Jump to subroutine long, pushing the program bank before the return address.
+pub fn jsl(core: &mut Core) -> &mut Core {
+    let target: u32 = operand24(core);
+    let return_address: u16 = core.pc.wrapping_add(3);
+    let bank: u8 = core.pbr;
+
+    push8(core, bank);
+    push16(core, return_address);
+
+    core.pbr = (target >> 16) as u8;
+    core.pc = target as u16;
+
+    core
+}
+
+pub fn rts(core: &mut Core) -> &mut Core {
+    core.pc = pull16(core).wrapping_add(1);
+
+    core
+}
+
+pub fn rtl(core: &mut Core) -> &mut Core {
+    core.pc = pull16(core).wrapping_add(1);
+    core.pbr = pull8(core);
+
+    core
+}
+
+// Native mode interrupts also push the program bank, which RTI pulls back.
+pub fn rti(core: &mut Core) -> &mut Core {
+    let status: u8 = pull8(core);
+
+    set_status(core, status);
+
+    core.pc = pull16(core);
+
+    if !core.emulation {
+        core.pbr = pull8(core);
+        core.cycles += 1;
+    }
+
+    core
+}
+
+// BRK and COP, both two bytes with a signature byte that is skipped over.
+fn software_interrupt(core: &mut Core, emulation_vector: u16, native_vector: u16) -> &mut Core {
+    let return_address: u16 = core.pc.wrapping_add(2);
+
+    let vector: u16 = if core.emulation {
+        // BRK pushes the break flag set, same as on the 6502.
+        let status: u8 = core.stat.pushed(core.ir == 0x00);
+
+        push16(core, return_address);
+        push8(core, status);
+
+        emulation_vector
+    } else {
+        let bank: u8 = core.pbr;
+        let status: u8 = core.stat.bits();
+
+        push8(core, bank);
+        push16(core, return_address);
+        push8(core, status);
+
+        core.cycles += 1;
+
+        native_vector
+    };
+
+    core.stat.set(Flag::Interrupt, true);
+    core.stat.set(Flag::Decimal, false);
+
+    core.pbr = 0;
+    core.pc = read16_bank0(core, vector);
+
+    core
+}
+
+pub fn brk(core: &mut Core) -> &mut Core {
+    software_interrupt(core, 0xFFFE, 0xFFE6)
+}
+
+pub fn cop(core: &mut Core) -> &mut Core {
+    software_interrupt(core, 0xFFF4, 0xFFE4)
+}
+
+// Hardware interrupt sequence, called by the system with the emulation
+// mode vector. The native mode vectors sit 0x10 lower.
+pub fn interrupt(core: &mut Core, vector: u16) {
+    let return_address: u16 = core.pc;
+
+    let vector: u16 = if core.emulation {
+        let status: u8 = core.stat.pushed(false);
+
+        push16(core, return_address);
+        push8(core, status);
+
+        vector
+    } else {
+        let bank: u8 = core.pbr;
+        let status: u8 = core.stat.bits();
+
+        push8(core, bank);
+        push16(core, return_address);
+        push8(core, status);
+
+        core.cycles += 1;
+
+        vector - 0x10
+    };
+
+    core.stat.set(Flag::Interrupt, true);
+    core.stat.set(Flag::Decimal, false);
+
+    core.pbr = 0;
+    core.pc = read16_bank0(core, vector);
+    core.cycles += 7;
+}
+
+// RESET drops back to emulation mode with the banks and direct page cleared.
+pub fn reset(core: &mut Core) {
+    core.emulation = true;
+    core.dp = 0;
+    core.dbr = 0;
+    core.pbr = 0;
+    core.sp_hi = 0x01;
+    core.ix_hi = 0;
+    core.iy_hi = 0;
+
+    // M and X only exist in native mode.
+    core.stat.set(Flag::Memory, false);
+    core.stat.set(Flag::Index, false);
+}
+
+pub fn pha(core: &mut Core) -> &mut Core {
+    let value: u16 = get_a(core);
+    let wide: bool = !m8(core);
+
+    push_data(core, value, wide);
+
+    skip(core, 1)
+}
+
+pub fn pla(core: &mut Core) -> &mut Core {
+    let wide: bool = !m8(core);
+    let value: u16 = pull_data(core, wide);
+
+    set_a(core, value);
+    zero_negative(core, value, wide);
+
+    skip(core, 1)
+}
+
+pub fn phx(core: &mut Core) -> &mut Core {
+    let value: u16 = get_x(core);
+    let wide: bool = !x8(core);
+
+    push_data(core, value, wide);
+
+    skip(core, 1)
+}
+
+pub fn plx(core: &mut Core) -> &mut Core {
+    let wide: bool = !x8(core);
+    let value: u16 = pull_data(core, wide);
+
+    set_x(core, value);
+    zero_negative(core, value, wide);
+
+    skip(core, 1)
+}
+
+pub fn phy(core: &mut Core) -> &mut Core {
+    let value: u16 = get_y(core);
+    let wide: bool = !x8(core);
+
+    push_data(core, value, wide);
+
+    skip(core, 1)
+}
+
+pub fn ply(core: &mut Core) -> &mut Core {
+    let wide: bool = !x8(core);
+    let value: u16 = pull_data(core, wide);
+
+    set_y(core, value);
+    zero_negative(core, value, wide);
+
+    skip(core, 1)
+}
+
+// In emulation mode the pushed copy has the break flag set, like the 6502.
+pub fn php(core: &mut Core) -> &mut Core {
+    let status: u8 = if core.emulation { core.stat.pushed(true) } else { core.stat.bits() };
+
+    push8(core, status);
+
+    skip(core, 1)
+}
+
+pub fn plp(core: &mut Core) -> &mut Core {
+    let status: u8 = pull8(core);
+
+    set_status(core, status);
+
+    skip(core, 1)
+}
+
+pub fn phb(core: &mut Core) -> &mut Core {
+    let bank: u8 = core.dbr;
+
+    push8(core, bank);
+
+    skip(core, 1)
+}
+
+pub fn plb(core: &mut Core) -> &mut Core {
+    core.dbr = pull8(core);
+
+    let bank: u16 = core.dbr as u16;
+    zero_negative(core, bank, false);
+
+    skip(core, 1)
+}
+
+pub fn phd(core: &mut Core) -> &mut Core {
+    let dp: u16 = core.dp;
+
+    push16(core, dp);
+
+    skip(core, 1)
+}
+
+pub fn pld(core: &mut Core) -> &mut Core {
+    core.dp = pull16(core);
+
+    let dp: u16 = core.dp;
+    zero_negative(core, dp, true);
+
+    skip(core, 1)
+}
+
+pub fn phk(core: &mut Core) -> &mut Core {
+    let bank: u8 = core.pbr;
+
+    push8(core, bank);
+
+    skip(core, 1)
+}
+
+// Push effective absolute address, really just a 16-bit immediate push.
+pub fn pea(core: &mut Core) -> &mut Core {
+    let value: u16 = operand16(core);
+
+    push16(core, value);
+
+    skip(core, 3)
+}
+
+// Push effective indirect address, the 16-bit pointer at a direct page location.
+pub fn pei(core: &mut Core) -> &mut Core {
+    let pointer: u32 = direct(core, 0);
+    let value: u16 = read16_bank0(core, pointer as u16);
+
+    push16(core, value);
+
+    skip(core, 2)
+}
+
+// Push effective relative address, PC relative like BRL.
+pub fn per(core: &mut Core) -> &mut Core {
+    let value: u16 = core.pc.wrapping_add(3).wrapping_add(operand16(core));
+
+    push16(core, value);
+
+    skip(core, 3)
+}
+
+// TAX and TAY copy the full 16-bit accumulator when X is clear, whatever M is.
+pub fn tax(core: &mut Core) -> &mut Core {
+    let value: u16 = get_c(core);
+
+    set_x(core, value);
+
+    let x: u16 = get_x(core);
+    zero_negative(core, x, !x8(core));
+
+    skip(core, 1)
+}
+
+pub fn tay(core: &mut Core) -> &mut Core {
+    let value: u16 = get_c(core);
+
+    set_y(core, value);
+
+    let y: u16 = get_y(core);
+    zero_negative(core, y, !x8(core));
+
+    skip(core, 1)
+}
+
+pub fn txa(core: &mut Core) -> &mut Core {
+    let value: u16 = get_x(core);
+
+    set_a(core, value);
+    zero_negative(core, value, !m8(core));
+
+    skip(core, 1)
+}
+
+pub fn tya(core: &mut Core) -> &mut Core {
+    let value: u16 = get_y(core);
+
+    set_a(core, value);
+    zero_negative(core, value, !m8(core));
+
+    skip(core, 1)
+}
+
+pub fn txy(core: &mut Core) -> &mut Core {
+    let value: u16 = get_x(core);
+
+    set_y(core, value);
+    zero_negative(core, value, !x8(core));
+
+    skip(core, 1)
+}
+
+pub fn tyx(core: &mut Core) -> &mut Core {
+    let value: u16 = get_y(core);
+
+    set_x(core, value);
+    zero_negative(core, value, !x8(core));
+
+    skip(core, 1)
+}
+
+pub fn tsx(core: &mut Core) -> &mut Core {
+    let value: u16 = get_s(core);
+
+    set_x(core, value);
+
+    let x: u16 = get_x(core);
+    zero_negative(core, x, !x8(core));
+
+    skip(core, 1)
+}
+
+pub fn txs(core: &mut Core) -> &mut Core {
+    let value: u16 = get_x(core);
+
+    set_s(core, value);
+
+    skip(core, 1)
+}
+
+pub fn tcd(core: &mut Core) -> &mut Core {
+    core.dp = get_c(core);
+
+    let dp: u16 = core.dp;
+    zero_negative(core, dp, true);
+
+    skip(core, 1)
+}
+
+pub fn tdc(core: &mut Core) -> &mut Core {
+    let dp: u16 = core.dp;
+
+    set_c(core, dp);
+    zero_negative(core, dp, true);
+
+    skip(core, 1)
+}
+
+pub fn tcs(core: &mut Core) -> &mut Core {
+    let value: u16 = get_c(core);
+
+    set_s(core, value);
+
+    skip(core, 1)
+}
+
+pub fn tsc(core: &mut Core) -> &mut Core {
+    let value: u16 = get_s(core);
+
+    set_c(core, value);
+    zero_negative(core, value, true);
+
+    skip(core, 1)
+}
+
+// Swap the two halves of the accumulator. Flags follow the new low byte.
+pub fn xba(core: &mut Core) -> &mut Core {
+    std::mem::swap(&mut core.acc, &mut core.acc_hi);
+
+    let a: u16 = core.acc as u16;
+    zero_negative(core, a, false);
+
+    skip(core, 1)
+}
+
+// Exchange carry and emulation flags, the only way in or out of native mode.
+pub fn xce(core: &mut Core) -> &mut Core {
+    let carry: bool = core.stat.get(Flag::Carry);
+
+    core.stat.set(Flag::Carry, core.emulation);
+    core.emulation = carry;
+
+    if core.emulation {
+        // Back to 6502 widths, the high bytes of X, Y and S are lost.
+        core.stat.set(Flag::Memory, false);
+        core.stat.set(Flag::Index, false);
+        core.ix_hi = 0;
+        core.iy_hi = 0;
+        core.sp_hi = 0x01;
+    } else {
+        // Native mode starts out with 8-bit registers.
+        core.stat.set(Flag::Memory, true);
+        core.stat.set(Flag::Index, true);
+    }
+
+    skip(core, 1)
+}
+
+pub fn rep(core: &mut Core) -> &mut Core {
+    let status: u8 = core.stat.bits() & !operand8(core, 1);
+
+    set_status(core, status);
+
+    skip(core, 2)
+}
+
+pub fn sep(core: &mut Core) -> &mut Core {
+    let status: u8 = core.stat.bits() | operand8(core, 1);
+
+    set_status(core, status);
+
+    skip(core, 2)
+}
+
+// Block moves copy one byte each time they run, and stay on the same
+// instruction until the count in C runs out. That keeps them interruptible.
+fn block_move(core: &mut Core, step: u16) -> &mut Core {
+    let destination: u8 = operand8(core, 1);
+    let source: u8 = operand8(core, 2);
+
+    core.dbr = destination;
+
+    let x: u16 = get_x(core);
+    let y: u16 = get_y(core);
+
+    let value: u8 = core.read_long(((source as u32) << 16) | x as u32);
+    core.write_long(((destination as u32) << 16) | y as u32, value);
+
+    set_x(core, x.wrapping_add(step));
+    set_y(core, y.wrapping_add(step));
+
+    let count: u16 = get_c(core).wrapping_sub(1);
+    set_c(core, count);
+
+    if count == 0xFFFF { core.pc = core.pc.wrapping_add(3) }
+
+    core
+}
+
+// Block move previous, X and Y count down.
+pub fn mvp(core: &mut Core) -> &mut Core {
+    block_move(core, 0xFFFF)
+}
+
+// Block move next, X and Y count up.
+pub fn mvn(core: &mut Core) -> &mut Core {
+    block_move(core, 0x0001)
+}
+
+// Reserved for future expansion, a two byte NOP.
+pub fn wdm(core: &mut Core) -> &mut Core {
+    skip(core, 2)
+}